@@ -1,69 +1,599 @@
 //use failure::*;
 use log::*;
 use std::error::Error;
+use std::io::{self, Write};
+use std::net::SocketAddr;
 use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::SystemTime;
 use structopt::StructOpt;
-use tokio::io::AsyncRead;
-use tokio_stream::{Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::UdpSocket;
+use tokio::sync::{broadcast, watch};
+use tokio_stream::StreamExt;
 use tokio_util::codec::FramedRead;
 
-use fishfinder::adsb;
-use fishfinder::sdr::{dsp, mode_s, rtl};
+use fishfinder::config::Config;
+use fishfinder::sdr::input::{self, Band, RadioSpec};
+use fishfinder::sdr::lines::{self, LineFormat};
+use fishfinder::sdr::{playback, repair, rtl, stats, uat};
+use fishfinder::{adsb, ahrs, baro, fisb, gdl90, gps, metrics};
+
+/* FIS-B uplinks waiting to be sent as GDL90, a ground station sends a few a second */
+const UPLINK_CAPACITY: usize = 64;
+
+/* IQ samples are written as they come, one buffer of the radio at a time */
+const RECORD_BUFFER_SIZE: usize = rtl::RTL_SDR_BUFFER_SIZE;
 
 #[derive(StructOpt)]
 #[structopt(name = "fishfinder", about = "ads-b tracker for rtl-sdr")]
 struct Cli {
-    #[structopt(short, long)]
-    path: Option<String>,
+    /// TOML configuration file, command line flags override it
+    #[structopt(short, long, global = true)]
+    config: Option<String>,
+
+    /// Log level: off, error, warn, info, debug or trace (RUST_LOG filters still apply)
+    #[structopt(long, global = true)]
+    log_level: Option<LevelFilter>,
+
+    #[structopt(subcommand)]
+    command: Command,
 }
 
-fn create_stream<T: 'static + AsyncRead + Sized>(
-    iq_sample_src: T,
-) -> Pin<Box<dyn Stream<Item = adsb::Message>>> {
-    let magnitude_src = dsp::IQMagnitudeReader::new(iq_sample_src);
+#[derive(StructOpt)]
+enum Command {
+    /// List attached RTL-SDRs with their serial numbers and tuner gains
+    Devices,
 
-    let mode_s_frame_stream = FramedRead::with_capacity(
-        magnitude_src,
-        mode_s::FrameDecoder::new(),
-        rtl::RTL_SDR_BUFFER_SIZE,
-    );
+    /// Capture 8 bit IQ samples from a radio into a file
+    Record {
+        /// File to write the samples to
+        output: String,
 
-    let adsb_stream = mode_s_frame_stream
-        .filter_map(|f| f.ok())
-        .filter_map(|frame| match frame.valid() {
-            true => Some(frame),
-            false => frame.try_repair(),
-        })
-        .filter_map(|f| f.parse());
+        /// Radio to record with, `<band>[:<serial>[:<ppm>]]`, the first configured one if not given
+        #[structopt(long)]
+        radio: Option<RadioSpec>,
+
+        /// Stop after this many seconds instead of on Ctrl-C
+        #[structopt(long)]
+        duration: Option<f64>,
+    },
+
+    /// Decode a recording, `[<band>:]<file>`, into AVR or JSON lines on stdout
+    Decode {
+        recording: Recording,
+
+        /// Output format: avr or json
+        #[structopt(long, default_value = "avr")]
+        format: LineFormat,
+
+        /// Error correction policy: off, 1bit (DF17/18) or 2bit (DF17 from known aircraft) [default: 1bit]
+        #[structopt(long)]
+        repair: Option<repair::RepairPolicy>,
+    },
+
+    /// Run recordings, `[<band>:]<file>`, through the full stack paced like live reception
+    Replay {
+        #[structopt(required = true)]
+        recordings: Vec<Recording>,
+
+        /// Pace playback at the recorded sample rate times this multiplier (e.g. 0.5, 2, 10)
+        #[structopt(short, long, default_value = "1")]
+        speed: f64,
 
-    return Box::pin(adsb_stream);
+        /// Loop playback
+        #[structopt(long = "loop")]
+        looping: bool,
+
+        #[structopt(flatten)]
+        stack: StackArgs,
+    },
+
+    /// Run the full live stack: radios, sensors and outputs
+    Serve {
+        /// RTL-SDR to receive with, `<band>[:<serial>[:<ppm>]]` with band 1090 or 978, repeat for
+        /// dual-band operation (e.g. --radio 1090:00001090 --radio 978:00000978)
+        #[structopt(long)]
+        radio: Vec<RadioSpec>,
+
+        #[structopt(flatten)]
+        stack: StackArgs,
+    },
+
+    /// Count what's in a recording, `[<band>:]<file>`: frames by type, errors, aircraft and signal
+    Stats { recording: Recording },
+
+    /// Print what ForeFlight and other EFBs broadcast when looking for GDL90
+    ForeflightDiscover {
+        /// Address to listen on
+        #[structopt(long, default_value = "0.0.0.0:63093")]
+        listen: SocketAddr,
+    },
 }
 
+/* Options of the full stack, `serve` and `replay` */
+#[derive(StructOpt)]
+struct StackArgs {
+    /// Receiver location as lat,lon[,alt_ft], enables range filtering and local CPR decoding
+    #[structopt(long)]
+    location: Option<adsb::ReceiverLocation>,
+
+    /// Reject positions further than this from the receiver location (NM) [default: 180]
+    #[structopt(long)]
+    max_range: Option<f64>,
+
+    /// GPS for ownship position: serial device, tcp://host:port or NMEA/UBX log file
+    #[structopt(long)]
+    gps: Option<String>,
+
+    /// Baud rate of a serial GPS [default: 9600]
+    #[structopt(long)]
+    gps_baud: Option<u32>,
+
+    /// Barometer for ownship pressure altitude: i2c bus with a BMP280/BMP388 or a replay file
+    #[structopt(long)]
+    baro: Option<String>,
+
+    /// IMU for attitude: i2c bus with an MPU-9250/ICM-20948 (kept still on startup) or a replay file
+    #[structopt(long)]
+    imu: Option<String>,
+
+    /// ICAO address (hex) of our own transponder, detected from the GPS if not given
+    #[structopt(long, parse(try_from_str = parse_icao))]
+    ownship: Option<u32>,
+
+    /// Send GDL90 to this address, e.g. 192.168.1.255:4000 or an EFB's address, repeat for more
+    #[structopt(long)]
+    gdl90: Vec<SocketAddr>,
+
+    /// Error correction policy: off, 1bit (DF17/18) or 2bit (DF17 from known aircraft) [default: 1bit]
+    #[structopt(long)]
+    repair: Option<repair::RepairPolicy>,
+
+    /// Serve Prometheus metrics on this port at /metrics
+    #[structopt(long)]
+    metrics: Option<u16>,
+}
+
+impl StackArgs {
+    /* Flags given on the command line win over the config file */
+    fn apply(&self, config: &mut Config) {
+        if let Some(location) = self.location {
+            config.set_location(location);
+        }
+        if self.max_range.is_some() {
+            config.receiver.max_range = self.max_range;
+        }
+        if self.ownship.is_some() {
+            config.tracker.ownship = self.ownship;
+        }
+        if self.repair.is_some() {
+            config.tracker.repair = self.repair;
+        }
+        if self.gps.is_some() {
+            config.sensors.gps = self.gps.clone();
+        }
+        if self.gps_baud.is_some() {
+            config.sensors.gps_baud = self.gps_baud;
+        }
+        if self.baro.is_some() {
+            config.sensors.baro = self.baro.clone();
+        }
+        if self.imu.is_some() {
+            config.sensors.imu = self.imu.clone();
+        }
+        if !self.gdl90.is_empty() {
+            config.outputs.gdl90 = self.gdl90.clone();
+        }
+        if self.metrics.is_some() {
+            config.outputs.metrics = self.metrics;
+        }
+    }
+}
+
+/* The config file if there is one, with the flags applied */
+fn load_config(args: &Cli) -> io::Result<Config> {
+    let mut config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    if args.log_level.is_some() {
+        config.log_level = args.log_level;
+    }
+    match &args.command {
+        Command::Serve { radio, stack } => {
+            if !radio.is_empty() {
+                config.radios = radio.clone();
+            }
+            stack.apply(&mut config);
+        }
+        Command::Replay { stack, .. } => stack.apply(&mut config),
+        Command::Decode {
+            repair: Some(repair),
+            ..
+        } => config.tracker.repair = Some(*repair),
+        _ => {}
+    }
+    config.validate()?;
+
+    Ok(config)
+}
+
+/// An IQ recording, 1090 MHz unless the band is given
+struct Recording {
+    band: Band,
+    path: String,
+}
+
+impl FromStr for Recording {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((band, path)) if band.parse::<Band>().is_ok() => Ok(Recording {
+                band: band.parse()?,
+                path: path.to_string(),
+            }),
+            _ => Ok(Recording {
+                band: Band::ModeS,
+                path: s.to_string(),
+            }),
+        }
+    }
+}
+
+fn parse_icao(s: &str) -> Result<u32, std::num::ParseIntError> {
+    u32::from_str_radix(s, 16)
+}
+
+type IqSource = Pin<Box<dyn AsyncRead + Send>>;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    pretty_env_logger::init();
-    log::set_max_level(LevelFilter::Trace);
     let args = Cli::from_args();
-
-    let mut stream = match args.path {
-        Some(path) => create_stream(tokio::fs::File::open(path).await?),
-        //_ => panic!("panik"),
-        _ => create_stream(rtl::Radio::open(rtl::RadioConfig::mode_s(0))),
+    let config = match load_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("fishfinder: {}", e);
+            std::process::exit(2);
+        }
     };
 
-    let mut tracker = adsb::Tracker::new();
-    let mut frame_count = 0u32;
+    let mut logger = pretty_env_logger::formatted_builder();
+    logger.filter_level(config.log_level.unwrap_or(LevelFilter::Info));
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        logger.parse_filters(&filters);
+    }
+    logger.init();
 
-    while let Some(frame) = stream.next().await {
-        info!("got frame: {:#?}", frame);
+    match args.command {
+        Command::Devices => devices(),
+        Command::Record {
+            output,
+            radio,
+            duration,
+        } => record(&config, radio, &output, duration).await?,
+        Command::Decode {
+            recording, format, ..
+        } => match decode(&config, &recording, format).await {
+            // piped into head or the like
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+            result => result?,
+        },
+        Command::Replay {
+            recordings,
+            speed,
+            looping,
+            ..
+        } => {
+            let mut inputs: Vec<(Band, IqSource)> = Vec::new();
+            for recording in &recordings {
+                let file = tokio::fs::File::open(&recording.path).await?;
+                let playback = playback::Playback::new(file, recording.band.sample_rate())
+                    .speed(speed)
+                    .looping(looping);
+                inputs.push((recording.band, Box::pin(playback)));
+            }
+            serve(config, inputs).await?
+        }
+        Command::Serve { .. } => {
+            let radios = match config.radios.is_empty() {
+                true => vec![RadioSpec::new(Band::ModeS)],
+                false => config.radios.clone(),
+            };
+            let mut inputs: Vec<(Band, IqSource)> = Vec::new();
+            // dongles without a serial number are taken in order
+            for (device_index, radio) in radios.iter().enumerate() {
+                let radio_config = radio.config(device_index as u8)?;
+                inputs.push((radio.band, Box::pin(rtl::Radio::open(radio_config)?)));
+            }
+            serve(config, inputs).await?
+        }
+        Command::Stats { recording } => {
+            let file = tokio::fs::File::open(&recording.path).await?;
+            let samples = file.metadata().await?.len() / 2;
+            let mut stats = stats::analyse(file, recording.band).await;
+            stats.samples = Some(samples);
+            print!("{}", stats);
+        }
+        Command::ForeflightDiscover { listen } => foreflight_discover(listen).await?,
+    }
 
-        //        tracker.process(&frame);
-        //       tracker.print();
-        frame_count += 1;
-        info!("total frames recvd: {}", frame_count);
+    Ok(())
+}
+
+fn devices() {
+    let devices = rtl::devices();
+    if devices.is_empty() {
+        println!("no rtl-sdr found");
     }
 
+    for device in devices {
+        let gains = match rtl::tuner_gains(device.index) {
+            Ok(gains) => {
+                let gains: Vec<String> = gains.iter().map(|gain| format!("{:.1}", gain)).collect();
+                format!("{} dB", gains.join(" "))
+            }
+            Err(_) => "unknown, in use".to_string(),
+        };
+        println!(
+            "{}: {} {}, serial {}, gains {}",
+            device.index, device.manufacturer, device.product, device.serial, gains
+        );
+    }
+}
+
+async fn record(
+    config: &Config,
+    radio: Option<RadioSpec>,
+    output: &str,
+    duration: Option<f64>,
+) -> io::Result<()> {
+    let radio = radio
+        .or_else(|| config.radios.first().cloned())
+        .unwrap_or_else(|| RadioSpec::new(Band::ModeS));
+    let radio_config = radio.config(0)?;
+    let sample_rate = radio_config.sample_rate();
+
+    let limit = duration.map_or(u64::MAX, |seconds| {
+        (seconds * sample_rate as f64) as u64 * 2
+    });
+    let mut src = rtl::Radio::open(radio_config)?.take(limit);
+    let mut file = tokio::fs::File::create(output).await?;
+    info!("recording {} MHz to {}", radio.band, output);
+
+    let mut buf = vec![0; RECORD_BUFFER_SIZE];
+    let mut recorded = 0u64;
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let n = tokio::select! {
+            n = src.read(&mut buf) => n?,
+            _ = &mut ctrl_c => break,
+        };
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n]).await?;
+        recorded += n as u64;
+    }
+    file.flush().await?;
+
+    info!(
+        "recorded {} samples, {:.1}s",
+        recorded / 2,
+        recorded as f64 / 2.0 / sample_rate as f64
+    );
+    Ok(())
+}
+
+async fn decode(config: &Config, recording: &Recording, format: LineFormat) -> io::Result<()> {
+    let file = tokio::fs::File::open(&recording.path).await?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    match recording.band {
+        Band::ModeS => {
+            let mut frames = input::mode_s_frames(file, config.repair_policy());
+            while let Some(frame) = frames.next().await {
+                writeln!(out, "{}", lines::mode_s(&frame, format))?;
+            }
+        }
+        Band::Uat => {
+            let mut frames =
+                FramedRead::with_capacity(file, uat::FrameDecoder::new(), rtl::RTL_SDR_BUFFER_SIZE)
+                    .filter_map(|f| f.ok());
+            while let Some(frame) = frames.next().await {
+                writeln!(out, "{}", lines::uat(&frame, format))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn foreflight_discover(listen: SocketAddr) -> io::Result<()> {
+    let socket = UdpSocket::bind(listen).await?;
+    println!("listening on {}", socket.local_addr()?);
+
+    let mut buf = vec![0; 1024];
+    loop {
+        let (len, from) = socket.recv_from(&mut buf).await?;
+        match gdl90::discovery::parse(&buf[..len]) {
+            Some(discovery) => println!(
+                "{} at {} wants GDL90 on port {}",
+                discovery.app,
+                from.ip(),
+                discovery.port
+            ),
+            None => println!(
+                "{}[recv:{}] => {}",
+                from,
+                len,
+                String::from_utf8_lossy(&buf[..len])
+            ),
+        }
+    }
+}
+
+/* Everything live: the tracker fed by all inputs, sensors, GDL90 and weather */
+async fn serve(config: Config, inputs: Vec<(Band, IqSource)>) -> Result<(), Box<dyn Error>> {
+    let outputs = &config.outputs;
+    for (name, port) in &[
+        ("beast", outputs.beast),
+        ("sbs", outputs.sbs),
+        ("json", outputs.json),
+    ] {
+        if let Some(port) = port {
+            warn!(
+                "{} output isn't implemented yet, port {} is reserved",
+                name, port
+            );
+        }
+    }
+
+    let tracker = adsb::SharedTracker::new(adsb::Tracker::with_config(config.tracker_config()));
+
+    let (ownship_tx, ownship) = watch::channel(gps::Ownship::default());
+    let ownship_tx = Arc::new(ownship_tx);
+    if let Some(source) = &config.sensors.gps {
+        let baud = config.sensors.gps_baud.unwrap_or(gps::GPS_DEFAULT_BAUD);
+        let src = gps::open(source, baud).await?;
+        let ownship_tx = ownship_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = gps::run(src, ownship_tx).await {
+                error!("gps: {}", e);
+            }
+        });
+    }
+
+    if let Some(source) = &config.sensors.baro {
+        let sensor = baro::open(source)?;
+        let ownship_tx = ownship_tx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = baro::run(sensor, ownship_tx).await {
+                error!("baro: {}", e);
+            }
+        });
+    }
+    drop(ownship_tx);
+
+    let (attitude_tx, attitude) = watch::channel(ahrs::Attitude::default());
+    if let Some(source) = &config.sensors.imu {
+        let imu = ahrs::open(source)?;
+        let ownship = ownship.clone();
+        tokio::spawn(async move {
+            if let Err(e) = ahrs::run(imu, ownship, attitude_tx).await {
+                error!("ahrs: {}", e);
+            }
+        });
+    }
+
+    // without a fixed location the receiver is wherever the GPS is
+    let follow = config.location().is_none();
+    let follower = tracker.clone();
+    let mut updates = ownship.clone();
+    tokio::spawn(async move {
+        while updates.changed().await.is_ok() {
+            let state = updates.borrow().clone();
+            follower.set_ownship_fix(state.fix(SystemTime::now()));
+            let location = state.location();
+            if follow && location.is_some() {
+                follower.set_receiver(location);
+            }
+        }
+    });
+
+    let (uplink_tx, _) = broadcast::channel(UPLINK_CAPACITY);
+    for &target in &config.outputs.gdl90 {
+        let tracker = tracker.clone();
+        let (ownship, attitude) = (ownship.clone(), attitude.clone());
+        let uplinks = uplink_tx.subscribe();
+        tokio::spawn(async move {
+            if let Err(e) = gdl90::run(tracker, ownship, attitude, uplinks, target).await {
+                error!("gdl90: {}", e);
+            }
+        });
+    }
+
+    if let Some(port) = config.outputs.metrics {
+        let tracker = tracker.clone();
+        tokio::spawn(async move {
+            let addr = SocketAddr::from(([0, 0, 0, 0], port));
+            if let Err(e) = metrics::run(tracker, addr).await {
+                error!("metrics: {}", e);
+            }
+        });
+    }
+    if let Some(interval) = config.summary_interval() {
+        tokio::spawn(metrics::log_summaries(tracker.clone(), interval));
+    }
+
+    let mut events = tracker.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(adsb::TrackerEvent::Emergency(alert)) => {
+                    warn!("alert: {} {:?}", alert.address, alert.kind)
+                }
+                Ok(event) => debug!("tracker: {:?}", event),
+                Err(broadcast::error::RecvError::Lagged(n)) => warn!("missed {} events", n),
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    // expire on a timer too, a quiet sky would otherwise never lose anyone
+    tokio::spawn(tracker.clone().expire_every(adsb::EXPIRE_INTERVAL));
+
+    // FIS-B weather is only cached so far, no output serves it yet, its size shows in the metrics
+    let mut uplinks = uplink_tx.subscribe();
+    tokio::spawn(async move {
+        let mut weather = fisb::WeatherCache::new();
+        loop {
+            match uplinks.recv().await {
+                Ok(frame) => {
+                    if let Some(uplink) = fisb::decode(frame.bytes()) {
+                        let at = frame.received_at();
+                        weather.expire(at);
+                        let products = weather.process(&uplink, at);
+                        metrics::Metrics::set(&metrics::METRICS.fisb_cached, weather.len() as u64);
+                        debug!("fisb: {} products, {} cached", products, weather.len());
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => warn!("missed {} uplinks", n),
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
+    // every input decodes on its own task, all into the same tracker
+    let repair_policy = config.repair_policy();
+    let handles: Vec<_> = inputs
+        .into_iter()
+        .map(|(band, src)| {
+            tokio::spawn(input::run(
+                src,
+                band,
+                tracker.clone(),
+                repair_policy,
+                uplink_tx.clone(),
+            ))
+        })
+        .collect();
+    drop(uplink_tx);
+
+    let mut frame_count = 0;
+    for handle in handles {
+        frame_count += handle.await?;
+    }
+    info!("total frames recvd: {}", frame_count);
+
     trace!("stream ended");
+    if tracker.receiver().is_some() {
+        info!("coverage:\n{}", tracker.coverage());
+    }
 
     Ok(())
 }
@@ -1,50 +0,0 @@
-#![warn(rust_2018_idioms)]
-
-use std::error::Error;
-use std::net::SocketAddr;
-use std::{env, io};
-use tokio::net::UdpSocket;
-
-struct Server {
-    socket: UdpSocket,
-    buf: Vec<u8>,
-}
-
-impl Server {
-    async fn run(self) -> Result<(), io::Error> {
-        let Server { socket, mut buf } = self;
-
-        loop {
-            let (len, addr) = socket.recv_from(&mut buf).await?;
-            println!(
-                "{}[recv:{}] => {}",
-                addr,
-                len,
-                std::str::from_utf8(&buf).unwrap()
-            );
-        }
-    }
-}
-
-struct GDL90Client {}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let addr = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "0.0.0.0:63093".to_string());
-
-    let socket = UdpSocket::bind(&addr).await?;
-    println!("Listening on: {}", socket.local_addr()?);
-
-    let server = Server {
-        socket,
-        buf: vec![0; 1024],
-    };
-
-    // This starts the server task.
-    server.run().await?;
-
-    Ok(())
-}
-
@@ -0,0 +1,123 @@
+/* Compact Position Reporting.
+ *
+ * Positions are transmitted as 17 bit fractions of a latitude zone and a
+ * longitude zone. Even and odd frames use a different number of latitude
+ * zones (60 and 59), one of each received within a few seconds of each
+ * other pins down the zones and therefore the global position.
+ */
+
+/// 2^17, the resolution of airborne CPR coordinates
+pub const CPR_MAX: f64 = 131072.0;
+
+// number of latitude zones between the equator and a pole
+const NZ: f64 = 15.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CprFrame {
+    pub lat: u32,
+    pub lon: u32,
+    pub odd: bool,
+}
+
+/// Number of longitude zones at a given latitude
+pub fn nl(lat: f64) -> u32 {
+    let lat = lat.abs();
+    if lat == 0.0 {
+        return 59;
+    } else if lat == 87.0 {
+        return 2;
+    } else if lat > 87.0 {
+        return 1;
+    }
+
+    let a = 1.0 - (std::f64::consts::PI / (2.0 * NZ)).cos();
+    let b = (std::f64::consts::PI / 180.0 * lat).cos().powi(2);
+    (2.0 * std::f64::consts::PI / (1.0 - a / b).acos()).floor() as u32
+}
+
+// always positive modulo
+fn modulo(a: f64, b: f64) -> f64 {
+    let r = a % b;
+    if r < 0.0 {
+        r + b
+    } else {
+        r
+    }
+}
+
+/// Decode the global position of an airborne aircraft from an even and an odd frame.
+/// The result is in the frame of whichever was received last (`odd_is_newest`).
+/// Returns None if the two frames straddle a latitude zone boundary.
+pub fn global_airborne(even: &CprFrame, odd: &CprFrame, odd_is_newest: bool) -> Option<(f64, f64)> {
+    let dlat_even = 360.0 / (4.0 * NZ);
+    let dlat_odd = 360.0 / (4.0 * NZ - 1.0);
+
+    let lat_even = even.lat as f64 / CPR_MAX;
+    let lat_odd = odd.lat as f64 / CPR_MAX;
+    let lon_even = even.lon as f64 / CPR_MAX;
+    let lon_odd = odd.lon as f64 / CPR_MAX;
+
+    // latitude zone index
+    let j = (59.0 * lat_even - 60.0 * lat_odd + 0.5).floor();
+
+    let mut rlat_even = dlat_even * (modulo(j, 60.0) + lat_even);
+    let mut rlat_odd = dlat_odd * (modulo(j, 59.0) + lat_odd);
+    if rlat_even >= 270.0 {
+        rlat_even -= 360.0;
+    }
+    if rlat_odd >= 270.0 {
+        rlat_odd -= 360.0;
+    }
+
+    if rlat_even.abs() > 90.0 || rlat_odd.abs() > 90.0 {
+        return None;
+    }
+
+    // both frames have to be from the same longitude zone
+    if nl(rlat_even) != nl(rlat_odd) {
+        return None;
+    }
+
+    let (rlat, nl_lat, lon) = match odd_is_newest {
+        true => (rlat_odd, nl(rlat_odd), lon_odd),
+        false => (rlat_even, nl(rlat_even), lon_even),
+    };
+
+    let ni = match odd_is_newest {
+        true => std::cmp::max(nl_lat as i32 - 1, 1),
+        false => std::cmp::max(nl_lat as i32, 1),
+    } as f64;
+    let m = (lon_even * (nl_lat as f64 - 1.0) - lon_odd * nl_lat as f64 + 0.5).floor();
+
+    let mut rlon = (360.0 / ni) * (modulo(m, ni) + lon);
+    if rlon >= 180.0 {
+        rlon -= 360.0;
+    }
+
+    Some((rlat, rlon))
+}
+
+/// Decode a single frame relative to a reference position, which has to be
+/// within half a zone of the aircraft (180 NM airborne, 45 NM on the surface).
+pub fn local(frame: &CprFrame, reference: (f64, f64), surface: bool) -> (f64, f64) {
+    // surface positions have 4 times the resolution over a quarter of the range
+    let span = if surface { 90.0 } else { 360.0 };
+    let i = if frame.odd { 1.0 } else { 0.0 };
+    let (ref_lat, ref_lon) = reference;
+
+    let lat = frame.lat as f64 / CPR_MAX;
+    let lon = frame.lon as f64 / CPR_MAX;
+
+    let dlat = span / (4.0 * NZ - i);
+    let j = (ref_lat / dlat).floor() + (0.5 + modulo(ref_lat, dlat) / dlat - lat).floor();
+    let rlat = dlat * (j + lat);
+
+    let dlon = span / (nl(rlat) as f64 - i).max(1.0);
+    let m = (ref_lon / dlon).floor() + (0.5 + modulo(ref_lon, dlon) / dlon - lon).floor();
+    let mut rlon = dlon * (m + lon);
+    if rlon >= 180.0 {
+        rlon -= 360.0;
+    }
+
+    (rlat, rlon)
+}
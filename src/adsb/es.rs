@@ -0,0 +1,401 @@
+/* Extended squitter (DF17/18) ME field decoding.
+ *
+ * The 56 bit ME field starts with a 5 bit type code selecting the format
+ * of the rest. Bit numbers in here are 1-based and inclusive like in the
+ * specification (DO-260B), bit 1 being the MSB of the type code.
+ */
+
+use super::cpr::CprFrame;
+use super::gillham;
+
+const CALLSIGN_CHARSET: &[u8; 64] =
+    b"#ABCDEFGHIJKLMNOPQRSTUVWXYZ##### ###############0123456789######";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EsMessage {
+    /// TC 1-4
+    Identification {
+        /// Emitter category set (A-D) and category as 0xA0..0xD7
+        category: u8,
+        callsign: String,
+    },
+    /// TC 5-8
+    SurfacePosition {
+        type_code: u8,
+        ground_speed: Option<f64>,
+        track: Option<f64>,
+        cpr: CprFrame,
+    },
+    /// TC 9-18 (barometric altitude) and 20-22 (GNSS height)
+    AirbornePosition {
+        type_code: u8,
+        altitude: Option<i32>,
+        gnss: bool,
+        surveillance_status: u8,
+        nic_supplement_b: bool,
+        cpr: CprFrame,
+    },
+    /// TC 19
+    AirborneVelocity(Velocity),
+    /// TC 28 subtype 1
+    AircraftStatus {
+        emergency: EmergencyState,
+        /// Mode A code in 0xABCD form
+        squawk: u16,
+    },
+    /// TC 29, only the version 2 format (subtype 1)
+    TargetState(TargetState),
+    /// TC 31
+    OperationalStatus(OperationalStatus),
+    Unknown {
+        type_code: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Velocity {
+    /// Ground speed (kt) and track (degrees) for subtypes 1 and 2
+    pub ground_speed: Option<f64>,
+    pub track: Option<f64>,
+    /// Magnetic heading (degrees) and airspeed (kt) for subtypes 3 and 4
+    pub heading: Option<f64>,
+    pub airspeed: Option<u32>,
+    pub airspeed_is_true: bool,
+    /// Vertical rate in ft/min
+    pub vertical_rate: Option<i32>,
+    pub vertical_rate_is_gnss: bool,
+    /// GNSS height minus barometric altitude in ft
+    pub gnss_baro_diff: Option<i32>,
+    /// Navigation accuracy category for velocity (version 1 and up)
+    pub nac_v: u8,
+}
+
+/// Emergency/priority status of an aircraft
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmergencyState {
+    None,
+    General,
+    Medical,
+    MinimumFuel,
+    NoCommunications,
+    UnlawfulInterference,
+    DownedAircraft,
+    Reserved,
+}
+
+impl EmergencyState {
+    pub(crate) fn from_code(code: u32) -> EmergencyState {
+        match code {
+            0 => EmergencyState::None,
+            1 => EmergencyState::General,
+            2 => EmergencyState::Medical,
+            3 => EmergencyState::MinimumFuel,
+            4 => EmergencyState::NoCommunications,
+            5 => EmergencyState::UnlawfulInterference,
+            6 => EmergencyState::DownedAircraft,
+            _ => EmergencyState::Reserved,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TargetState {
+    /// Selected altitude in ft, and whether it's from the FMS instead of the MCP/FCU
+    pub selected_altitude: Option<i32>,
+    pub selected_altitude_fms: bool,
+    /// Barometric pressure setting in hPa
+    pub baro_setting: Option<f64>,
+    /// Selected heading in degrees
+    pub selected_heading: Option<f64>,
+    pub nac_p: u8,
+    pub nic_baro: bool,
+    pub sil: u8,
+    pub sil_per_sample: bool,
+    /// Autopilot modes, if the aircraft reports them
+    pub autopilot: Option<bool>,
+    pub vnav: Option<bool>,
+    pub altitude_hold: Option<bool>,
+    pub approach: Option<bool>,
+    pub lnav: Option<bool>,
+    pub tcas_operational: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OperationalStatus {
+    /// Airborne (subtype 0) or surface (subtype 1) format
+    pub airborne: bool,
+    /// ADS-B version, 0 (DO-260), 1 (DO-260A) or 2 (DO-260B).
+    /// Version 0 messages carry none of the fields below.
+    pub version: u8,
+    pub nic_supplement_a: bool,
+    /// Surface only
+    pub nic_supplement_c: bool,
+    pub nac_p: u8,
+    /// Geometric vertical accuracy, airborne only
+    pub gva: Option<u8>,
+    pub sil: u8,
+    pub sil_per_sample: bool,
+    /// Airborne only
+    pub nic_baro: Option<bool>,
+    /// Headings are relative to magnetic instead of true north
+    pub heading_magnetic: bool,
+    pub tcas_operational: Option<bool>,
+    pub tcas_ra_active: bool,
+    pub ident: bool,
+}
+
+/// Extract bits `first..=last` (1-based) of the ME field, or of a whole message
+pub fn bits(me: &[u8], first: usize, last: usize) -> u32 {
+    let mut value = 0;
+    for bit in first..=last {
+        let byte = me[(bit - 1) / 8];
+        value = (value << 1) | ((byte >> (7 - (bit - 1) % 8)) & 1) as u32;
+    }
+    value
+}
+
+pub fn bit(me: &[u8], n: usize) -> bool {
+    bits(me, n, n) == 1
+}
+
+pub fn type_code(me: &[u8]) -> u8 {
+    bits(me, 1, 5) as u8
+}
+
+/// The IMF bit of fine TIS-B and ADS-R messages, set when the address
+/// isn't an ICAO address. It reuses a different bit depending on format.
+pub fn imf(me: &[u8]) -> bool {
+    match type_code(me) {
+        5..=8 => bit(me, 21),
+        9..=18 | 20..=22 => bit(me, 8),
+        19 => bit(me, 9),
+        _ => false,
+    }
+}
+
+pub fn decode(me: &[u8]) -> EsMessage {
+    match type_code(me) {
+        tc @ 1..=4 => decode_identification(tc, me),
+        tc @ 5..=8 => decode_surface_position(tc, me),
+        tc @ 9..=18 | tc @ 20..=22 => decode_airborne_position(tc, me),
+        19 => match decode_velocity(me) {
+            Some(velocity) => EsMessage::AirborneVelocity(velocity),
+            None => EsMessage::Unknown { type_code: 19 },
+        },
+        28 => match bits(me, 6, 8) {
+            1 => EsMessage::AircraftStatus {
+                emergency: EmergencyState::from_code(bits(me, 9, 11)),
+                squawk: gillham::decode_id13(bits(me, 12, 24)),
+            },
+            // subtype 2 is a TCAS resolution advisory broadcast
+            _ => EsMessage::Unknown { type_code: 28 },
+        },
+        29 => match decode_target_state(me) {
+            Some(state) => EsMessage::TargetState(state),
+            None => EsMessage::Unknown { type_code: 29 },
+        },
+        31 => match decode_operational_status(me) {
+            Some(status) => EsMessage::OperationalStatus(status),
+            None => EsMessage::Unknown { type_code: 31 },
+        },
+        tc => EsMessage::Unknown { type_code: tc },
+    }
+}
+
+fn decode_identification(tc: u8, me: &[u8]) -> EsMessage {
+    let callsign: String = (0..8)
+        .map(|i| CALLSIGN_CHARSET[bits(me, 9 + i * 6, 14 + i * 6) as usize] as char)
+        .collect();
+
+    EsMessage::Identification {
+        category: ((0xe - tc) << 4) | bits(me, 6, 8) as u8,
+        callsign: callsign.trim_end().to_string(),
+    }
+}
+
+fn decode_cpr(me: &[u8]) -> CprFrame {
+    CprFrame {
+        odd: bit(me, 22),
+        lat: bits(me, 23, 39),
+        lon: bits(me, 40, 56),
+    }
+}
+
+fn decode_surface_position(tc: u8, me: &[u8]) -> EsMessage {
+    let ground_speed = match bits(me, 6, 12) {
+        0 | 125..=127 => None,
+        1 => Some(0.0),
+        m @ 2..=8 => Some((m - 1) as f64 * 0.125),
+        m @ 9..=12 => Some(1.0 + (m - 9) as f64 * 0.25),
+        m @ 13..=38 => Some(2.0 + (m - 13) as f64 * 0.5),
+        m @ 39..=93 => Some(15.0 + (m - 39) as f64),
+        m @ 94..=108 => Some(70.0 + (m - 94) as f64 * 2.0),
+        m @ 109..=123 => Some(100.0 + (m - 109) as f64 * 5.0),
+        _ => Some(175.0),
+    };
+
+    let track = match bit(me, 13) {
+        true => Some(bits(me, 14, 20) as f64 * 360.0 / 128.0),
+        false => None,
+    };
+
+    EsMessage::SurfacePosition {
+        type_code: tc,
+        ground_speed,
+        track,
+        cpr: decode_cpr(me),
+    }
+}
+
+fn decode_airborne_position(tc: u8, me: &[u8]) -> EsMessage {
+    EsMessage::AirbornePosition {
+        type_code: tc,
+        altitude: gillham::decode_ac12(bits(me, 9, 20)),
+        gnss: tc >= 20,
+        surveillance_status: bits(me, 6, 7) as u8,
+        nic_supplement_b: bit(me, 8),
+        cpr: decode_cpr(me),
+    }
+}
+
+fn decode_velocity(me: &[u8]) -> Option<Velocity> {
+    let subtype = bits(me, 6, 8);
+
+    // subtypes 2 and 4 are for supersonic aircraft, 4 times the unit
+    let multiplier = match subtype {
+        1 | 3 => 1,
+        2 | 4 => 4,
+        _ => return None,
+    };
+
+    // subtypes 3 and 4 send heading and airspeed instead of the velocity over ground
+    let airspeed = subtype > 2;
+    let (ground_speed, track) = match (bits(me, 15, 24), bits(me, 26, 35)) {
+        (ew, ns) if !airspeed && ew != 0 && ns != 0 => {
+            let mut ew = ((ew - 1) * multiplier) as f64;
+            let mut ns = ((ns - 1) * multiplier) as f64;
+            if bit(me, 14) {
+                ew = -ew; // west
+            }
+            if bit(me, 25) {
+                ns = -ns; // south
+            }
+
+            let track = ew.atan2(ns).to_degrees();
+            (
+                Some((ew * ew + ns * ns).sqrt()),
+                Some(if track < 0.0 { track + 360.0 } else { track }),
+            )
+        }
+        _ => (None, None),
+    };
+
+    Some(Velocity {
+        ground_speed,
+        track,
+        heading: match airspeed && bit(me, 14) {
+            true => Some(bits(me, 15, 24) as f64 * 360.0 / 1024.0),
+            false => None,
+        },
+        airspeed: match bits(me, 26, 35) {
+            v if airspeed && v != 0 => Some((v - 1) * multiplier),
+            _ => None,
+        },
+        airspeed_is_true: airspeed && bit(me, 25),
+        vertical_rate: match bits(me, 38, 46) {
+            0 => None,
+            v if bit(me, 37) => Some(-((v as i32 - 1) * 64)),
+            v => Some((v as i32 - 1) * 64),
+        },
+        vertical_rate_is_gnss: !bit(me, 36),
+        gnss_baro_diff: match bits(me, 50, 56) {
+            0 => None,
+            v if bit(me, 49) => Some(-((v as i32 - 1) * 25)),
+            v => Some((v as i32 - 1) * 25),
+        },
+        nac_v: bits(me, 11, 13) as u8,
+    })
+}
+
+fn decode_target_state(me: &[u8]) -> Option<TargetState> {
+    // subtype 0 is the DO-260A format, which hardly anyone ever sent
+    if bits(me, 6, 7) != 1 {
+        return None;
+    }
+
+    // autopilot modes are only valid with their status bit set
+    let mode = |n| match bit(me, 47) {
+        true => Some(bit(me, n)),
+        false => None,
+    };
+
+    Some(TargetState {
+        selected_altitude: match bits(me, 10, 20) {
+            0 => None,
+            v => Some((v as i32 - 1) * 32),
+        },
+        selected_altitude_fms: bit(me, 9),
+        baro_setting: match bits(me, 21, 29) {
+            0 => None,
+            v => Some(800.0 + (v - 1) as f64 * 0.8),
+        },
+        selected_heading: match bit(me, 30) {
+            true => Some(bits(me, 31, 39) as f64 * 180.0 / 256.0),
+            false => None,
+        },
+        nac_p: bits(me, 40, 43) as u8,
+        nic_baro: bit(me, 44),
+        sil: bits(me, 45, 46) as u8,
+        sil_per_sample: bit(me, 8),
+        autopilot: mode(48),
+        vnav: mode(49),
+        altitude_hold: mode(50),
+        approach: mode(52),
+        lnav: mode(54),
+        tcas_operational: bit(me, 53),
+    })
+}
+
+fn decode_operational_status(me: &[u8]) -> Option<OperationalStatus> {
+    let airborne = match bits(me, 6, 8) {
+        0 => true,
+        1 => false,
+        _ => return None,
+    };
+
+    let version = bits(me, 41, 43) as u8;
+    if version == 0 {
+        return Some(OperationalStatus {
+            airborne,
+            version,
+            ..Default::default()
+        });
+    }
+
+    Some(OperationalStatus {
+        airborne,
+        version,
+        nic_supplement_a: bit(me, 44),
+        nic_supplement_c: !airborne && version >= 2 && bit(me, 20),
+        nac_p: bits(me, 45, 48) as u8,
+        gva: match airborne && version >= 2 {
+            true => Some(bits(me, 49, 50) as u8),
+            false => None,
+        },
+        sil: bits(me, 51, 52) as u8,
+        sil_per_sample: version >= 2 && bit(me, 55),
+        nic_baro: match airborne {
+            true => Some(bit(me, 53)),
+            false => None,
+        },
+        heading_magnetic: bit(me, 54),
+        // version 1 has a TCAS not installed/operational flag in the same place
+        tcas_operational: match (airborne, version) {
+            (false, _) => None,
+            (true, 1) => Some(!bit(me, 11)),
+            (true, _) => Some(bit(me, 11)),
+        },
+        tcas_ra_active: bit(me, 27),
+        ident: bit(me, 28),
+    })
+}
@@ -0,0 +1,145 @@
+/* Tracker events.
+ *
+ * The tracker publishes what changed on a broadcast channel, so every
+ * output (GDL90, JSON, logging, UI) can follow along at its own pace
+ * instead of polling the aircraft table.
+ */
+
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use super::es::EmergencyState;
+use super::{Address, Aircraft};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertKind {
+    /// Aircraft started squawking 7500, 7600 or 7700
+    Squawk(u16),
+    /// Aircraft declared an emergency/priority state in an aircraft status message
+    Emergency(EmergencyState),
+    /// Pilot pressed IDENT, the aircraft reports the SPI condition
+    Ident,
+}
+
+/// Something about an aircraft outputs should flag. Raised once when the
+/// condition starts, not for every message repeating it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alert {
+    pub address: Address,
+    pub kind: AlertKind,
+    pub at: SystemTime,
+}
+
+/// Groups of `Aircraft` fields reported by `TrackerEvent::Updated`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    AddressType,
+    /// the link data is taken from, e.g. a TIS-B target now heard directly
+    Source,
+    Callsign,
+    EmitterCategory,
+    OnGround,
+    Squawk,
+    Emergency,
+    Ident,
+    /// altitude, its source and the GNSS/baro difference
+    Altitude,
+    /// ground speed, track, heading and vertical rate
+    Velocity,
+    /// selected altitude, heading and baro setting
+    TargetState,
+    Integrity,
+    Ownship,
+}
+
+#[derive(Debug, Clone)]
+pub enum TrackerEvent {
+    /// First message from an aircraft, with everything decoded from it
+    NewAircraft(Arc<Aircraft>),
+    /// Fields other than the position changed
+    Updated {
+        aircraft: Arc<Aircraft>,
+        fields: Vec<Field>,
+    },
+    /// A new position was decoded
+    PositionUpdate {
+        address: Address,
+        latitude: f64,
+        longitude: f64,
+        altitude: Option<i32>,
+        at: SystemTime,
+    },
+    /// Nothing heard from the aircraft for the aircraft timeout, it was dropped
+    Lost(Arc<Aircraft>),
+    Emergency(Alert),
+}
+
+/// Which fields differ between two states of the same aircraft
+pub fn changed_fields(before: &Aircraft, after: &Aircraft) -> Vec<Field> {
+    let mut fields = Vec::new();
+
+    if before.address_type != after.address_type {
+        fields.push(Field::AddressType);
+    }
+    if before.source != after.source {
+        fields.push(Field::Source);
+    }
+    if before.callsign != after.callsign {
+        fields.push(Field::Callsign);
+    }
+    if before.emitter_category != after.emitter_category {
+        fields.push(Field::EmitterCategory);
+    }
+    if before.on_ground != after.on_ground {
+        fields.push(Field::OnGround);
+    }
+    if before.squawk != after.squawk {
+        fields.push(Field::Squawk);
+    }
+    if before.emergency != after.emergency {
+        fields.push(Field::Emergency);
+    }
+    if before.ident != after.ident {
+        fields.push(Field::Ident);
+    }
+    if (
+        before.altitude,
+        before.alt_is_gnss,
+        before.alt_gnss_baro_diff,
+    ) != (after.altitude, after.alt_is_gnss, after.alt_gnss_baro_diff)
+    {
+        fields.push(Field::Altitude);
+    }
+    if (
+        before.ground_speed,
+        before.track,
+        before.heading,
+        before.vertical_rate,
+    ) != (
+        after.ground_speed,
+        after.track,
+        after.heading,
+        after.vertical_rate,
+    ) {
+        fields.push(Field::Velocity);
+    }
+    if (
+        before.selected_altitude,
+        before.selected_heading,
+        before.baro_setting,
+    ) != (
+        after.selected_altitude,
+        after.selected_heading,
+        after.baro_setting,
+    ) {
+        fields.push(Field::TargetState);
+    }
+    if before.integrity != after.integrity {
+        fields.push(Field::Integrity);
+    }
+    if before.ownship != after.ownship {
+        fields.push(Field::Ownship);
+    }
+
+    fields
+}
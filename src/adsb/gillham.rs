@@ -0,0 +1,162 @@
+/* Mode A/C code conversions.
+ *
+ * Identity (squawk) codes and the non-Q altitude codes are transmitted as
+ * 13 bits interleaving the four octal digits A, B, C and D of the code:
+ *
+ *   C1 A1 C2 A2 C4 A4 X/M B1 D1/Q B2 D2 B4 D4
+ *
+ * We keep them in "hex gillham" form where every nibble is one octal
+ * digit (0xABCD), so squawk 7700 is 0x7700 and prints with {:04x}.
+ */
+
+/// Reorder a 13 bit identity/altitude field into 0xABCD form
+pub fn decode_id13(id13: u32) -> u16 {
+    let mut hex_gillham: u16 = 0;
+
+    if id13 & 0x1000 != 0 {
+        hex_gillham |= 0x0010; // C1
+    }
+    if id13 & 0x0800 != 0 {
+        hex_gillham |= 0x1000; // A1
+    }
+    if id13 & 0x0400 != 0 {
+        hex_gillham |= 0x0020; // C2
+    }
+    if id13 & 0x0200 != 0 {
+        hex_gillham |= 0x2000; // A2
+    }
+    if id13 & 0x0100 != 0 {
+        hex_gillham |= 0x0040; // C4
+    }
+    if id13 & 0x0080 != 0 {
+        hex_gillham |= 0x4000; // A4
+    }
+    if id13 & 0x0020 != 0 {
+        hex_gillham |= 0x0100; // B1
+    }
+    if id13 & 0x0010 != 0 {
+        hex_gillham |= 0x0001; // D1
+    }
+    if id13 & 0x0008 != 0 {
+        hex_gillham |= 0x0200; // B2
+    }
+    if id13 & 0x0004 != 0 {
+        hex_gillham |= 0x0002; // D2
+    }
+    if id13 & 0x0002 != 0 {
+        hex_gillham |= 0x0400; // B4
+    }
+    if id13 & 0x0001 != 0 {
+        hex_gillham |= 0x0004; // D4
+    }
+
+    hex_gillham
+}
+
+/// Convert a gray coded Mode C altitude in 0xABCD form to hundreds of feet
+pub fn mode_a_to_mode_c(mode_a: u16) -> Option<i32> {
+    let mode_a = mode_a as u32;
+    let mut five_hundreds: u32 = 0;
+    let mut one_hundreds: u32 = 0;
+
+    // D1 is never used for altitude, and at least one C bit has to be set
+    if (mode_a & 0xffff_8889) != 0 || (mode_a & 0x0000_00f0) == 0 {
+        return None;
+    }
+
+    if mode_a & 0x0010 != 0 {
+        one_hundreds ^= 0x007; // C1
+    }
+    if mode_a & 0x0020 != 0 {
+        one_hundreds ^= 0x003; // C2
+    }
+    if mode_a & 0x0040 != 0 {
+        one_hundreds ^= 0x001; // C4
+    }
+
+    // remove 7s from one_hundreds (make 7->5, and 5->7)
+    if (one_hundreds & 5) == 5 {
+        one_hundreds ^= 2;
+    }
+
+    // check for invalid codes, only 1 to 5 are valid
+    if one_hundreds > 5 {
+        return None;
+    }
+
+    if mode_a & 0x0002 != 0 {
+        five_hundreds ^= 0x0ff; // D2
+    }
+    if mode_a & 0x0004 != 0 {
+        five_hundreds ^= 0x07f; // D4
+    }
+    if mode_a & 0x1000 != 0 {
+        five_hundreds ^= 0x03f; // A1
+    }
+    if mode_a & 0x2000 != 0 {
+        five_hundreds ^= 0x01f; // A2
+    }
+    if mode_a & 0x4000 != 0 {
+        five_hundreds ^= 0x00f; // A4
+    }
+    if mode_a & 0x0100 != 0 {
+        five_hundreds ^= 0x007; // B1
+    }
+    if mode_a & 0x0200 != 0 {
+        five_hundreds ^= 0x003; // B2
+    }
+    if mode_a & 0x0400 != 0 {
+        five_hundreds ^= 0x001; // B4
+    }
+
+    // correct order of one_hundreds
+    if five_hundreds & 1 != 0 {
+        one_hundreds = 6 - one_hundreds;
+    }
+
+    Some((five_hundreds * 5 + one_hundreds) as i32 - 13)
+}
+
+/// 13 bit altitude code of surveillance replies (DF0/4/16/20) in feet
+pub fn decode_ac13(ac13: u32) -> Option<i32> {
+    let m_bit = ac13 & 0x0040;
+    let q_bit = ac13 & 0x0010;
+
+    if ac13 == 0 || m_bit != 0 {
+        // unknown, or metric altitude which nobody uses
+        return None;
+    }
+
+    if q_bit != 0 {
+        // 25 ft increments, remove the M and Q bits
+        let n = ((ac13 & 0x1f80) >> 2) | ((ac13 & 0x0020) >> 1) | (ac13 & 0x000f);
+        return Some(n as i32 * 25 - 1000);
+    }
+
+    match mode_a_to_mode_c(decode_id13(ac13)) {
+        Some(n) if n >= -12 => Some(n * 100),
+        _ => None,
+    }
+}
+
+/// 12 bit altitude code of extended squitter airborne positions in feet
+pub fn decode_ac12(ac12: u32) -> Option<i32> {
+    let q_bit = ac12 & 0x10;
+
+    if ac12 == 0 {
+        return None;
+    }
+
+    if q_bit != 0 {
+        // 25 ft increments, remove the Q bit
+        let n = ((ac12 & 0x0fe0) >> 1) | (ac12 & 0x000f);
+        return Some(n as i32 * 25 - 1000);
+    }
+
+    // make it a 13 bit gillham coded altitude by inserting M=0 at bit 6
+    let n = ((ac12 & 0x0fc0) << 1) | (ac12 & 0x003f);
+    match mode_a_to_mode_c(decode_id13(n)) {
+        Some(n) if n >= -12 => Some(n * 100),
+        _ => None,
+    }
+}
@@ -0,0 +1,154 @@
+/* Position integrity and accuracy.
+ *
+ * The NIC (navigation integrity category) isn't transmitted as such, it
+ * follows from the type code of a position message together with the NIC
+ * supplement bits, which arrive in different messages:
+ *
+ *   A: operational status (TC31) bit 44
+ *   B: airborne position bit 8 (version 2 only, a single antenna flag before)
+ *   C: surface operational status capability class bit 20 (version 2 only)
+ *
+ * The rest (NACp, NACv, SIL, ...) are transmitted directly.
+ */
+
+use super::es::{OperationalStatus, TargetState};
+use super::uat::ModeStatus;
+use crate::geo::METERS_PER_NM;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Integrity {
+    /// ADS-B version (DO-260 = 0, DO-260A = 1, DO-260B = 2), from operational status
+    pub version: Option<u8>,
+    /// Navigation integrity category of the last position, 0-11
+    pub nic: Option<u8>,
+    /// Horizontal containment radius of the last position in meters
+    pub rc: Option<f64>,
+    /// Navigation accuracy category for position, 0-11
+    pub nac_p: Option<u8>,
+    /// Navigation accuracy category for velocity, 0-4
+    pub nac_v: Option<u8>,
+    /// Source integrity level, 0-3
+    pub sil: Option<u8>,
+    /// SIL is a per sample instead of a per hour probability
+    pub sil_per_sample: bool,
+    /// Geometric vertical accuracy, 0-3
+    pub gva: Option<u8>,
+    /// Barometric altitude has been cross-checked against another source
+    pub nic_baro: Option<bool>,
+
+    pub nic_supplement_a: bool,
+    pub nic_supplement_c: bool,
+}
+
+impl Integrity {
+    pub fn update_operational_status(&mut self, status: &OperationalStatus) {
+        self.version = Some(status.version);
+        if status.version == 0 {
+            // DO-260 has no integrity fields in here
+            return;
+        }
+
+        self.nic_supplement_a = status.nic_supplement_a;
+        self.nic_supplement_c = status.nic_supplement_c;
+        self.nac_p = Some(status.nac_p);
+        self.sil = Some(status.sil);
+        self.sil_per_sample = status.sil_per_sample;
+        self.gva = status.gva.or(self.gva);
+        self.nic_baro = status.nic_baro.or(self.nic_baro);
+    }
+
+    pub fn update_target_state(&mut self, state: &TargetState) {
+        self.nac_p = Some(state.nac_p);
+        self.sil = Some(state.sil);
+        self.sil_per_sample = state.sil_per_sample;
+        self.nic_baro = Some(state.nic_baro);
+    }
+
+    /// UAT sends the NIC itself, the containment radius follows from it
+    pub fn update_nic(&mut self, nic: u8) {
+        self.nic = Some(nic);
+        self.rc = rc(nic);
+    }
+
+    pub fn update_mode_status(&mut self, status: &ModeStatus) {
+        self.nac_p = Some(status.nac_p);
+        self.nac_v = Some(status.nac_v);
+        self.sil = Some(status.sil);
+        self.nic_baro = Some(status.nic_baro);
+    }
+
+    /// Derive NIC and containment radius from a position message
+    pub fn update_position(&mut self, type_code: u8, nic_supplement_b: bool) {
+        let a = self.nic_supplement_a;
+        // before version 2 there's only supplement A, which takes B's place in the tables
+        let b = match self.version {
+            Some(2) => nic_supplement_b,
+            _ => a,
+        };
+        let c = self.nic_supplement_c;
+
+        let (nic, rc) = match nic(type_code, a, b, c) {
+            Some((nic, rc)) => (Some(nic), rc),
+            None => (None, None),
+        };
+        self.nic = nic;
+        self.rc = rc;
+    }
+}
+
+/// Upper bound of the containment radius (m) of a NIC, DO-282B table 2-17
+pub fn rc(nic: u8) -> Option<f64> {
+    match nic {
+        1 => Some(20.0 * METERS_PER_NM),
+        2 => Some(8.0 * METERS_PER_NM),
+        3 => Some(4.0 * METERS_PER_NM),
+        4 => Some(2.0 * METERS_PER_NM),
+        5 => Some(1.0 * METERS_PER_NM),
+        6 => Some(0.6 * METERS_PER_NM),
+        7 => Some(0.2 * METERS_PER_NM),
+        8 => Some(0.1 * METERS_PER_NM),
+        9 => Some(75.0),
+        10 => Some(25.0),
+        11 => Some(7.5),
+        _ => None,
+    }
+}
+
+/// NIC and containment radius (m) of a position type code, DO-260B tables 2-14 and 2-15
+pub fn nic(type_code: u8, a: bool, b: bool, c: bool) -> Option<(u8, Option<f64>)> {
+    let nic = match (type_code, a, b, c) {
+        // surface position
+        (5, ..) => (11, Some(7.5)),
+        (6, ..) => (10, Some(25.0)),
+        (7, true, _, false) => (9, Some(75.0)),
+        (7, ..) => (8, Some(0.1 * METERS_PER_NM)),
+        (8, true, _, true) => (7, Some(0.2 * METERS_PER_NM)),
+        (8, true, _, false) => (6, Some(0.3 * METERS_PER_NM)),
+        (8, false, _, true) => (6, Some(0.6 * METERS_PER_NM)),
+        (8, ..) => (0, None),
+
+        // airborne position, barometric altitude
+        (9, ..) => (11, Some(7.5)),
+        (10, ..) => (10, Some(25.0)),
+        (11, true, true, _) => (9, Some(75.0)),
+        (11, ..) => (8, Some(0.1 * METERS_PER_NM)),
+        (12, ..) => (7, Some(0.2 * METERS_PER_NM)),
+        (13, false, true, _) => (6, Some(0.3 * METERS_PER_NM)),
+        (13, true, true, _) => (6, Some(0.6 * METERS_PER_NM)),
+        (13, ..) => (6, Some(0.5 * METERS_PER_NM)),
+        (14, ..) => (5, Some(1.0 * METERS_PER_NM)),
+        (15, ..) => (4, Some(2.0 * METERS_PER_NM)),
+        (16, true, true, _) => (3, Some(4.0 * METERS_PER_NM)),
+        (16, ..) => (2, Some(8.0 * METERS_PER_NM)),
+        (17, ..) => (1, Some(20.0 * METERS_PER_NM)),
+        (18, ..) => (0, None),
+
+        // airborne position, GNSS height
+        (20, ..) => (11, Some(7.5)),
+        (21, ..) => (10, Some(25.0)),
+        (22, ..) => (0, None),
+
+        _ => return None,
+    };
+    Some(nic)
+}
@@ -1,88 +1,921 @@
+pub mod cpr;
+pub mod es;
+pub mod event;
+pub mod gillham;
+pub mod integrity;
+pub mod ownship;
+pub mod receiver;
+pub mod shared;
+pub mod uat;
+
+use log::*;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+
+use crate::geo::{Position, METERS_PER_NM};
+use crate::sdr::mode_s::Frame;
+use crate::sdr::uat::Frame as UatFrame;
+use cpr::CprFrame;
+use es::{EmergencyState, EsMessage};
+pub use event::{Alert, AlertKind, Field, TrackerEvent};
+use integrity::Integrity;
+pub use ownship::OwnshipFix;
+use receiver::Coverage;
+pub use receiver::{ReceiverLocation, Relative};
+pub use shared::{SharedTracker, Snapshot};
+
+/* Even and odd CPR frames further apart than this are not combined into a
+ * global position, the aircraft may have crossed into another zone. */
+pub const CPR_MAX_PAIR_AGE: Duration = Duration::from_secs(10);
+
+/* Single CPR frames are decoded relative to a previous position of the
+ * aircraft if it isn't older than this, it must still be within half a zone */
+pub const CPR_LOCAL_MAX_AGE: Duration = Duration::from_secs(300);
+
+/* Half a CPR zone, how far from the receiver an aircraft may be for its
+ * positions to be decoded relative to the receiver location */
+pub const CPR_AIRBORNE_LOCAL_RANGE: f64 = 180.0 * METERS_PER_NM;
+
+/* How long data from a better address type (e.g. direct ADS-B) takes
+ * precedence over rebroadcasts (TIS-B, ADS-R) of the same aircraft,
+ * unless configured otherwise. */
+pub const ADDRESS_TYPE_TIMEOUT: Duration = Duration::from_secs(30);
 
-pub use adsb::{ADSBMessageKind, ICAOAddress, Message, MessageKind};
+/* Aircraft not heard from for this long are dropped, unless configured otherwise */
+pub const AIRCRAFT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/* How often a running stack expires aircraft, so they are lost on time even
+ * when no messages arrive to do it, see `SharedTracker::expire_every` */
+pub const EXPIRE_INTERVAL: Duration = Duration::from_secs(1);
+
+/* Events buffered per subscriber before the slowest one starts missing them */
+pub const TRACKER_EVENT_CAPACITY: usize = 256;
+
+/* Decoded positions are checked against these unless configured otherwise.
+ * The default range is the most that still lets single airborne frames be
+ * decoded relative to the receiver. */
+pub const DEFAULT_MAX_SPEED: f64 = 1000.0 * METERS_PER_NM / 3600.0;
+pub const DEFAULT_MAX_RANGE: f64 = CPR_AIRBORNE_LOCAL_RANGE;
+pub const DEFAULT_RELIABLE_POSITIONS: u8 = 2;
+
+/* Distance a position may be off on top of the speed limit, CPR
+ * resolution and timing jitter of the receive timestamps */
+pub const POSITION_SLACK: f64 = 500.0;
+
+/// Plausibility limits for position updates
+#[derive(Debug, Clone)]
+pub struct TrackerConfig {
+    /// Receiver location, positions further away than `max_range` are rejected.
+    /// Also the reference for decoding single CPR frames.
+    pub receiver: Option<ReceiverLocation>,
+    /// Meters. Above `CPR_AIRBORNE_LOCAL_RANGE` the receiver is no reference for
+    /// airborne positions, those then need an even/odd pair first.
+    pub max_range: f64,
+    /// Highest speed (m/s) implied by two consecutive positions
+    pub max_speed: f64,
+    /// Consistent positions needed before an aircraft's position is published
+    pub reliable_positions: u8,
+    /// Address of our own transponder, detected from the GPS fix if not set
+    pub ownship_icao: Option<u32>,
+    /// Aircraft not heard from for this long are dropped
+    pub aircraft_timeout: Duration,
+    /// How long a better address type takes precedence over rebroadcasts
+    pub address_type_timeout: Duration,
+}
+
+impl Default for TrackerConfig {
+    fn default() -> TrackerConfig {
+        TrackerConfig {
+            receiver: None,
+            max_range: DEFAULT_MAX_RANGE,
+            max_speed: DEFAULT_MAX_SPEED,
+            reliable_positions: DEFAULT_RELIABLE_POSITIONS,
+            ownship_icao: None,
+            aircraft_timeout: AIRCRAFT_TIMEOUT,
+            address_type_timeout: ADDRESS_TYPE_TIMEOUT,
+        }
+    }
+}
+
+/// What happened to the decoded positions of an aircraft
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PositionStats {
+    pub accepted: u64,
+    /// Even/odd pair from different latitude/longitude zones
+    pub rejected_zone: u64,
+    /// Implied speed since the last position too high
+    pub rejected_speed: u64,
+    /// Further from the receiver than the configured range
+    pub rejected_range: u64,
+}
+
+/// Squawk codes reserved for emergencies, in 0xABCD form
+pub const SQUAWK_HIJACK: u16 = 0x7500;
+pub const SQUAWK_RADIO_FAILURE: u16 = 0x7600;
+pub const SQUAWK_EMERGENCY: u16 = 0x7700;
+
+/// How an aircraft is addressed, and who sent us its data. Ordered from
+/// most to least trusted, direct reception wins over ground station rebroadcasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AddressType {
+    /// ICAO address heard directly (DF11/17, DF18 CF=0, address/parity replies)
+    Icao,
+    /// Non-transponder ADS-B device with a non-ICAO address (DF18 CF=1)
+    NonIcao,
+    /// UAT aircraft rebroadcast by a ground station (ADS-R), ICAO address
+    AdsR,
+    /// ADS-R rebroadcast with a non-ICAO address
+    AdsROther,
+    /// Radar target rebroadcast by a ground station (TIS-B), ICAO address
+    TisB,
+    /// TIS-B target identified by a track file number instead of an address
+    TisBOther,
+    /// Self-assigned temporary address
+    Anonymous,
+}
+
+impl AddressType {
+    /// True if the address is a real 24 bit ICAO address
+    pub fn is_icao(&self) -> bool {
+        self.family() == AddressFamily::Icao
+    }
+
+    pub fn family(&self) -> AddressFamily {
+        match self {
+            AddressType::Icao | AddressType::AdsR | AddressType::TisB => AddressFamily::Icao,
+            AddressType::NonIcao | AddressType::AdsROther => AddressFamily::AdsBOther,
+            AddressType::TisBOther => AddressFamily::TisBTrackFile,
+            AddressType::Anonymous => AddressFamily::Anonymous,
+        }
+    }
+}
+
+/// Namespace an address is unique in. The same 24 bits may be an ICAO
+/// address, a track file number and a self-assigned address at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    /// ICAO address, however it was received
+    Icao,
+    /// Non-ICAO address of an ADS-B device, heard directly or through ADS-R
+    AdsBOther,
+    /// TIS-B track file number
+    TisBTrackFile,
+    /// Self-assigned temporary address
+    Anonymous,
+}
+
+/// Link an aircraft's data arrived over, direct or through a ground station
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// 1090 MHz Mode S, extended squitter or replies to interrogations
+    Es1090,
+    /// 978 MHz UAT ADS-B
+    Uat,
+    /// Radar target rebroadcast by a ground station, on either link
+    TisB,
+    /// ADS-B from the other link rebroadcast by a ground station
+    AdsR,
+}
+
+impl Source {
+    /* Rebroadcasts are told apart by address type, whatever link they came in on */
+    fn new(address_type: AddressType, uat: bool) -> Source {
+        match address_type {
+            AddressType::TisB | AddressType::TisBOther => Source::TisB,
+            AddressType::AdsR | AddressType::AdsROther => Source::AdsR,
+            _ if uat => Source::Uat,
+            _ => Source::Es1090,
+        }
+    }
+}
+
+/// Key of an aircraft in the tracker. ICAO addresses share one namespace no
+/// matter how they were received, anything else (track file numbers,
+/// self-assigned addresses) is kept apart by its family so none of them
+/// collide with each other or with ICAO addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Address {
+    pub address: u32,
+    pub family: AddressFamily,
+}
+
+impl Address {
+    pub fn new(address: u32, address_type: AddressType) -> Address {
+        Address {
+            address,
+            family: address_type.family(),
+        }
+    }
+
+    pub fn is_icao(&self) -> bool {
+        self.family == AddressFamily::Icao
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.family {
+            AddressFamily::Icao => write!(f, "{:06x}", self.address),
+            _ => write!(f, "~{:06x}", self.address),
+        }
+    }
+}
 
+#[derive(Debug, Clone)]
 pub struct Aircraft {
-    icao: ICAOAddress,
-    reg: Option<String>,
-    callsign: Option<String>,
-    emitter_category: Option<u8>,
-    on_ground: Option<bool>,
-    squawk: Option<u16>,
+    pub address: Address,
+    pub address_type: AddressType,
+    /// Source of the data last taken for this aircraft
+    pub source: Source,
+    /// Every source it was heard on, in the order they were first heard
+    pub sources: Vec<Source>,
+    pub reg: Option<String>,
+    pub callsign: Option<String>,
+    pub emitter_category: Option<u8>,
+    pub on_ground: Option<bool>,
+    pub squawk: Option<u16>,
+    pub emergency: Option<EmergencyState>,
+    /// Special position identification (IDENT) is active
+    pub ident: bool,
+    /// This is our own transponder
+    pub ownship: bool,
+    /// Average signal level in dBFS
+    pub signal_level: Option<f32>,
+
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<i32>,
+    pub alt_gnss_baro_diff: Option<i32>,
+    pub alt_is_gnss: Option<bool>,
+
+    pub ground_speed: Option<f64>,
+    pub track: Option<f64>,
+    pub heading: Option<f64>,
+    pub vertical_rate: Option<i32>,
+
+    pub selected_altitude: Option<i32>,
+    pub selected_heading: Option<f64>,
+    pub baro_setting: Option<f64>,
+
+    pub integrity: Integrity,
 
-    latitude: Option<f64>,
-    longitude: Option<f64>,
-    altitude: Option<i32>,
-    alt_gnss_baro_diff: Option<i32>,
-    alt_is_gnss: Option<bool>,
+    /// Consecutive positions that passed the plausibility checks
+    pub position_reliability: u8,
+    pub position_stats: PositionStats,
 
-    msg_count: u64,
+    pub msg_count: u64,
+    pub last_seen: SystemTime,
+
+    address_type_seen: SystemTime,
+    cpr_even: Option<(CprFrame, SystemTime)>,
+    cpr_odd: Option<(CprFrame, SystemTime)>,
+    last_fix: Option<(Position, SystemTime)>,
+    ownship_matches: u8,
 }
 
 impl Aircraft {
-    fn new(icao_address: ICAOAddress) -> Aircraft {
+    fn new(
+        address: Address,
+        address_type: AddressType,
+        source: Source,
+        at: SystemTime,
+    ) -> Aircraft {
         Aircraft {
-            icao: icao_address,
+            address,
+            address_type,
+            source,
+            sources: vec![],
             reg: None,
             callsign: None,
             emitter_category: None,
             on_ground: None,
             squawk: None,
+            emergency: None,
+            ident: false,
+            ownship: false,
+            signal_level: None,
             latitude: None,
             longitude: None,
             altitude: None,
             alt_gnss_baro_diff: None,
             alt_is_gnss: None,
+            ground_speed: None,
+            track: None,
+            heading: None,
+            vertical_rate: None,
+            selected_altitude: None,
+            selected_heading: None,
+            baro_setting: None,
+            integrity: Integrity::default(),
+            position_reliability: 0,
+            position_stats: PositionStats::default(),
             msg_count: 0,
+            last_seen: at,
+            address_type_seen: at,
+            cpr_even: None,
+            cpr_odd: None,
+            last_fix: None,
+            ownship_matches: 0,
+        }
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        match (self.latitude, self.longitude) {
+            (Some(latitude), Some(longitude)) => Some(Position::new(latitude, longitude)),
+            _ => None,
         }
     }
 
-    fn update(&mut self, adsb: &ADSBMessageKind) {
-        use ADSBMessageKind::*;
+    /* Data is only taken from the best source we recently heard this
+     * aircraft from, so a TIS-B rebroadcast of an aircraft we receive
+     * directly doesn't make it jump around. */
+    fn accept_source(
+        &mut self,
+        address_type: AddressType,
+        at: SystemTime,
+        timeout: Duration,
+    ) -> bool {
+        let stale = at
+            .duration_since(self.address_type_seen)
+            .is_ok_and(|age| age > timeout);
 
-        match adsb {
-            AircraftIdentification { callsign, .. } => self.callsign = Some(callsign.to_string()),
-            _ => {}
+        if address_type <= self.address_type || stale {
+            self.address_type = address_type;
+            self.address_type_seen = at;
+            return true;
+        }
+
+        false
+    }
+
+    fn update(&mut self, es: &EsMessage, at: SystemTime, config: &TrackerConfig) {
+        use EsMessage::*;
+
+        match es {
+            Identification { category, callsign } => {
+                self.callsign = Some(callsign.to_string());
+                self.emitter_category = Some(*category);
+            }
+            SurfacePosition {
+                type_code,
+                ground_speed,
+                track,
+                cpr,
+            } => {
+                self.on_ground = Some(true);
+                self.integrity.update_position(*type_code, false);
+                self.ground_speed = ground_speed.or(self.ground_speed);
+                self.track = track.or(self.track);
+                self.update_surface_cpr(cpr, at, config);
+            }
+            AirbornePosition {
+                type_code,
+                altitude,
+                gnss,
+                surveillance_status,
+                nic_supplement_b,
+                cpr,
+            } => {
+                self.on_ground = Some(false);
+                self.ident = *surveillance_status == 3;
+                self.integrity
+                    .update_position(*type_code, *nic_supplement_b);
+                if altitude.is_some() {
+                    self.altitude = *altitude;
+                    self.alt_is_gnss = Some(*gnss);
+                }
+                self.update_cpr(cpr, at, config);
+            }
+            AirborneVelocity(velocity) => {
+                self.ground_speed = velocity.ground_speed.or(self.ground_speed);
+                self.track = velocity.track.or(self.track);
+                self.heading = velocity.heading.or(self.heading);
+                self.vertical_rate = velocity.vertical_rate.or(self.vertical_rate);
+                self.alt_gnss_baro_diff = velocity.gnss_baro_diff.or(self.alt_gnss_baro_diff);
+                if self.integrity.version.is_some_and(|v| v >= 1) {
+                    self.integrity.nac_v = Some(velocity.nac_v);
+                }
+            }
+            AircraftStatus { emergency, squawk } => {
+                self.emergency = Some(*emergency);
+                self.squawk = Some(*squawk);
+            }
+            TargetState(state) => {
+                self.selected_altitude = state.selected_altitude.or(self.selected_altitude);
+                self.selected_heading = state.selected_heading.or(self.selected_heading);
+                self.baro_setting = state.baro_setting.or(self.baro_setting);
+                self.integrity.update_target_state(state);
+            }
+            OperationalStatus(status) => {
+                self.integrity.update_operational_status(status);
+            }
+            Unknown { .. } => {}
+        }
+    }
+
+    fn update_uat(&mut self, payload: &uat::Payload, at: SystemTime, config: &TrackerConfig) {
+        if let Some(sv) = &payload.state_vector {
+            self.on_ground = sv.on_ground.or(self.on_ground);
+            self.integrity.update_nic(sv.nic);
+            if sv.altitude.is_some() {
+                self.altitude = sv.altitude;
+                self.alt_is_gnss = Some(sv.alt_is_gnss);
+            }
+            self.ground_speed = sv.ground_speed.or(self.ground_speed);
+            self.track = sv.track.or(self.track);
+            self.heading = sv.heading.or(self.heading);
+            self.vertical_rate = sv.vertical_rate.or(self.vertical_rate);
+
+            // no CPR, but a corrupt frame that got through FEC is still possible
+            if let Some(position) = sv.position {
+                self.update_position(position, at, config);
+            }
+        }
+
+        if let Some(ms) = &payload.mode_status {
+            self.emitter_category = Some(ms.category);
+            self.callsign = ms.callsign.clone().or(self.callsign.take());
+            self.squawk = ms.squawk.or(self.squawk);
+            self.emergency = Some(ms.emergency);
+            self.ident = ms.ident;
+            self.integrity.update_mode_status(ms);
+        }
+
+        if let (Some(sv), Some(aux)) = (&payload.state_vector, &payload.aux_state_vector) {
+            self.alt_gnss_baro_diff = match (sv.altitude, aux.secondary_altitude) {
+                (Some(primary), Some(secondary)) if sv.alt_is_gnss => Some(primary - secondary),
+                (Some(primary), Some(secondary)) => Some(secondary - primary),
+                _ => self.alt_gnss_baro_diff,
+            };
+        }
+    }
+
+    fn update_cpr(&mut self, cpr: &CprFrame, at: SystemTime, config: &TrackerConfig) {
+        match cpr.odd {
+            true => self.cpr_odd = Some((*cpr, at)),
+            false => self.cpr_even = Some((*cpr, at)),
+        }
+
+        let pair = match (&self.cpr_even, &self.cpr_odd) {
+            (Some(even), Some(odd)) => {
+                let (older, newer) = match even.1 < odd.1 {
+                    true => (even.1, odd.1),
+                    false => (odd.1, even.1),
+                };
+                match newer.duration_since(older).unwrap_or_default() > CPR_MAX_PAIR_AGE {
+                    true => None,
+                    false => Some((even.0, odd.0)),
+                }
+            }
+            _ => None,
+        };
+
+        if let Some((even, odd)) = pair {
+            match cpr::global_airborne(&even, &odd, cpr.odd) {
+                Some((lat, lon)) => self.update_position(Position::new(lat, lon), at, config),
+                None => {
+                    debug!("{} cpr pair from different zones", self.address);
+                    self.position_stats.rejected_zone += 1;
+                }
+            }
+            return;
+        }
+
+        if let Some(reference) = self.cpr_reference(at, config, CPR_AIRBORNE_LOCAL_RANGE) {
+            let (lat, lon) = cpr::local(cpr, reference, false);
+            self.update_position(Position::new(lat, lon), at, config);
+        }
+    }
+
+    /* Surface positions have no global decoding without a reference, and
+     * nothing is ever heard on the surface from further than half a
+     * surface zone (45 NM) away, so the receiver location always works. */
+    fn update_surface_cpr(&mut self, cpr: &CprFrame, at: SystemTime, config: &TrackerConfig) {
+        // an airborne pair must not be combined across a landing and takeoff
+        self.cpr_even = None;
+        self.cpr_odd = None;
+
+        if let Some(reference) = self.cpr_reference(at, config, f64::INFINITY) {
+            let (lat, lon) = cpr::local(cpr, reference, true);
+            self.update_position(Position::new(lat, lon), at, config);
+        }
+    }
+
+    /* Reference position for decoding a single CPR frame, our own recent
+     * reliable position or the receiver if nothing further than
+     * `receiver_range` away can be received. */
+    fn cpr_reference(
+        &self,
+        at: SystemTime,
+        config: &TrackerConfig,
+        receiver_range: f64,
+    ) -> Option<(f64, f64)> {
+        if self.position_reliability >= config.reliable_positions {
+            if let Some((fix, fix_at)) = self.last_fix {
+                if at.duration_since(fix_at).unwrap_or_default() <= CPR_LOCAL_MAX_AGE {
+                    return Some((fix.latitude, fix.longitude));
+                }
+            }
         }
 
-        self.msg_count += 1
+        match &config.receiver {
+            Some(receiver) if config.max_range <= receiver_range => {
+                Some((receiver.position.latitude, receiver.position.longitude))
+            }
+            _ => None,
+        }
+    }
+
+    /* A bad CPR pair or a corrupt frame can put an aircraft anywhere, so a
+     * position has to be within range and reachable from the previous one.
+     * Only after a few consistent ones is it made public. */
+    fn update_position(&mut self, position: Position, at: SystemTime, config: &TrackerConfig) {
+        if let Some(receiver) = &config.receiver {
+            if receiver.position.distance(&position) > config.max_range {
+                debug!("{} position {:?} out of range", self.address, position);
+                self.position_stats.rejected_range += 1;
+                return;
+            }
+        }
+
+        if let Some((last, last_at)) = self.last_fix {
+            let elapsed = at.duration_since(last_at).unwrap_or_default();
+            let limit = config.max_speed * elapsed.as_secs_f64() + POSITION_SLACK;
+            if last.distance(&position) > limit {
+                debug!(
+                    "{} position {:?} too far from {:?}",
+                    self.address, position, last
+                );
+                self.position_stats.rejected_speed += 1;
+
+                // if the previous fix was the bad one, start over
+                self.position_reliability = self.position_reliability.saturating_sub(1);
+                if self.position_reliability == 0 {
+                    self.last_fix = None;
+                }
+                return;
+            }
+        }
+
+        self.position_stats.accepted += 1;
+        self.position_reliability = self.position_reliability.saturating_add(1);
+        self.last_fix = Some((position, at));
+
+        if self.position_reliability >= config.reliable_positions {
+            self.latitude = Some(position.latitude);
+            self.longitude = Some(position.longitude);
+        }
     }
 }
 
 pub struct Tracker {
-    db: HashMap<ICAOAddress, Aircraft>,
+    config: TrackerConfig,
+    coverage: Coverage,
+    ownship_fix: Option<OwnshipFix>,
+    db: HashMap<Address, Arc<Aircraft>>,
+    events: broadcast::Sender<TrackerEvent>,
+    last_expire: SystemTime,
+}
+
+impl Default for Tracker {
+    fn default() -> Tracker {
+        Tracker::new()
+    }
 }
 
 impl Tracker {
     pub fn new() -> Tracker {
-        Tracker { db: HashMap::new() }
+        Tracker::with_config(TrackerConfig::default())
     }
 
-    pub fn process(&mut self, frame: &adsb::Message) {
-        use adsb::MessageKind::*;
+    pub fn with_config(config: TrackerConfig) -> Tracker {
+        let (events, _) = broadcast::channel(TRACKER_EVENT_CAPACITY);
+        Tracker {
+            config,
+            coverage: Coverage::new(),
+            ownship_fix: None,
+            db: HashMap::new(),
+            events,
+            last_expire: SystemTime::UNIX_EPOCH,
+        }
+    }
 
-        match &frame.kind {
-            ADSBMessage {
-                icao_address, kind, ..
-            } => {
-                let ac = match self.db.entry(*icao_address) {
-                    Vacant(entry) => entry.insert(Aircraft::new(*icao_address)),
-                    Occupied(entry) => entry.into_mut(),
-                };
+    /// Receive tracker events from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<TrackerEvent> {
+        self.events.subscribe()
+    }
+
+    /// Cheap copy of all tracked aircraft
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(self.db.clone(), self.config.receiver)
+    }
+
+    pub fn receiver(&self) -> Option<&ReceiverLocation> {
+        self.config.receiver.as_ref()
+    }
+
+    /// Move the receiver, e.g. to follow the ownship GPS position
+    pub fn set_receiver(&mut self, receiver: Option<ReceiverLocation>) {
+        self.config.receiver = receiver;
+    }
+
+    /// Latest GPS fix, used to recognize our own transponder
+    pub fn set_ownship_fix(&mut self, fix: Option<OwnshipFix>) {
+        self.ownship_fix = fix;
+    }
+
+    /// Our own transponder, if configured or detected
+    pub fn ownship(&self) -> Option<Arc<Aircraft>> {
+        self.db.values().find(|ac| ac.ownship).cloned()
+    }
+
+    /// Furthest positions received per bearing, empty without a receiver location
+    pub fn coverage(&self) -> &Coverage {
+        &self.coverage
+    }
+
+    pub fn get(&self, icao: u32) -> Option<Arc<Aircraft>> {
+        self.db.get(&Address::new(icao, AddressType::Icao)).cloned()
+    }
+
+    fn publish(&self, event: TrackerEvent) {
+        // nobody listening is fine
+        let _ = self.events.send(event);
+    }
+
+    /// Drop aircraft not heard from within the aircraft timeout of `now`
+    pub fn expire(&mut self, now: SystemTime) {
+        let lost: Vec<Address> = self
+            .db
+            .iter()
+            .filter(|(_, ac)| {
+                now.duration_since(ac.last_seen)
+                    .is_ok_and(|age| age > self.config.aircraft_timeout)
+            })
+            .map(|(address, _)| *address)
+            .collect();
+
+        for address in lost {
+            if let Some(ac) = self.db.remove(&address) {
+                debug!("lost {}", address);
+                self.publish(TrackerEvent::Lost(ac));
+            }
+        }
+        self.last_expire = now;
+    }
 
-                ac.update(&kind);
+    /* Alerts for conditions the last message started */
+    fn alerts(before: &Aircraft, ac: &Aircraft, at: SystemTime) -> Vec<Alert> {
+        let mut kinds = Vec::new();
+
+        match ac.squawk {
+            Some(code @ SQUAWK_HIJACK)
+            | Some(code @ SQUAWK_RADIO_FAILURE)
+            | Some(code @ SQUAWK_EMERGENCY)
+                if before.squawk != ac.squawk =>
+            {
+                kinds.push(AlertKind::Squawk(code))
+            }
+            _ => {}
+        }
+        match ac.emergency {
+            Some(EmergencyState::None) | None => {}
+            Some(state) if before.emergency != ac.emergency => {
+                kinds.push(AlertKind::Emergency(state))
             }
             _ => {}
         }
+        if ac.ident && !before.ident {
+            kinds.push(AlertKind::Ident);
+        }
+
+        kinds
+            .into_iter()
+            .map(|kind| Alert {
+                address: ac.address,
+                kind,
+                at,
+            })
+            .collect()
+    }
+
+    /* DF18 is sent by everything that isn't a transponder, the CF field
+     * tells ADS-B devices, TIS-B and ADS-R ground stations apart. */
+    fn df18_address_type(bytes: &[u8]) -> Option<AddressType> {
+        let me = &bytes[4..11];
+        match bytes[0] & 0x7 {
+            0 => Some(AddressType::Icao),
+            1 => Some(AddressType::NonIcao),
+            2 if es::imf(me) => Some(AddressType::TisBOther),
+            2 => Some(AddressType::TisB),
+            // coarse TIS-B airborne position, the IMF is the first ME bit
+            3 if es::bit(me, 1) => Some(AddressType::TisBOther),
+            3 => Some(AddressType::TisB),
+            5 => Some(AddressType::TisBOther),
+            6 if es::imf(me) => Some(AddressType::AdsROther),
+            6 => Some(AddressType::AdsR),
+            // 4 is TIS-B/ADS-R management, 7 reserved
+            _ => None,
+        }
+    }
+
+    /// Update the tracked aircraft from a frame that passed CRC (or address filtering)
+    pub fn process(&mut self, frame: &Frame) {
+        let df = frame.downlink_format();
+        let bytes = frame.bytes();
+        let at = frame.received_at();
+
+        let address_type = match df {
+            0 | 4 | 5 | 11 | 16 | 17 | 20 | 21 => Some(AddressType::Icao),
+            18 => Tracker::df18_address_type(bytes),
+            _ => None,
+        };
+        let (address_type, address) = match (address_type, frame.icao()) {
+            (Some(address_type), Some(address)) => (address_type, address),
+            _ => return,
+        };
+
+        self.track(
+            address,
+            address_type,
+            Source::new(address_type, false),
+            at,
+            frame.signal_level(),
+            |ac, config| {
+                match df {
+                    17 | 18 => {
+                        if let Some(message) = frame.parse() {
+                            ac.update(&message, at, config);
+                        }
+                    }
+                    0 | 4 | 16 | 20 => {
+                        if let Some(altitude) = gillham::decode_ac13(es::bits(bytes, 20, 32)) {
+                            ac.altitude = Some(altitude);
+                            ac.alt_is_gnss = Some(false);
+                        }
+                    }
+                    5 | 21 => ac.squawk = Some(gillham::decode_id13(es::bits(bytes, 20, 32))),
+                    _ => {}
+                }
+
+                // flight status of surveillance replies: airborne/ground, alert and SPI
+                if let 4 | 5 | 20 | 21 = df {
+                    match es::bits(bytes, 6, 8) {
+                        0 | 2 => ac.on_ground = Some(false),
+                        1 | 3 => ac.on_ground = Some(true),
+                        _ => {}
+                    }
+                    ac.ident = matches!(es::bits(bytes, 6, 8), 4 | 5);
+                }
+            },
+        );
+    }
+
+    /// Update the tracked aircraft from an error corrected UAT ADS-B frame
+    pub fn process_uat(&mut self, frame: &UatFrame) {
+        if !frame.is_adsb() {
+            return;
+        }
+        let payload = match uat::decode(frame.bytes()) {
+            Some(payload) => payload,
+            None => return,
+        };
+        let address_type = match payload.address_type() {
+            Some(address_type) => address_type,
+            None => return,
+        };
+        let at = frame.received_at();
+
+        self.track(
+            payload.address,
+            address_type,
+            Source::new(address_type, true),
+            at,
+            frame.signal_level(),
+            |ac, config| ac.update_uat(&payload, at, config),
+        );
+    }
+
+    /* Look up (or start tracking) an aircraft, let `update` apply what a
+     * frame says about it and publish what changed. */
+    fn track<F>(
+        &mut self,
+        address: u32,
+        address_type: AddressType,
+        source: Source,
+        at: SystemTime,
+        signal_level: f32,
+        update: F,
+    ) where
+        F: FnOnce(&mut Aircraft, &TrackerConfig),
+    {
+        if at
+            .duration_since(self.last_expire)
+            .is_ok_and(|age| age > Duration::from_secs(1))
+        {
+            self.expire(at);
+        }
+
+        let key = Address::new(address, address_type);
+        let (entry, new) = match self.db.entry(key) {
+            Vacant(entry) => (
+                entry.insert(Arc::new(Aircraft::new(key, address_type, source, at))),
+                true,
+            ),
+            Occupied(entry) => (entry.into_mut(), false),
+        };
+        // copy-on-write, snapshots still holding the aircraft keep the old state
+        let ac = Arc::make_mut(entry);
+        ac.msg_count += 1;
+        ac.last_seen = at;
+        if !ac.sources.contains(&source) {
+            ac.sources.push(source);
+        }
+
+        if !ac.accept_source(address_type, at, self.config.address_type_timeout) {
+            return;
+        }
+
+        let before = ac.clone();
+        ac.source = source;
+        update(ac, &self.config);
+
+        ac.signal_level = Some(match ac.signal_level {
+            Some(level) => level * 0.75 + signal_level * 0.25,
+            None => signal_level,
+        });
+
+        match self.config.ownship_icao {
+            Some(icao) => ac.ownship = ac.address == Address::new(icao, AddressType::Icao),
+            None => match &self.ownship_fix {
+                Some(fix) if (before.latitude, before.longitude) != (ac.latitude, ac.longitude) => {
+                    ownship::detect(ac, fix, at)
+                }
+                _ => {}
+            },
+        }
+
+        let ac = Arc::clone(entry);
+        let mut events = Vec::new();
+        if new {
+            events.push(TrackerEvent::NewAircraft(ac.clone()));
+        } else {
+            let fields = event::changed_fields(&before, &ac);
+            if !fields.is_empty() {
+                events.push(TrackerEvent::Updated {
+                    aircraft: ac.clone(),
+                    fields,
+                });
+            }
+        }
+
+        if let (Some(latitude), Some(longitude)) = (ac.latitude, ac.longitude) {
+            if (before.latitude, before.longitude) != (ac.latitude, ac.longitude) {
+                if let Some(relative) = self.config.receiver.and_then(|r| r.relative(&ac)) {
+                    self.coverage.record(&relative);
+                }
+                events.push(TrackerEvent::PositionUpdate {
+                    address: ac.address,
+                    latitude,
+                    longitude,
+                    altitude: ac.altitude,
+                    at,
+                });
+            }
+        }
+
+        for alert in Tracker::alerts(&before, &ac, at) {
+            debug!("{} alert {:?}", alert.address, alert.kind);
+            events.push(TrackerEvent::Emergency(alert));
+        }
+
+        for event in events {
+            self.publish(event);
+        }
     }
 
     pub fn print(&self) {
         print!("\x1B[2J\x1B[1;1H");
 
-        println!("ICAO\tCALLSIGN\tMSGS");
+        println!("ADDRESS\tTYPE\tCALLSIGN\tALT\tLAT\tLON\tDIST\tBRG\tMSGS");
         for (_, val) in self.db.iter() {
-            println!("{}\t{:?}\t{}\t", val.icao, val.callsign, val.msg_count);
+            let relative = self.config.receiver.and_then(|r| r.relative(val));
+            println!(
+                "{}\t{:?}\t{:?}\t{:?}\t{:?}\t{:?}\t{:?}\t{:?}\t{}\t",
+                val.address,
+                val.address_type,
+                val.callsign,
+                val.altitude,
+                val.latitude,
+                val.longitude,
+                relative.map(|r| (r.distance / METERS_PER_NM).round()),
+                relative.map(|r| r.bearing.round()),
+                val.msg_count
+            );
         }
     }
 }
@@ -0,0 +1,79 @@
+/* Recognizing our own transponder.
+ *
+ * Our own ADS-B Out is received like any other aircraft, right on top of
+ * us, and EFBs will happily alert on it. It's either configured by address,
+ * or detected as the loudest aircraft whose position and altitude keep
+ * matching the GPS.
+ */
+
+use std::time::SystemTime;
+
+use super::Aircraft;
+use crate::geo::{Position, METERS_PER_NM};
+
+/* Average signal level (dBFS) our own transponder is expected to exceed */
+pub const OWNSHIP_MIN_SIGNAL: f32 = -10.0;
+/* How far an aircraft may be from the GPS position and still be us. Allows
+ * for the delay between GPS and ADS-B fixes at cruise speed. */
+pub const OWNSHIP_MAX_DISTANCE: f64 = 0.5 * METERS_PER_NM;
+/* Pressure altitude and GPS altitude differ with the weather, allow for it (ft) */
+pub const OWNSHIP_MAX_ALTITUDE_DIFF: f64 = 500.0;
+/* Consecutive matching positions before an aircraft is taken to be us */
+pub const OWNSHIP_DETECT_MATCHES: u8 = 5;
+
+/// Ownship GPS fix aircraft are compared against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OwnshipFix {
+    pub position: Position,
+    /// Feet MSL
+    pub altitude: Option<f64>,
+    pub at: SystemTime,
+}
+
+impl OwnshipFix {
+    fn matches(&self, ac: &Aircraft) -> bool {
+        let position = match ac.position() {
+            Some(position) => position,
+            None => return false,
+        };
+        if ac
+            .signal_level
+            .is_none_or(|level| level < OWNSHIP_MIN_SIGNAL)
+        {
+            return false;
+        }
+        if self.position.distance(&position) > OWNSHIP_MAX_DISTANCE {
+            return false;
+        }
+
+        match (self.altitude, ac.altitude) {
+            (Some(gps), Some(altitude)) => {
+                (gps - altitude as f64).abs() <= OWNSHIP_MAX_ALTITUDE_DIFF
+            }
+            // can't rule it out, position and signal have to do
+            _ => true,
+        }
+    }
+}
+
+/// Update the ownship flag of an aircraft after a new position. Takes as many
+/// mismatches to clear as it took matches to set, so one bad fix doesn't
+/// make us show up as traffic.
+pub(super) fn detect(ac: &mut Aircraft, fix: &OwnshipFix, at: SystemTime) {
+    let age = at.duration_since(fix.at).unwrap_or_default();
+    if age > crate::gps::ownship::OWNSHIP_FIX_TIMEOUT {
+        return;
+    }
+
+    if fix.matches(ac) {
+        ac.ownship_matches = std::cmp::min(ac.ownship_matches + 1, OWNSHIP_DETECT_MATCHES);
+    } else {
+        ac.ownship_matches = ac.ownship_matches.saturating_sub(1);
+    }
+
+    match ac.ownship_matches {
+        OWNSHIP_DETECT_MATCHES => ac.ownship = true,
+        0 => ac.ownship = false,
+        _ => {}
+    }
+}
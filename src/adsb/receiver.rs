@@ -0,0 +1,130 @@
+/* Where the receiver is, and what it can see from there. */
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::Aircraft;
+use crate::geo::{Position, METERS_PER_NM};
+
+/* Width of the coverage plot sectors */
+pub const COVERAGE_SECTOR_DEGREES: usize = 5;
+const COVERAGE_SECTORS: usize = 360 / COVERAGE_SECTOR_DEGREES;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReceiverLocation {
+    pub position: Position,
+    /// Antenna altitude in ft MSL
+    pub altitude: Option<i32>,
+}
+
+impl ReceiverLocation {
+    pub fn new(latitude: f64, longitude: f64, altitude: Option<i32>) -> ReceiverLocation {
+        ReceiverLocation {
+            position: Position::new(latitude, longitude),
+            altitude,
+        }
+    }
+
+    /// Where an aircraft is as seen from the receiver, if its position is known
+    pub fn relative(&self, ac: &Aircraft) -> Option<Relative> {
+        let position = ac.position()?;
+
+        Some(Relative {
+            distance: self.position.distance(&position),
+            bearing: self.position.bearing(&position),
+            altitude: match (ac.altitude, self.altitude) {
+                (Some(aircraft), Some(receiver)) => Some(aircraft - receiver),
+                _ => None,
+            },
+        })
+    }
+}
+
+/// Parses "lat,lon" or "lat,lon,alt" with altitude in ft
+impl FromStr for ReceiverLocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(',').map(|f| f.trim()).collect();
+        let error = || format!("invalid location '{}' (expected lat,lon[,alt_ft])", s);
+
+        if fields.len() < 2 || fields.len() > 3 {
+            return Err(error());
+        }
+        let latitude: f64 = fields[0].parse().map_err(|_| error())?;
+        let longitude: f64 = fields[1].parse().map_err(|_| error())?;
+        let altitude: Option<i32> = match fields.get(2) {
+            Some(alt) => Some(alt.parse().map_err(|_| error())?),
+            None => None,
+        };
+
+        if latitude.abs() > 90.0 || longitude.abs() > 180.0 {
+            return Err(error());
+        }
+
+        Ok(ReceiverLocation::new(latitude, longitude, altitude))
+    }
+}
+
+/// Position of an aircraft relative to the receiver
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Relative {
+    /// Meters
+    pub distance: f64,
+    /// True bearing from the receiver in degrees
+    pub bearing: f64,
+    /// Feet above (positive) or below the receiver
+    pub altitude: Option<i32>,
+}
+
+/// Furthest position received per bearing sector, a polar plot of antenna coverage
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    sectors: [f64; COVERAGE_SECTORS],
+}
+
+impl Default for Coverage {
+    fn default() -> Coverage {
+        Coverage::new()
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage {
+            sectors: [0.0; COVERAGE_SECTORS],
+        }
+    }
+
+    pub fn record(&mut self, relative: &Relative) {
+        let sector = (relative.bearing as usize / COVERAGE_SECTOR_DEGREES) % COVERAGE_SECTORS;
+        if relative.distance > self.sectors[sector] {
+            self.sectors[sector] = relative.distance;
+        }
+    }
+
+    /// Max range in meters of the sector containing `bearing`, 0 if nothing was seen there
+    pub fn max_range(&self, bearing: f64) -> f64 {
+        let bearing = (bearing % 360.0 + 360.0) % 360.0;
+        self.sectors[(bearing as usize / COVERAGE_SECTOR_DEGREES) % COVERAGE_SECTORS]
+    }
+
+    /// Start bearing and max range of every sector
+    pub fn sectors(&self) -> impl Iterator<Item = (usize, f64)> + '_ {
+        self.sectors
+            .iter()
+            .enumerate()
+            .map(|(i, range)| (i * COVERAGE_SECTOR_DEGREES, *range))
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (bearing, range) in self.sectors() {
+            if range > 0.0 {
+                writeln!(f, "{:03}\u{b0} {:6.1} NM", bearing, range / METERS_PER_NM)?;
+            }
+        }
+        Ok(())
+    }
+}
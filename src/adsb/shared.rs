@@ -0,0 +1,180 @@
+/* Sharing the tracker between tasks.
+ *
+ * Aircraft are kept behind an Arc and updated copy-on-write, so taking a
+ * snapshot only clones pointers and readers never hold the lock for longer
+ * than that. A snapshot stays consistent no matter what the decoder does
+ * in the meantime.
+ */
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+
+use super::receiver::Coverage;
+use super::{
+    Address, AddressType, Aircraft, OwnshipFix, ReceiverLocation, Relative, Tracker, TrackerEvent,
+};
+use crate::geo::Position;
+use crate::sdr::mode_s::Frame;
+use crate::sdr::uat;
+
+/// Point in time copy of the tracked aircraft
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    aircraft: HashMap<Address, Arc<Aircraft>>,
+    receiver: Option<ReceiverLocation>,
+}
+
+impl Snapshot {
+    pub(super) fn new(
+        aircraft: HashMap<Address, Arc<Aircraft>>,
+        receiver: Option<ReceiverLocation>,
+    ) -> Snapshot {
+        Snapshot { aircraft, receiver }
+    }
+
+    pub fn receiver(&self) -> Option<&ReceiverLocation> {
+        self.receiver.as_ref()
+    }
+
+    /// Distance, bearing and altitude of an aircraft from the receiver
+    pub fn relative(&self, ac: &Aircraft) -> Option<Relative> {
+        self.receiver.as_ref()?.relative(ac)
+    }
+
+    pub fn len(&self) -> usize {
+        self.aircraft.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aircraft.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Aircraft>> {
+        self.aircraft.values()
+    }
+
+    /// Our own transponder, if configured or detected
+    pub fn ownship(&self) -> Option<&Arc<Aircraft>> {
+        self.aircraft.values().find(|ac| ac.ownship)
+    }
+
+    /// Everything except our own transponder
+    pub fn traffic(&self) -> impl Iterator<Item = &Arc<Aircraft>> {
+        self.aircraft.values().filter(|ac| !ac.ownship)
+    }
+
+    /// Aircraft with the given (ICAO) address
+    pub fn get(&self, icao: u32) -> Option<&Arc<Aircraft>> {
+        self.aircraft.get(&Address::new(icao, AddressType::Icao))
+    }
+
+    /// Aircraft with a known position within `range` meters of `center`, and if
+    /// `altitude` is given, with a known altitude (ft) inside the band
+    pub fn within(
+        &self,
+        center: &Position,
+        range: f64,
+        altitude: Option<RangeInclusive<i32>>,
+    ) -> Vec<Arc<Aircraft>> {
+        self.aircraft
+            .values()
+            .filter(|ac| {
+                ac.position()
+                    .is_some_and(|position| center.distance(&position) <= range)
+            })
+            .filter(|ac| match &altitude {
+                Some(band) => ac.altitude.is_some_and(|alt| band.contains(&alt)),
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The `n` aircraft closest to `center` with their distance in meters, closest first
+    pub fn nearest(&self, center: &Position, n: usize) -> Vec<(f64, Arc<Aircraft>)> {
+        let mut by_distance: Vec<(f64, Arc<Aircraft>)> = self
+            .aircraft
+            .values()
+            .filter_map(|ac| {
+                ac.position()
+                    .map(|position| (center.distance(&position), ac.clone()))
+            })
+            // a position that isn't a place on earth has no distance to sort by
+            .filter(|(distance, _)| distance.is_finite())
+            .collect();
+
+        by_distance.sort_by(|a, b| a.0.total_cmp(&b.0));
+        by_distance.truncate(n);
+        by_distance
+    }
+}
+
+/// Tracker handle that can be cloned into every task that needs it
+#[derive(Clone)]
+pub struct SharedTracker {
+    inner: Arc<RwLock<Tracker>>,
+}
+
+impl SharedTracker {
+    pub fn new(tracker: Tracker) -> SharedTracker {
+        SharedTracker {
+            inner: Arc::new(RwLock::new(tracker)),
+        }
+    }
+
+    pub fn process(&self, frame: &Frame) {
+        self.inner.write().unwrap().process(frame);
+    }
+
+    pub fn process_uat(&self, frame: &uat::Frame) {
+        self.inner.write().unwrap().process_uat(frame);
+    }
+
+    pub fn expire(&self, now: SystemTime) {
+        self.inner.write().unwrap().expire(now);
+    }
+
+    /// Expire aircraft every `interval` for as long as the stack runs
+    pub async fn expire_every(self, interval: Duration) {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            self.expire(SystemTime::now());
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TrackerEvent> {
+        self.inner.read().unwrap().subscribe()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.inner.read().unwrap().snapshot()
+    }
+
+    pub fn get(&self, icao: u32) -> Option<Arc<Aircraft>> {
+        self.inner.read().unwrap().get(icao)
+    }
+
+    pub fn receiver(&self) -> Option<ReceiverLocation> {
+        self.inner.read().unwrap().receiver().cloned()
+    }
+
+    pub fn set_receiver(&self, receiver: Option<ReceiverLocation>) {
+        self.inner.write().unwrap().set_receiver(receiver);
+    }
+
+    pub fn set_ownship_fix(&self, fix: Option<OwnshipFix>) {
+        self.inner.write().unwrap().set_ownship_fix(fix);
+    }
+
+    pub fn ownship(&self) -> Option<Arc<Aircraft>> {
+        self.inner.read().unwrap().ownship()
+    }
+
+    pub fn coverage(&self) -> Coverage {
+        self.inner.read().unwrap().coverage().clone()
+    }
+}
@@ -0,0 +1,277 @@
+/* UAT ADS-B payload decoding, DO-282B section 2.2.4.5.
+ *
+ * Every payload starts with a header and, for the types aircraft send, a
+ * state vector. The payload type says what follows in a long frame:
+ *
+ *   type  bytes 0-3  4-16  17-28        29-33
+ *   0     HDR        SV                              (basic frame)
+ *   1     HDR        SV    mode status  aux SV
+ *   2     HDR        SV    reserved     aux SV
+ *   3     HDR        SV    mode status  target state
+ *   4     HDR        SV    reserved     target state
+ *   5     HDR        SV    reserved     aux SV
+ *   6     HDR        SV    target state aux SV
+ *
+ * Positions are sent in full (no CPR), in units of 360 / 2^24 degrees.
+ * Target states aren't decoded.
+ */
+
+use super::es::{bit, bits, EmergencyState};
+use super::AddressType;
+use crate::geo::Position;
+
+/// Emitter category, callsign and flight plan ID characters (base 40)
+const BASE40_CHARSET: &[u8; 40] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ  ..";
+
+/* Header and state vector, all a basic frame has */
+pub const UAT_STATE_VECTOR_BYTES: usize = 17;
+pub const UAT_LONG_PAYLOAD_BYTES: usize = 34;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateVector {
+    pub position: Option<Position>,
+    pub altitude: Option<i32>,
+    /// Altitude is geometric instead of pressure altitude
+    pub alt_is_gnss: bool,
+    pub nic: u8,
+    /// None if the air/ground state is reserved
+    pub on_ground: Option<bool>,
+    pub ground_speed: Option<f64>,
+    pub track: Option<f64>,
+    pub heading: Option<f64>,
+    pub vertical_rate: Option<i32>,
+    pub utc_coupled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModeStatus {
+    /// Emitter category set (A-D) and category as 0xA0..0xD7, like ES
+    pub category: u8,
+    pub callsign: Option<String>,
+    /// Mode 3/A code sent as flight plan ID instead of a callsign, in 0xABCD form
+    pub squawk: Option<u16>,
+    pub emergency: EmergencyState,
+    pub uat_version: u8,
+    pub sil: u8,
+    pub nac_p: u8,
+    pub nac_v: u8,
+    pub nic_baro: bool,
+    pub tcas_operational: bool,
+    pub tcas_ra_active: bool,
+    pub ident: bool,
+    /// Headings are relative to magnetic instead of true north
+    pub heading_magnetic: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuxStateVector {
+    /// Altitude of the other type than the state vector's
+    pub secondary_altitude: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payload {
+    pub payload_type: u8,
+    pub address_qualifier: u8,
+    pub address: u32,
+    pub state_vector: Option<StateVector>,
+    pub mode_status: Option<ModeStatus>,
+    pub aux_state_vector: Option<AuxStateVector>,
+}
+
+impl Payload {
+    /// Tracker address type, None for reserved qualifiers
+    pub fn address_type(&self) -> Option<AddressType> {
+        match self.address_qualifier {
+            0 => Some(AddressType::Icao),
+            // self-assigned, surface vehicles and fixed beacons
+            1 | 4 | 5 => Some(AddressType::NonIcao),
+            2 => Some(AddressType::TisB),
+            3 => Some(AddressType::TisBOther),
+            6 => Some(AddressType::AdsR),
+            _ => None,
+        }
+    }
+}
+
+/// Decode an error corrected basic (18 byte) or long (34 byte) ADS-B payload
+pub fn decode(payload: &[u8]) -> Option<Payload> {
+    if payload.len() < UAT_STATE_VECTOR_BYTES {
+        return None;
+    }
+
+    let payload_type = bits(payload, 1, 5) as u8;
+    let long = payload.len() >= UAT_LONG_PAYLOAD_BYTES;
+    if (payload_type == 0) == long {
+        return None;
+    }
+
+    let state_vector = match payload_type {
+        0..=10 => Some(decode_state_vector(payload)),
+        _ => None,
+    };
+    let mode_status = match payload_type {
+        1 | 3 => Some(decode_mode_status(payload)),
+        _ => None,
+    };
+    let aux_state_vector = match payload_type {
+        1 | 2 | 5 | 6 => Some(decode_aux_state_vector(payload)),
+        _ => None,
+    };
+
+    Some(Payload {
+        payload_type,
+        address_qualifier: bits(payload, 6, 8) as u8,
+        address: bits(payload, 9, 32),
+        state_vector,
+        mode_status,
+        aux_state_vector,
+    })
+}
+
+fn decode_altitude(raw: u32) -> Option<i32> {
+    match raw {
+        0 => None,
+        v => Some((v as i32 - 1) * 25 - 1000),
+    }
+}
+
+/* Velocities are 10 bit magnitudes plus one, with a sign bit in front */
+fn decode_velocity(raw: u32, multiplier: u32) -> Option<f64> {
+    match raw & 0x3ff {
+        0 => None,
+        v if raw & 0x400 != 0 => Some(-(((v - 1) * multiplier) as f64)),
+        v => Some(((v - 1) * multiplier) as f64),
+    }
+}
+
+fn decode_state_vector(payload: &[u8]) -> StateVector {
+    let raw_lat = bits(payload, 33, 55);
+    let raw_lon = bits(payload, 56, 79);
+    let nic = bits(payload, 93, 96) as u8;
+
+    let position = match (raw_lat, raw_lon, nic) {
+        (0, 0, 0) => None,
+        _ => {
+            let mut latitude = raw_lat as f64 * 360.0 / (1 << 24) as f64;
+            if latitude > 90.0 {
+                latitude -= 180.0;
+            }
+            let mut longitude = raw_lon as f64 * 360.0 / (1 << 24) as f64;
+            if longitude > 180.0 {
+                longitude -= 360.0;
+            }
+            Some(Position::new(latitude, longitude))
+        }
+    };
+
+    let mut sv = StateVector {
+        position,
+        altitude: decode_altitude(bits(payload, 81, 92)),
+        alt_is_gnss: bit(payload, 80),
+        nic,
+        on_ground: None,
+        ground_speed: None,
+        track: None,
+        heading: None,
+        vertical_rate: None,
+        utc_coupled: bit(payload, 133),
+    };
+
+    let air_ground = bits(payload, 97, 98);
+    match air_ground {
+        // subsonic and supersonic (4 times the unit)
+        0 | 1 => {
+            let multiplier = if air_ground == 1 { 4 } else { 1 };
+            sv.on_ground = Some(false);
+
+            let ns = decode_velocity(bits(payload, 100, 110), multiplier);
+            let ew = decode_velocity(bits(payload, 111, 121), multiplier);
+            if let (Some(ns), Some(ew)) = (ns, ew) {
+                sv.ground_speed = Some((ew * ew + ns * ns).sqrt());
+                let track = ew.atan2(ns).to_degrees();
+                sv.track = Some(if track < 0.0 { track + 360.0 } else { track });
+            }
+
+            // source bit (baro/geometric) in front of the sign
+            sv.vertical_rate = match bits(payload, 124, 132) {
+                0 => None,
+                v if bit(payload, 123) => Some(-((v as i32 - 1) * 64)),
+                v => Some((v as i32 - 1) * 64),
+            };
+        }
+        2 => {
+            sv.on_ground = Some(true);
+            sv.ground_speed = match bits(payload, 101, 110) {
+                0 => None,
+                v => Some((v - 1) as f64),
+            };
+
+            let angle = bits(payload, 113, 121) as f64 * 360.0 / 512.0;
+            match bits(payload, 111, 112) {
+                1 => sv.track = Some(angle),
+                2 | 3 => sv.heading = Some(angle),
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+
+    sv
+}
+
+fn decode_base40(payload: &[u8], first: usize) -> [u8; 3] {
+    let raw = bits(payload, first, first + 15);
+    [
+        BASE40_CHARSET[(raw / 1600 % 40) as usize],
+        BASE40_CHARSET[(raw / 40 % 40) as usize],
+        BASE40_CHARSET[(raw % 40) as usize],
+    ]
+}
+
+fn decode_mode_status(payload: &[u8]) -> ModeStatus {
+    // the first character slot holds the emitter category
+    let category = bits(payload, 137, 152) / 1600 % 40;
+    let mut chars = decode_base40(payload, 137)[1..].to_vec();
+    chars.extend_from_slice(&decode_base40(payload, 153));
+    chars.extend_from_slice(&decode_base40(payload, 169));
+    let text = String::from_utf8_lossy(&chars).trim_end().to_string();
+
+    // without the call sign ID flag the characters are a flight plan ID, the squawk
+    let (callsign, squawk) = match bit(payload, 215) {
+        true if !text.is_empty() => (Some(text), None),
+        true => (None, None),
+        false => {
+            let squawk = chars[..4].iter().try_fold(0u16, |code, &c| match c {
+                b'0'..=b'7' => Some(code << 4 | (c - b'0') as u16),
+                _ => None,
+            });
+            (None, squawk)
+        }
+    };
+
+    ModeStatus {
+        category: match category {
+            0..=31 => (((0xa + category / 8) << 4) | (category % 8)) as u8,
+            _ => 0,
+        },
+        callsign,
+        squawk,
+        emergency: EmergencyState::from_code(bits(payload, 185, 187)),
+        uat_version: bits(payload, 188, 190) as u8,
+        sil: bits(payload, 191, 192) as u8,
+        nac_p: bits(payload, 201, 204) as u8,
+        nac_v: bits(payload, 205, 207) as u8,
+        nic_baro: bit(payload, 208),
+        tcas_operational: bit(payload, 210),
+        tcas_ra_active: bit(payload, 211),
+        ident: bit(payload, 212),
+        heading_magnetic: bit(payload, 214),
+    }
+}
+
+fn decode_aux_state_vector(payload: &[u8]) -> AuxStateVector {
+    AuxStateVector {
+        secondary_altitude: decode_altitude(bits(payload, 233, 244)),
+    }
+}
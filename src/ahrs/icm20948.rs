@@ -0,0 +1,170 @@
+/* TDK InvenSense ICM-20948 over Linux i2c-dev.
+ *
+ * Registers are spread over four banks switched through REG_BANK_SEL.
+ * Gyro at ±500 °/s and accelerometer at ±4 g with their low pass filters
+ * on. The AK09916 magnetometer inside sits behind the I2C bypass at its
+ * own address, with y and z flipped relative to the accelerometer.
+ */
+
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::io;
+use std::time::Instant;
+
+use super::{gyro_bias, Imu, ImuSample, Vector};
+
+pub const ICM20948_WHO_AM_I: u8 = 0xea;
+pub const AK09916_ADDRESS: u16 = 0x0c;
+pub const AK09916_WIA2: u8 = 0x09;
+
+const REG_BANK_SEL: u8 = 0x7f;
+
+// bank 0
+const REG_WHO_AM_I: u8 = 0x00;
+const REG_USER_CTRL: u8 = 0x03;
+const REG_PWR_MGMT_1: u8 = 0x06;
+const REG_PWR_MGMT_2: u8 = 0x07;
+const REG_INT_PIN_CFG: u8 = 0x0f;
+const REG_ACCEL_XOUT_H: u8 = 0x2d;
+
+// bank 2
+const REG_GYRO_SMPLRT_DIV: u8 = 0x00;
+const REG_GYRO_CONFIG_1: u8 = 0x01;
+const REG_ACCEL_SMPLRT_DIV_2: u8 = 0x11;
+const REG_ACCEL_CONFIG: u8 = 0x14;
+
+const AK_REG_WIA2: u8 = 0x01;
+const AK_REG_ST1: u8 = 0x10;
+const AK_REG_HXL: u8 = 0x11;
+const AK_REG_CNTL2: u8 = 0x31;
+
+// LSB per g at ±4 g, per °/s at ±500 °/s, µT per LSB
+const ACCEL_SCALE: f64 = 8192.0;
+const GYRO_SCALE: f64 = 65.5;
+const MAG_SCALE: f64 = 0.15;
+
+pub struct Icm20948 {
+    dev: LinuxI2CDevice,
+    mag: Option<LinuxI2CDevice>,
+    last_mag: Option<Vector>,
+    gyro_bias: Vector,
+    opened: Instant,
+}
+
+impl Icm20948 {
+    pub fn open(bus: &str, address: u16) -> io::Result<Icm20948> {
+        let mut dev = LinuxI2CDevice::new(bus, address)?;
+        dev.smbus_write_byte_data(REG_BANK_SEL, 0 << 4)?;
+        let id = dev.smbus_read_byte_data(REG_WHO_AM_I)?;
+        if id != ICM20948_WHO_AM_I {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("who am i {:#x} is not an ICM-20948", id),
+            ));
+        }
+
+        // wake up with the best clock, everything on, magnetometer bypass
+        dev.smbus_write_byte_data(REG_PWR_MGMT_1, 0x01)?;
+        dev.smbus_write_byte_data(REG_PWR_MGMT_2, 0x00)?;
+        dev.smbus_write_byte_data(REG_USER_CTRL, 0x00)?;
+        dev.smbus_write_byte_data(REG_INT_PIN_CFG, 0x02)?;
+
+        // ~225 Hz sample rates, dlpf 3, ±500 °/s and ±4 g
+        dev.smbus_write_byte_data(REG_BANK_SEL, 2 << 4)?;
+        dev.smbus_write_byte_data(REG_GYRO_SMPLRT_DIV, 0x04)?;
+        dev.smbus_write_byte_data(REG_GYRO_CONFIG_1, 0x1b)?;
+        dev.smbus_write_byte_data(REG_ACCEL_SMPLRT_DIV_2, 0x04)?;
+        dev.smbus_write_byte_data(REG_ACCEL_CONFIG, 0x1b)?;
+        dev.smbus_write_byte_data(REG_BANK_SEL, 0 << 4)?;
+
+        let mag = match Icm20948::open_mag(bus) {
+            Ok(mag) => Some(mag),
+            Err(e) => {
+                log::warn!("icm20948: no magnetometer, heading unavailable: {}", e);
+                None
+            }
+        };
+
+        let mut imu = Icm20948 {
+            dev,
+            mag,
+            last_mag: None,
+            gyro_bias: [0.0; 3],
+            opened: Instant::now(),
+        };
+        imu.gyro_bias = gyro_bias(|| imu.read_motion().map(|(_, gyro)| gyro))?;
+        Ok(imu)
+    }
+
+    fn open_mag(bus: &str) -> io::Result<LinuxI2CDevice> {
+        let mut mag = LinuxI2CDevice::new(bus, AK09916_ADDRESS)?;
+        let id = mag.smbus_read_byte_data(AK_REG_WIA2)?;
+        if id != AK09916_WIA2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("wia2 {:#x} is not an AK09916", id),
+            ));
+        }
+
+        // continuous measurement at 100 Hz
+        mag.smbus_write_byte_data(AK_REG_CNTL2, 0x08)?;
+        Ok(mag)
+    }
+
+    fn read_motion(&mut self) -> io::Result<(Vector, Vector)> {
+        let mut data = [0u8; 12];
+        self.dev.write(&[REG_ACCEL_XOUT_H])?;
+        self.dev.read(&mut data)?;
+
+        let raw = |i: usize| i16::from_be_bytes([data[i], data[i + 1]]) as f64;
+        Ok((
+            [
+                raw(0) / ACCEL_SCALE,
+                raw(2) / ACCEL_SCALE,
+                raw(4) / ACCEL_SCALE,
+            ],
+            [
+                raw(6) / GYRO_SCALE,
+                raw(8) / GYRO_SCALE,
+                raw(10) / GYRO_SCALE,
+            ],
+        ))
+    }
+
+    fn read_mag(&mut self) -> io::Result<Option<Vector>> {
+        let mag = match &mut self.mag {
+            Some(mag) => mag,
+            None => return Ok(None),
+        };
+        if mag.smbus_read_byte_data(AK_REG_ST1)? & 0x01 == 0 {
+            return Ok(self.last_mag);
+        }
+
+        // HXL..HZH, a dummy and ST2, reading ST2 releases the data registers
+        let mut data = [0u8; 8];
+        mag.write(&[AK_REG_HXL])?;
+        mag.read(&mut data)?;
+        if data[7] & 0x08 != 0 {
+            return Ok(self.last_mag);
+        }
+
+        let raw = |i: usize| i16::from_le_bytes([data[i], data[i + 1]]) as f64 * MAG_SCALE;
+        self.last_mag = Some([raw(0), -raw(2), -raw(4)]);
+        Ok(self.last_mag)
+    }
+}
+
+impl Imu for Icm20948 {
+    fn read(&mut self) -> io::Result<ImuSample> {
+        let (accel, gyro) = self.read_motion()?;
+        let mag = self.read_mag()?;
+        let bias = self.gyro_bias;
+
+        Ok(ImuSample {
+            at: self.opened.elapsed(),
+            accel,
+            gyro: [gyro[0] - bias[0], gyro[1] - bias[1], gyro[2] - bias[2]],
+            mag,
+        })
+    }
+}
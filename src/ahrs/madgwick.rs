@@ -0,0 +1,234 @@
+/* Madgwick's gradient descent orientation filter.
+ *
+ * The gyro is integrated into an orientation quaternion, and every sample
+ * nudges it (by at most `beta` rad/s) towards where the accelerometer says
+ * up is, and with a magnetometer where it says north is. The quaternion
+ * rotates sensor frame vectors into the earth frame, which has x north
+ * and z up.
+ *
+ * Madgwick, "An efficient orientation filter for inertial and
+ * inertial/magnetic sensor arrays", 2010.
+ */
+
+pub type Vector = [f64; 3];
+
+/* Correction gain, higher converges faster but lets more of the
+ * accelerometer's noise through */
+pub const MADGWICK_BETA: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Madgwick {
+    pub beta: f64,
+    q: [f64; 4],
+}
+
+impl Madgwick {
+    pub fn new(beta: f64) -> Madgwick {
+        Madgwick {
+            beta,
+            q: [1.0, 0.0, 0.0, 0.0],
+        }
+    }
+
+    pub fn quaternion(&self) -> [f64; 4] {
+        self.q
+    }
+
+    /// Start from the attitude the accelerometer (and magnetometer) show
+    /// instead of converging to it from level
+    pub fn init(&mut self, accel: Vector, mag: Option<Vector>) {
+        let (roll, pitch) = (
+            accel[1].atan2(accel[2]),
+            (-accel[0]).atan2((accel[1] * accel[1] + accel[2] * accel[2]).sqrt()),
+        );
+        self.q = from_euler(roll, pitch, 0.0);
+
+        if let Some(mag) = mag {
+            // turn so the horizontal part of the field points north
+            let h = self.rotate(mag);
+            let yaw = h[1].atan2(h[0]);
+            self.q = multiply(from_euler(0.0, 0.0, -yaw), self.q);
+        }
+    }
+
+    /// Sensor frame vector in the earth frame
+    pub fn rotate(&self, v: Vector) -> Vector {
+        let [q0, q1, q2, q3] = self.q;
+        [
+            (1.0 - 2.0 * (q2 * q2 + q3 * q3)) * v[0]
+                + 2.0 * (q1 * q2 - q0 * q3) * v[1]
+                + 2.0 * (q1 * q3 + q0 * q2) * v[2],
+            2.0 * (q1 * q2 + q0 * q3) * v[0]
+                + (1.0 - 2.0 * (q1 * q1 + q3 * q3)) * v[1]
+                + 2.0 * (q2 * q3 - q0 * q1) * v[2],
+            2.0 * (q1 * q3 - q0 * q2) * v[0]
+                + 2.0 * (q2 * q3 + q0 * q1) * v[1]
+                + (1.0 - 2.0 * (q1 * q1 + q2 * q2)) * v[2],
+        ]
+    }
+
+    /// Roll, pitch and yaw in radians, yaw counterclockwise from north
+    pub fn euler(&self) -> (f64, f64, f64) {
+        let [q0, q1, q2, q3] = self.q;
+        (
+            (q0 * q1 + q2 * q3).atan2(0.5 - q1 * q1 - q2 * q2),
+            (-2.0 * (q1 * q3 - q0 * q2)).clamp(-1.0, 1.0).asin(),
+            (q1 * q2 + q0 * q3).atan2(0.5 - q2 * q2 - q3 * q3),
+        )
+    }
+
+    /// One sample: gyro in rad/s, accelerometer and magnetometer in any
+    /// unit, `dt` in seconds
+    pub fn update(&mut self, gyro: Vector, accel: Vector, mag: Option<Vector>, dt: f64) {
+        let [q0, q1, q2, q3] = self.q;
+        let [gx, gy, gz] = gyro;
+
+        let mut q_dot = [
+            0.5 * (-q1 * gx - q2 * gy - q3 * gz),
+            0.5 * (q0 * gx + q2 * gz - q3 * gy),
+            0.5 * (q0 * gy - q1 * gz + q3 * gx),
+            0.5 * (q0 * gz + q1 * gy - q2 * gx),
+        ];
+
+        let step = match (normalize(accel), mag.and_then(normalize)) {
+            (Some(a), Some(m)) => Some(self.marg_step(a, m)),
+            (Some(a), None) => Some(self.imu_step(a)),
+            // free fall, nothing to correct with
+            _ => None,
+        };
+        if let Some(s) = step.and_then(normalize4) {
+            for i in 0..4 {
+                q_dot[i] -= self.beta * s[i];
+            }
+        }
+
+        let q = [
+            q0 + q_dot[0] * dt,
+            q1 + q_dot[1] * dt,
+            q2 + q_dot[2] * dt,
+            q3 + q_dot[3] * dt,
+        ];
+        self.q = normalize4(q).unwrap_or([1.0, 0.0, 0.0, 0.0]);
+    }
+
+    // gradient of the error between measured and predicted gravity
+    fn imu_step(&self, a: Vector) -> [f64; 4] {
+        let [q0, q1, q2, q3] = self.q;
+        let [ax, ay, az] = a;
+        let (q1q1, q2q2) = (q1 * q1, q2 * q2);
+
+        [
+            4.0 * q0 * q2q2 + 2.0 * q2 * ax + 4.0 * q0 * q1q1 - 2.0 * q1 * ay,
+            4.0 * q1 * q3 * q3 - 2.0 * q3 * ax + 4.0 * q0 * q0 * q1 - 2.0 * q0 * ay - 4.0 * q1
+                + 8.0 * q1 * q1q1
+                + 8.0 * q1 * q2q2
+                + 4.0 * q1 * az,
+            4.0 * q0 * q0 * q2 + 2.0 * q0 * ax + 4.0 * q2 * q3 * q3 - 2.0 * q3 * ay - 4.0 * q2
+                + 8.0 * q2 * q1q1
+                + 8.0 * q2 * q2q2
+                + 4.0 * q2 * az,
+            4.0 * q1q1 * q3 - 2.0 * q1 * ax + 4.0 * q2q2 * q3 - 2.0 * q2 * ay,
+        ]
+    }
+
+    // gradient of the error in gravity and magnetic field
+    fn marg_step(&self, a: Vector, m: Vector) -> [f64; 4] {
+        let [q0, q1, q2, q3] = self.q;
+        let [ax, ay, az] = a;
+        let [mx, my, mz] = m;
+
+        // earth frame field, flattened onto the x-z plane
+        let h = self.rotate(m);
+        let bx = (h[0] * h[0] + h[1] * h[1]).sqrt();
+        let bz = h[2];
+
+        // predicted minus measured gravity and field
+        let f = [
+            2.0 * (q1 * q3 - q0 * q2) - ax,
+            2.0 * (q0 * q1 + q2 * q3) - ay,
+            2.0 * (0.5 - q1 * q1 - q2 * q2) - az,
+            2.0 * bx * (0.5 - q2 * q2 - q3 * q3) + 2.0 * bz * (q1 * q3 - q0 * q2) - mx,
+            2.0 * bx * (q1 * q2 - q0 * q3) + 2.0 * bz * (q0 * q1 + q2 * q3) - my,
+            2.0 * bx * (q0 * q2 + q1 * q3) + 2.0 * bz * (0.5 - q1 * q1 - q2 * q2) - mz,
+        ];
+
+        // transposed jacobian of f times f
+        let j = [
+            [
+                -2.0 * q2,
+                2.0 * q1,
+                0.0,
+                -2.0 * bz * q2,
+                -2.0 * bx * q3 + 2.0 * bz * q1,
+                2.0 * bx * q2,
+            ],
+            [
+                2.0 * q3,
+                2.0 * q0,
+                -4.0 * q1,
+                2.0 * bz * q3,
+                2.0 * bx * q2 + 2.0 * bz * q0,
+                2.0 * bx * q3 - 4.0 * bz * q1,
+            ],
+            [
+                -2.0 * q0,
+                2.0 * q3,
+                -4.0 * q2,
+                -4.0 * bx * q2 - 2.0 * bz * q0,
+                2.0 * bx * q1 + 2.0 * bz * q3,
+                2.0 * bx * q0 - 4.0 * bz * q2,
+            ],
+            [
+                2.0 * q1,
+                2.0 * q2,
+                0.0,
+                -4.0 * bx * q3 + 2.0 * bz * q1,
+                -2.0 * bx * q0 + 2.0 * bz * q2,
+                2.0 * bx * q1,
+            ],
+        ];
+
+        let mut s = [0.0; 4];
+        for i in 0..4 {
+            s[i] = j[i].iter().zip(f.iter()).map(|(j, f)| j * f).sum();
+        }
+        s
+    }
+}
+
+fn from_euler(roll: f64, pitch: f64, yaw: f64) -> [f64; 4] {
+    let (sr, cr) = (roll / 2.0).sin_cos();
+    let (sp, cp) = (pitch / 2.0).sin_cos();
+    let (sy, cy) = (yaw / 2.0).sin_cos();
+    [
+        cr * cp * cy + sr * sp * sy,
+        sr * cp * cy - cr * sp * sy,
+        cr * sp * cy + sr * cp * sy,
+        cr * cp * sy - sr * sp * cy,
+    ]
+}
+
+fn multiply(a: [f64; 4], b: [f64; 4]) -> [f64; 4] {
+    [
+        a[0] * b[0] - a[1] * b[1] - a[2] * b[2] - a[3] * b[3],
+        a[0] * b[1] + a[1] * b[0] + a[2] * b[3] - a[3] * b[2],
+        a[0] * b[2] - a[1] * b[3] + a[2] * b[0] + a[3] * b[1],
+        a[0] * b[3] + a[1] * b[2] - a[2] * b[1] + a[3] * b[0],
+    ]
+}
+
+fn normalize(v: Vector) -> Option<Vector> {
+    let norm = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    match norm > 0.0 {
+        true => Some([v[0] / norm, v[1] / norm, v[2] / norm]),
+        false => None,
+    }
+}
+
+fn normalize4(q: [f64; 4]) -> Option<[f64; 4]> {
+    let norm = q.iter().map(|x| x * x).sum::<f64>().sqrt();
+    match norm > 0.0 {
+        true => Some([q[0] / norm, q[1] / norm, q[2] / norm, q[3] / norm]),
+        false => None,
+    }
+}
@@ -0,0 +1,224 @@
+/* Attitude and heading reference from an IMU.
+ *
+ * Gyro, accelerometer and (if there is one) magnetometer samples go
+ * through a Madgwick filter. The IMU is assumed to be mounted with its x
+ * axis pointing forward and z up, which makes y the left wing.
+ *
+ * In a sustained turn the accelerometer feels the centripetal acceleration
+ * as well as gravity and a plain filter slowly levels the wings. With a GPS
+ * ground speed the centripetal part (turn rate times speed) is taken out
+ * before the accelerometer is trusted as a vertical reference.
+ */
+
+pub mod icm20948;
+pub mod madgwick;
+pub mod mpu9250;
+pub mod replay;
+
+use log::*;
+use std::io;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+
+use crate::geo::METERS_PER_NM;
+use crate::gps::Ownship;
+pub use icm20948::Icm20948;
+pub use madgwick::{Madgwick, Vector, MADGWICK_BETA};
+pub use mpu9250::Mpu9250;
+pub use replay::Replay;
+
+/// Standard gravity in m/s²
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+
+/* Addresses an MPU-9250/ICM-20948 can be strapped to */
+pub const IMU_I2C_ADDRESSES: [u16; 2] = [0x68, 0x69];
+
+/* Samples averaged for the gyro bias on startup, the IMU has to be still */
+pub const IMU_CALIBRATION_SAMPLES: usize = 100;
+
+/* An attitude older than this is no longer sent */
+pub const AHRS_TIMEOUT: Duration = Duration::from_secs(1);
+
+const IMU_INTERVAL: Duration = Duration::from_millis(20);
+
+/* Gaps in the samples longer than this restart the filter */
+const IMU_MAX_GAP: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImuSample {
+    /// Since the IMU was opened (or the start of a recording)
+    pub at: Duration,
+    /// g
+    pub accel: Vector,
+    /// Degrees per second
+    pub gyro: Vector,
+    /// Microtesla
+    pub mag: Option<Vector>,
+}
+
+pub trait Imu {
+    fn read(&mut self) -> io::Result<ImuSample>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Attitude {
+    /// Degrees, right wing down positive
+    pub roll: f64,
+    /// Degrees, nose up positive
+    pub pitch: f64,
+    /// Degrees magnetic, only with a magnetometer
+    pub heading: Option<f64>,
+    /// Degrees per second, right positive
+    pub turn_rate: f64,
+    /// Load factor along the vertical axis, 1 in level flight
+    pub g_load: f64,
+    /// Degrees the ball is off center, right positive
+    pub slip_skid: f64,
+    pub last_update: Option<SystemTime>,
+}
+
+impl Attitude {
+    pub fn valid(&self, now: SystemTime) -> bool {
+        self.last_update.is_some_and(|at| {
+            now.duration_since(at).unwrap_or_default() <= AHRS_TIMEOUT
+        })
+    }
+}
+
+pub struct Ahrs {
+    filter: Madgwick,
+    last: Option<Duration>,
+    attitude: Attitude,
+}
+
+impl Default for Ahrs {
+    fn default() -> Ahrs {
+        Ahrs::new()
+    }
+}
+
+impl Ahrs {
+    pub fn new() -> Ahrs {
+        Ahrs {
+            filter: Madgwick::new(MADGWICK_BETA),
+            last: None,
+            attitude: Attitude::default(),
+        }
+    }
+
+    pub fn attitude(&self) -> &Attitude {
+        &self.attitude
+    }
+
+    /// Apply a sample, `ground_speed` in knots
+    pub fn update(&mut self, sample: &ImuSample, ground_speed: Option<f64>) -> &Attitude {
+        let gyro = [
+            sample.gyro[0].to_radians(),
+            sample.gyro[1].to_radians(),
+            sample.gyro[2].to_radians(),
+        ];
+
+        // centripetal acceleration of flying forward while rotating, ω × v
+        let gravity = match ground_speed {
+            Some(speed) => {
+                let v = speed * METERS_PER_NM / 3600.0 / STANDARD_GRAVITY;
+                [
+                    sample.accel[0],
+                    sample.accel[1] - gyro[2] * v,
+                    sample.accel[2] + gyro[1] * v,
+                ]
+            }
+            None => sample.accel,
+        };
+
+        let dt = self.last.and_then(|last| sample.at.checked_sub(last));
+        match dt {
+            Some(dt) if dt <= IMU_MAX_GAP => {
+                self.filter
+                    .update(gyro, gravity, sample.mag, dt.as_secs_f64())
+            }
+            _ => self.filter.init(gravity, sample.mag),
+        }
+        self.last = Some(sample.at);
+
+        let (roll, pitch, yaw) = self.filter.euler();
+        // rotation about the earth's vertical, counterclockwise
+        let yaw_rate = self.filter.rotate(gyro)[2];
+        let [_, ay, az] = sample.accel;
+
+        self.attitude = Attitude {
+            roll: roll.to_degrees(),
+            pitch: -pitch.to_degrees(),
+            heading: sample.mag.map(|_| (-yaw.to_degrees()).rem_euclid(360.0)),
+            turn_rate: -yaw_rate.to_degrees(),
+            g_load: az,
+            slip_skid: ay.atan2(az).to_degrees(),
+            last_update: Some(SystemTime::now()),
+        };
+        &self.attitude
+    }
+}
+
+/// Average gyro reading of an IMU at rest
+pub fn gyro_bias<F: FnMut() -> io::Result<Vector>>(mut read: F) -> io::Result<Vector> {
+    let mut sum = [0.0; 3];
+    for _ in 0..IMU_CALIBRATION_SAMPLES {
+        let gyro = read()?;
+        for i in 0..3 {
+            sum[i] += gyro[i];
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let n = IMU_CALIBRATION_SAMPLES as f64;
+    Ok([sum[0] / n, sum[1] / n, sum[2] / n])
+}
+
+/// Open an IMU: an i2c bus (`/dev/i2c-1`) probed for an ICM-20948 or
+/// MPU-9250, or anything else as a replay file.
+pub fn open(source: &str) -> io::Result<Box<dyn Imu + Send>> {
+    if !source.starts_with("/dev/") {
+        return Ok(Box::new(Replay::from_file(source)?));
+    }
+
+    for &address in IMU_I2C_ADDRESSES.iter() {
+        if let Ok(imu) = Icm20948::open(source, address) {
+            info!("ahrs: ICM-20948 at {}:{:#x}", source, address);
+            return Ok(Box::new(imu));
+        }
+        if let Ok(imu) = Mpu9250::open(source, address) {
+            info!("ahrs: MPU-9250 at {}:{:#x}", source, address);
+            return Ok(Box::new(imu));
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no MPU-9250/ICM-20948 on {}", source),
+    ))
+}
+
+/// Fuse IMU samples into the attitude until the IMU fails or a replay ends
+pub async fn run(
+    mut imu: Box<dyn Imu + Send>,
+    ownship: watch::Receiver<Ownship>,
+    attitude: watch::Sender<Attitude>,
+) -> io::Result<()> {
+    let mut ahrs = Ahrs::new();
+    let mut interval = tokio::time::interval(IMU_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let sample = match imu.read() {
+            Ok(sample) => sample,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let ground_speed = {
+            let gps = ownship.borrow();
+            gps.ground_speed.filter(|_| gps.valid(SystemTime::now()))
+        };
+
+        let _ = attitude.send(*ahrs.update(&sample, ground_speed));
+    }
+}
@@ -0,0 +1,176 @@
+/* InvenSense MPU-9250 over Linux i2c-dev.
+ *
+ * Gyro at ±500 °/s and accelerometer at ±4 g, both behind a 41 Hz low
+ * pass filter. The AK8963 magnetometer inside sits behind the I2C bypass
+ * at its own address, with its x and y axes swapped and z flipped
+ * relative to the accelerometer.
+ */
+
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::io;
+use std::time::Instant;
+
+use super::{gyro_bias, Imu, ImuSample, Vector};
+
+pub const MPU9250_WHO_AM_I: u8 = 0x71;
+pub const MPU9255_WHO_AM_I: u8 = 0x73;
+pub const AK8963_ADDRESS: u16 = 0x0c;
+pub const AK8963_WIA: u8 = 0x48;
+
+const REG_SMPLRT_DIV: u8 = 0x19;
+const REG_CONFIG: u8 = 0x1a;
+const REG_GYRO_CONFIG: u8 = 0x1b;
+const REG_ACCEL_CONFIG: u8 = 0x1c;
+const REG_ACCEL_CONFIG2: u8 = 0x1d;
+const REG_INT_PIN_CFG: u8 = 0x37;
+const REG_ACCEL_XOUT_H: u8 = 0x3b;
+const REG_PWR_MGMT_1: u8 = 0x6b;
+const REG_WHO_AM_I: u8 = 0x75;
+
+const AK_REG_WIA: u8 = 0x00;
+const AK_REG_ST1: u8 = 0x02;
+const AK_REG_HXL: u8 = 0x03;
+const AK_REG_CNTL1: u8 = 0x0a;
+const AK_REG_ASAX: u8 = 0x10;
+
+// LSB per g at ±4 g, per °/s at ±500 °/s, µT per LSB at 16 bit
+const ACCEL_SCALE: f64 = 8192.0;
+const GYRO_SCALE: f64 = 65.5;
+const MAG_SCALE: f64 = 0.15;
+
+pub struct Mpu9250 {
+    dev: LinuxI2CDevice,
+    mag: Option<LinuxI2CDevice>,
+    /// Factory sensitivity adjustment of the magnetometer axes
+    mag_adjust: Vector,
+    last_mag: Option<Vector>,
+    gyro_bias: Vector,
+    opened: Instant,
+}
+
+impl Mpu9250 {
+    pub fn open(bus: &str, address: u16) -> io::Result<Mpu9250> {
+        let mut dev = LinuxI2CDevice::new(bus, address)?;
+        let id = dev.smbus_read_byte_data(REG_WHO_AM_I)?;
+        if id != MPU9250_WHO_AM_I && id != MPU9255_WHO_AM_I {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("who am i {:#x} is not an MPU-9250", id),
+            ));
+        }
+
+        // clock from the gyro PLL, 200 Hz sample rate
+        dev.smbus_write_byte_data(REG_PWR_MGMT_1, 0x01)?;
+        dev.smbus_write_byte_data(REG_CONFIG, 0x03)?;
+        dev.smbus_write_byte_data(REG_SMPLRT_DIV, 0x04)?;
+        dev.smbus_write_byte_data(REG_GYRO_CONFIG, 0x08)?;
+        dev.smbus_write_byte_data(REG_ACCEL_CONFIG, 0x08)?;
+        dev.smbus_write_byte_data(REG_ACCEL_CONFIG2, 0x03)?;
+        dev.smbus_write_byte_data(REG_INT_PIN_CFG, 0x02)?;
+
+        let (mag, mag_adjust) = match Mpu9250::open_mag(bus) {
+            Ok((mag, adjust)) => (Some(mag), adjust),
+            Err(e) => {
+                log::warn!("mpu9250: no magnetometer, heading unavailable: {}", e);
+                (None, [1.0; 3])
+            }
+        };
+
+        let mut imu = Mpu9250 {
+            dev,
+            mag,
+            mag_adjust,
+            last_mag: None,
+            gyro_bias: [0.0; 3],
+            opened: Instant::now(),
+        };
+        imu.gyro_bias = gyro_bias(|| imu.read_motion().map(|(_, gyro)| gyro))?;
+        Ok(imu)
+    }
+
+    fn open_mag(bus: &str) -> io::Result<(LinuxI2CDevice, Vector)> {
+        let mut mag = LinuxI2CDevice::new(bus, AK8963_ADDRESS)?;
+        let id = mag.smbus_read_byte_data(AK_REG_WIA)?;
+        if id != AK8963_WIA {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("wia {:#x} is not an AK8963", id),
+            ));
+        }
+
+        // sensitivity adjustment is only readable in fuse rom access mode
+        mag.smbus_write_byte_data(AK_REG_CNTL1, 0x00)?;
+        mag.smbus_write_byte_data(AK_REG_CNTL1, 0x0f)?;
+        let mut asa = [0u8; 3];
+        mag.write(&[AK_REG_ASAX])?;
+        mag.read(&mut asa)?;
+        mag.smbus_write_byte_data(AK_REG_CNTL1, 0x00)?;
+        // 16 bit, continuous measurement at 100 Hz
+        mag.smbus_write_byte_data(AK_REG_CNTL1, 0x16)?;
+
+        let adjust = |a: u8| (a as f64 - 128.0) / 256.0 + 1.0;
+        Ok((mag, [adjust(asa[0]), adjust(asa[1]), adjust(asa[2])]))
+    }
+
+    fn read_motion(&mut self) -> io::Result<(Vector, Vector)> {
+        let mut data = [0u8; 14];
+        self.dev.write(&[REG_ACCEL_XOUT_H])?;
+        self.dev.read(&mut data)?;
+
+        let raw = |i: usize| i16::from_be_bytes([data[i], data[i + 1]]) as f64;
+        Ok((
+            [
+                raw(0) / ACCEL_SCALE,
+                raw(2) / ACCEL_SCALE,
+                raw(4) / ACCEL_SCALE,
+            ],
+            // temperature in between
+            [
+                raw(8) / GYRO_SCALE,
+                raw(10) / GYRO_SCALE,
+                raw(12) / GYRO_SCALE,
+            ],
+        ))
+    }
+
+    fn read_mag(&mut self) -> io::Result<Option<Vector>> {
+        let mag = match &mut self.mag {
+            Some(mag) => mag,
+            None => return Ok(None),
+        };
+        if mag.smbus_read_byte_data(AK_REG_ST1)? & 0x01 == 0 {
+            // no new measurement yet
+            return Ok(self.last_mag);
+        }
+
+        // reading through ST2 releases the data registers
+        let mut data = [0u8; 7];
+        mag.write(&[AK_REG_HXL])?;
+        mag.read(&mut data)?;
+        if data[6] & 0x08 != 0 {
+            // magnetic sensor overflow
+            return Ok(self.last_mag);
+        }
+
+        let raw = |i: usize| i16::from_le_bytes([data[i], data[i + 1]]) as f64 * MAG_SCALE;
+        let adjust = self.mag_adjust;
+        self.last_mag = Some([raw(2) * adjust[1], raw(0) * adjust[0], -raw(4) * adjust[2]]);
+        Ok(self.last_mag)
+    }
+}
+
+impl Imu for Mpu9250 {
+    fn read(&mut self) -> io::Result<ImuSample> {
+        let (accel, gyro) = self.read_motion()?;
+        let mag = self.read_mag()?;
+        let bias = self.gyro_bias;
+
+        Ok(ImuSample {
+            at: self.opened.elapsed(),
+            accel,
+            gyro: [gyro[0] - bias[0], gyro[1] - bias[1], gyro[2] - bias[2]],
+            mag,
+        })
+    }
+}
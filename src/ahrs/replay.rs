@@ -0,0 +1,85 @@
+/* Recorded IMU samples, one per line:
+ *
+ *   t_s,ax_g,ay_g,az_g,gx_dps,gy_dps,gz_dps[,mx_ut,my_ut,mz_ut]
+ *
+ * Blank lines and lines starting with '#' are skipped.
+ */
+
+use std::io;
+use std::time::Duration;
+
+use super::{Imu, ImuSample};
+
+pub struct Replay {
+    samples: Vec<ImuSample>,
+    next: usize,
+}
+
+impl Replay {
+    pub fn new(samples: Vec<ImuSample>) -> Replay {
+        Replay {
+            samples,
+            next: 0,
+        }
+    }
+
+    pub fn from_file(path: &str) -> io::Result<Replay> {
+        Replay::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(log: &str) -> io::Result<Replay> {
+        let mut samples = vec![];
+        for (n, line) in log.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<f64> = line
+                .split(',')
+                .map(|f| f.trim().parse::<f64>())
+                .collect::<Result<_, _>>()
+                .ok()
+                .filter(|fields: &Vec<f64>| fields.len() == 7 || fields.len() == 10)
+                .filter(|fields| fields[0] >= 0.0)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("imu replay line {}: {}", n + 1, line),
+                    )
+                })?;
+
+            samples.push(ImuSample {
+                at: Duration::from_secs_f64(fields[0]),
+                accel: [fields[1], fields[2], fields[3]],
+                gyro: [fields[4], fields[5], fields[6]],
+                mag: fields.get(7..10).map(|m| [m[0], m[1], m[2]]),
+            });
+        }
+
+        Ok(Replay::new(samples))
+    }
+}
+
+impl Imu for Replay {
+    fn read(&mut self) -> io::Result<ImuSample> {
+        match self.samples.get(self.next) {
+            Some(&sample) => {
+                self.next += 1;
+                Ok(sample)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "imu replay ended",
+            )),
+        }
+    }
+}
+
+impl Iterator for Replay {
+    type Item = ImuSample;
+
+    fn next(&mut self) -> Option<ImuSample> {
+        self.read().ok()
+    }
+}
@@ -0,0 +1,138 @@
+/* Bosch BMP280 over Linux i2c-dev.
+ *
+ * Runs in normal mode, 16x pressure / 2x temperature oversampling and an
+ * IIR filter of 16 (the datasheet's "indoor navigation" setting), and
+ * compensates raw readings with the factory trimming parameters.
+ */
+
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::io;
+
+use super::{Barometer, Reading};
+
+pub const BMP280_CHIP_ID: u8 = 0x58;
+
+const REG_CALIBRATION: u8 = 0x88;
+const REG_CHIP_ID: u8 = 0xd0;
+const REG_CTRL_MEAS: u8 = 0xf4;
+const REG_CONFIG: u8 = 0xf5;
+const REG_DATA: u8 = 0xf7;
+
+// osrs_t x2, osrs_p x16, normal mode
+const CTRL_MEAS: u8 = 0b010 << 5 | 0b101 << 2 | 0b11;
+// 0.5 ms standby, filter 16
+const CONFIG: u8 = 0b100 << 2;
+
+/// Factory trimming parameters (dig_T1..dig_P9)
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Calibration {
+    pub t1: u16,
+    pub t2: i16,
+    pub t3: i16,
+    pub p1: u16,
+    pub p2: i16,
+    pub p3: i16,
+    pub p4: i16,
+    pub p5: i16,
+    pub p6: i16,
+    pub p7: i16,
+    pub p8: i16,
+    pub p9: i16,
+}
+
+impl Calibration {
+    /// From the 24 bytes at 0x88
+    pub fn from_bytes(b: &[u8; 24]) -> Calibration {
+        let u = |i: usize| u16::from_le_bytes([b[i], b[i + 1]]);
+        let s = |i: usize| u(i) as i16;
+        Calibration {
+            t1: u(0),
+            t2: s(2),
+            t3: s(4),
+            p1: u(6),
+            p2: s(8),
+            p3: s(10),
+            p4: s(12),
+            p5: s(14),
+            p6: s(16),
+            p7: s(18),
+            p8: s(20),
+            p9: s(22),
+        }
+    }
+
+    /// Floating point compensation from the datasheet, raw 20 bit ADC values
+    pub fn compensate(&self, adc_t: i32, adc_p: i32) -> Option<Reading> {
+        let (adc_t, adc_p) = (adc_t as f64, adc_p as f64);
+        let t1 = self.t1 as f64;
+
+        let var1 = (adc_t / 16384.0 - t1 / 1024.0) * self.t2 as f64;
+        let var2 = (adc_t / 131072.0 - t1 / 8192.0).powi(2) * self.t3 as f64;
+        let t_fine = var1 + var2;
+
+        let var1 = t_fine / 2.0 - 64000.0;
+        let var2 = var1 * var1 * self.p6 as f64 / 32768.0;
+        let var2 = var2 + var1 * self.p5 as f64 * 2.0;
+        let var2 = var2 / 4.0 + self.p4 as f64 * 65536.0;
+        let var1 = (self.p3 as f64 * var1 * var1 / 524288.0 + self.p2 as f64 * var1) / 524288.0;
+        let var1 = (1.0 + var1 / 32768.0) * self.p1 as f64;
+        if var1 == 0.0 {
+            // uncalibrated or not talking to a BMP280 at all
+            return None;
+        }
+
+        let p = 1048576.0 - adc_p;
+        let p = (p - var2 / 4096.0) * 6250.0 / var1;
+        let var1 = self.p9 as f64 * p * p / 2147483648.0;
+        let var2 = p * self.p8 as f64 / 32768.0;
+
+        Some(Reading {
+            pressure: p + (var1 + var2 + self.p7 as f64) / 16.0,
+            temperature: t_fine / 5120.0,
+        })
+    }
+}
+
+pub struct Bmp280 {
+    dev: LinuxI2CDevice,
+    calibration: Calibration,
+}
+
+impl Bmp280 {
+    pub fn open(bus: &str, address: u16) -> io::Result<Bmp280> {
+        let mut dev = LinuxI2CDevice::new(bus, address)?;
+        let id = dev.smbus_read_byte_data(REG_CHIP_ID)?;
+        if id != BMP280_CHIP_ID {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("chip id {:#x} is not a BMP280", id),
+            ));
+        }
+
+        let mut trim = [0u8; 24];
+        dev.write(&[REG_CALIBRATION])?;
+        dev.read(&mut trim)?;
+
+        dev.smbus_write_byte_data(REG_CONFIG, CONFIG)?;
+        dev.smbus_write_byte_data(REG_CTRL_MEAS, CTRL_MEAS)?;
+
+        Ok(Bmp280 {
+            dev,
+            calibration: Calibration::from_bytes(&trim),
+        })
+    }
+}
+
+impl Barometer for Bmp280 {
+    fn read(&mut self) -> io::Result<Reading> {
+        let mut data = [0u8; 6];
+        self.dev.write(&[REG_DATA])?;
+        self.dev.read(&mut data)?;
+
+        let raw = |b: &[u8]| (b[0] as i32) << 12 | (b[1] as i32) << 4 | (b[2] as i32) >> 4;
+        self.calibration
+            .compensate(raw(&data[3..6]), raw(&data[0..3]))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "bmp280 not calibrated"))
+    }
+}
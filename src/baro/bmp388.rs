@@ -0,0 +1,137 @@
+/* Bosch BMP388 (and BMP390, same register map) over Linux i2c-dev.
+ *
+ * Runs in normal mode at 25 Hz with 8x pressure oversampling and an IIR
+ * filter coefficient of 3. The trimming parameters come as fixed point
+ * integers and are scaled to floating point once.
+ */
+
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::io;
+
+use super::{Barometer, Reading};
+
+pub const BMP388_CHIP_ID: u8 = 0x50;
+pub const BMP390_CHIP_ID: u8 = 0x60;
+
+const REG_CHIP_ID: u8 = 0x00;
+const REG_DATA: u8 = 0x04;
+const REG_PWR_CTRL: u8 = 0x1b;
+const REG_OSR: u8 = 0x1c;
+const REG_ODR: u8 = 0x1d;
+const REG_CONFIG: u8 = 0x1f;
+const REG_CALIBRATION: u8 = 0x31;
+
+// pressure and temperature enabled, normal mode
+const PWR_CTRL: u8 = 0x33;
+// osr_p x8, osr_t x1
+const OSR: u8 = 0x03;
+// 25 Hz
+const ODR: u8 = 0x03;
+// iir_filter coefficient 3
+const CONFIG: u8 = 0x04;
+
+/// Trimming parameters (PAR_T1..PAR_P11), already scaled
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Calibration {
+    pub t1: f64,
+    pub t2: f64,
+    pub t3: f64,
+    pub p1: f64,
+    pub p2: f64,
+    pub p3: f64,
+    pub p4: f64,
+    pub p5: f64,
+    pub p6: f64,
+    pub p7: f64,
+    pub p8: f64,
+    pub p9: f64,
+    pub p10: f64,
+    pub p11: f64,
+}
+
+impl Calibration {
+    /// From the 21 bytes at 0x31
+    pub fn from_bytes(b: &[u8; 21]) -> Calibration {
+        let u = |i: usize| u16::from_le_bytes([b[i], b[i + 1]]) as f64;
+        let s = |i: usize| i16::from_le_bytes([b[i], b[i + 1]]) as f64;
+        let s8 = |i: usize| b[i] as i8 as f64;
+        Calibration {
+            t1: u(0) * 2f64.powi(8),
+            t2: u(2) / 2f64.powi(30),
+            t3: s8(4) / 2f64.powi(48),
+            p1: (s(5) - 2f64.powi(14)) / 2f64.powi(20),
+            p2: (s(7) - 2f64.powi(14)) / 2f64.powi(29),
+            p3: s8(9) / 2f64.powi(32),
+            p4: s8(10) / 2f64.powi(37),
+            p5: u(11) * 2f64.powi(3),
+            p6: u(13) / 2f64.powi(6),
+            p7: s8(15) / 2f64.powi(8),
+            p8: s8(16) / 2f64.powi(15),
+            p9: s(17) / 2f64.powi(48),
+            p10: s8(19) / 2f64.powi(48),
+            p11: s8(20) / 2f64.powi(65),
+        }
+    }
+
+    /// Floating point compensation from the datasheet, raw 24 bit ADC values
+    pub fn compensate(&self, adc_t: u32, adc_p: u32) -> Reading {
+        let pd1 = adc_t as f64 - self.t1;
+        let t = pd1 * self.t2 + pd1 * pd1 * self.t3;
+
+        let out1 = self.p5 + self.p6 * t + self.p7 * t * t + self.p8 * t * t * t;
+        let up = adc_p as f64;
+        let out2 = up * (self.p1 + self.p2 * t + self.p3 * t * t + self.p4 * t * t * t);
+        let out3 = up * up * (self.p9 + self.p10 * t) + up * up * up * self.p11;
+
+        Reading {
+            pressure: out1 + out2 + out3,
+            temperature: t,
+        }
+    }
+}
+
+pub struct Bmp388 {
+    dev: LinuxI2CDevice,
+    calibration: Calibration,
+}
+
+impl Bmp388 {
+    pub fn open(bus: &str, address: u16) -> io::Result<Bmp388> {
+        let mut dev = LinuxI2CDevice::new(bus, address)?;
+        let id = dev.smbus_read_byte_data(REG_CHIP_ID)?;
+        if id != BMP388_CHIP_ID && id != BMP390_CHIP_ID {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("chip id {:#x} is not a BMP388", id),
+            ));
+        }
+
+        let mut trim = [0u8; 21];
+        dev.write(&[REG_CALIBRATION])?;
+        dev.read(&mut trim)?;
+
+        dev.smbus_write_byte_data(REG_OSR, OSR)?;
+        dev.smbus_write_byte_data(REG_ODR, ODR)?;
+        dev.smbus_write_byte_data(REG_CONFIG, CONFIG)?;
+        dev.smbus_write_byte_data(REG_PWR_CTRL, PWR_CTRL)?;
+
+        Ok(Bmp388 {
+            dev,
+            calibration: Calibration::from_bytes(&trim),
+        })
+    }
+}
+
+impl Barometer for Bmp388 {
+    fn read(&mut self) -> io::Result<Reading> {
+        let mut data = [0u8; 6];
+        self.dev.write(&[REG_DATA])?;
+        self.dev.read(&mut data)?;
+
+        let raw = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], 0]);
+        Ok(self
+            .calibration
+            .compensate(raw(&data[3..6]), raw(&data[0..3])))
+    }
+}
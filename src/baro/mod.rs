@@ -0,0 +1,99 @@
+/* Barometric pressure for ownship pressure altitude.
+ *
+ * Traffic reports Mode C/S altitude relative to the standard atmosphere
+ * (1013.25 hPa), GPS altitude is geometric and can be off from it by
+ * hundreds of feet depending on the weather. A pressure sensor on board
+ * puts us on the same reference as everyone else.
+ */
+
+pub mod bmp280;
+pub mod bmp388;
+pub mod replay;
+
+use log::*;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::gps::Ownship;
+pub use bmp280::Bmp280;
+pub use bmp388::Bmp388;
+pub use replay::Replay;
+
+/* Standard atmosphere sea level pressure in Pa */
+pub const STANDARD_PRESSURE: f64 = 101_325.0;
+
+/* Addresses a BMP280/BMP388 can be strapped to */
+pub const BARO_I2C_ADDRESSES: [u16; 2] = [0x77, 0x76];
+
+const BARO_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reading {
+    /// Pa
+    pub pressure: f64,
+    /// Degrees celsius
+    pub temperature: f64,
+}
+
+impl Reading {
+    /// Feet in the standard atmosphere, what a transponder would report
+    pub fn pressure_altitude(&self) -> f64 {
+        pressure_altitude(self.pressure)
+    }
+}
+
+pub trait Barometer {
+    fn read(&mut self) -> io::Result<Reading>;
+}
+
+/// Pressure altitude in feet for a static pressure in Pa
+pub fn pressure_altitude(pressure: f64) -> f64 {
+    (1.0 - (pressure / STANDARD_PRESSURE).powf(0.190284)) * 145_366.45
+}
+
+/// Open a barometer: an i2c bus (`/dev/i2c-1`) probed for a BMP388 or
+/// BMP280, or anything else as a replay file.
+pub fn open(source: &str) -> io::Result<Box<dyn Barometer + Send>> {
+    if !source.starts_with("/dev/") {
+        return Ok(Box::new(Replay::from_file(source)?));
+    }
+
+    for &address in BARO_I2C_ADDRESSES.iter() {
+        if let Ok(baro) = Bmp388::open(source, address) {
+            info!("baro: BMP388 at {}:{:#x}", source, address);
+            return Ok(Box::new(baro));
+        }
+        if let Ok(baro) = Bmp280::open(source, address) {
+            info!("baro: BMP280 at {}:{:#x}", source, address);
+            return Ok(Box::new(baro));
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("no BMP280/BMP388 on {}", source),
+    ))
+}
+
+/// Feed pressure altitude into the ownship model until the barometer fails
+/// or a replay ends. Reads block, but only for a register read.
+pub async fn run(
+    mut baro: Box<dyn Barometer + Send>,
+    ownship: Arc<watch::Sender<Ownship>>,
+) -> io::Result<()> {
+    let mut interval = tokio::time::interval(BARO_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let reading = match baro.read() {
+            Ok(reading) => reading,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        trace!("baro: {:?}", reading);
+        ownship.send_modify(|state| state.update_baro(&reading));
+    }
+}
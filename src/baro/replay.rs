@@ -0,0 +1,88 @@
+/* Recorded or made up barometer readings, one per line:
+ *
+ *   pressure_hpa,temperature_c
+ *
+ * Blank lines and lines starting with '#' are skipped. Handy for testing
+ * without a sensor, and for replaying a flight next to its GPS log.
+ */
+
+use std::io;
+
+use super::{Barometer, Reading};
+
+pub struct Replay {
+    readings: Vec<Reading>,
+    next: usize,
+    looping: bool,
+}
+
+impl Replay {
+    pub fn new(readings: Vec<Reading>) -> Replay {
+        Replay {
+            readings,
+            next: 0,
+            looping: false,
+        }
+    }
+
+    /// A sensor stuck at one reading
+    pub fn constant(reading: Reading) -> Replay {
+        Replay::new(vec![reading]).looping(true)
+    }
+
+    pub fn from_file(path: &str) -> io::Result<Replay> {
+        Replay::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(log: &str) -> io::Result<Replay> {
+        let mut readings = vec![];
+        for (n, line) in log.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("baro replay line {}: {}", n + 1, line),
+                )
+            };
+            let mut fields = line.split(',').map(|f| f.trim().parse::<f64>());
+            let pressure = fields.next().and_then(|f| f.ok()).ok_or_else(invalid)?;
+            let temperature = fields.next().and_then(|f| f.ok()).ok_or_else(invalid)?;
+
+            readings.push(Reading {
+                pressure: pressure * 100.0,
+                temperature,
+            });
+        }
+
+        Ok(Replay::new(readings))
+    }
+
+    /// Start over at the end instead of running out
+    pub fn looping(mut self, looping: bool) -> Replay {
+        self.looping = looping;
+        self
+    }
+}
+
+impl Barometer for Replay {
+    fn read(&mut self) -> io::Result<Reading> {
+        if self.next == self.readings.len() && self.looping {
+            self.next = 0;
+        }
+
+        match self.readings.get(self.next) {
+            Some(&reading) => {
+                self.next += 1;
+                Ok(reading)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "baro replay ended",
+            )),
+        }
+    }
+}
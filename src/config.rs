@@ -0,0 +1,316 @@
+/* Configuration file for the whole stack, in TOML. Everything is optional
+ * and command line flags override what the file says:
+ *
+ *   log_level = "info"
+ *   summary_interval = 60       # s between stats in the log, 0 for none
+ *
+ *   [[radio]]
+ *   band = "1090"
+ *   serial = "00001090"
+ *
+ *   [[radio]]
+ *   band = "978"
+ *   serial = "00000978"
+ *   ppm = -2
+ *
+ *   [receiver]
+ *   latitude = 47.45
+ *   longitude = -122.3
+ *   altitude = 430              # ft MSL
+ *   max_range = 180             # NM, above 180 airborne positions can't be
+ *                               # decoded relative to the receiver
+ *
+ *   [tracker]
+ *   aircraft_timeout = 60       # s
+ *   address_type_timeout = 30   # s
+ *   max_speed = 1000            # kt
+ *   reliable_positions = 2
+ *   ownship = "a1b2c3"
+ *   repair = "1bit"
+ *
+ *   [sensors]
+ *   gps = "/dev/ttyACM0"
+ *   gps_baud = 9600
+ *   baro = "/dev/i2c-1"
+ *   imu = "/dev/i2c-1"
+ *
+ *   [outputs]
+ *   gdl90 = ["192.168.1.255:4000"]
+ *   beast = 30005               # reserved
+ *   sbs = 30003                 # reserved
+ *   json = 8080                 # reserved
+ *   metrics = 9100              # Prometheus /metrics
+ *
+ * The Beast, SBS and JSON ports are reserved for outputs that don't exist
+ * yet. They are validated like the others so a config written for them
+ * keeps working, but nothing listens on them.
+ */
+
+use log::LevelFilter;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::adsb::{ReceiverLocation, TrackerConfig};
+use crate::geo::METERS_PER_NM;
+use crate::metrics::METRICS_SUMMARY_INTERVAL;
+use crate::sdr::input::RadioSpec;
+use crate::sdr::repair::RepairPolicy;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(deserialize_with = "parsed")]
+    pub log_level: Option<LevelFilter>,
+    /// Seconds
+    pub summary_interval: Option<u64>,
+    #[serde(rename = "radio")]
+    pub radios: Vec<RadioSpec>,
+    pub receiver: ReceiverSection,
+    pub tracker: TrackerSection,
+    pub sensors: SensorSection,
+    pub outputs: OutputSection,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReceiverSection {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// ft MSL
+    pub altitude: Option<i32>,
+    /// NM
+    pub max_range: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrackerSection {
+    /// Seconds
+    pub aircraft_timeout: Option<u64>,
+    /// Seconds
+    pub address_type_timeout: Option<u64>,
+    /// Knots
+    pub max_speed: Option<f64>,
+    pub reliable_positions: Option<u8>,
+    #[serde(deserialize_with = "icao")]
+    pub ownship: Option<u32>,
+    #[serde(deserialize_with = "parsed")]
+    pub repair: Option<RepairPolicy>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SensorSection {
+    pub gps: Option<String>,
+    pub gps_baud: Option<u32>,
+    pub baro: Option<String>,
+    pub imu: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSection {
+    pub gdl90: Vec<SocketAddr>,
+    /// Reserved for outputs that don't exist yet, validated but not listened on
+    pub beast: Option<u16>,
+    pub sbs: Option<u16>,
+    pub json: Option<u16>,
+    pub metrics: Option<u16>,
+}
+
+/* Option<T> from a string through T's FromStr, for types with their own syntax */
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map(Some).map_err(de::Error::custom)
+}
+
+fn icao<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    match u32::from_str_radix(&s, 16) {
+        Ok(address) if address <= 0xffffff => Ok(Some(address)),
+        _ => Err(de::Error::custom(format!(
+            "invalid ICAO address '{}' (expected 6 hex digits)",
+            s
+        ))),
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl Config {
+    pub fn from_toml(text: &str) -> io::Result<Config> {
+        toml::from_str(text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    pub fn load(path: &str) -> io::Result<Config> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+        Config::from_toml(&text).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+    }
+
+    pub fn location(&self) -> Option<ReceiverLocation> {
+        match (self.receiver.latitude, self.receiver.longitude) {
+            (Some(latitude), Some(longitude)) => Some(ReceiverLocation::new(
+                latitude,
+                longitude,
+                self.receiver.altitude,
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn set_location(&mut self, location: ReceiverLocation) {
+        self.receiver.latitude = Some(location.position.latitude);
+        self.receiver.longitude = Some(location.position.longitude);
+        self.receiver.altitude = location.altitude;
+    }
+
+    /// Time between stats in the log, None if they are turned off
+    pub fn summary_interval(&self) -> Option<Duration> {
+        match self.summary_interval {
+            Some(0) => None,
+            Some(seconds) => Some(Duration::from_secs(seconds)),
+            None => Some(METRICS_SUMMARY_INTERVAL),
+        }
+    }
+
+    pub fn repair_policy(&self) -> RepairPolicy {
+        self.tracker.repair.unwrap_or(RepairPolicy::SingleBit)
+    }
+
+    /// Tracker configuration, defaults for whatever isn't set
+    pub fn tracker_config(&self) -> TrackerConfig {
+        let defaults = TrackerConfig::default();
+        let tracker = &self.tracker;
+
+        TrackerConfig {
+            receiver: self.location(),
+            max_range: self
+                .receiver
+                .max_range
+                .map_or(defaults.max_range, |nm| nm * METERS_PER_NM),
+            max_speed: tracker
+                .max_speed
+                .map_or(defaults.max_speed, |kt| kt * METERS_PER_NM / 3600.0),
+            reliable_positions: tracker
+                .reliable_positions
+                .unwrap_or(defaults.reliable_positions),
+            ownship_icao: tracker.ownship,
+            aircraft_timeout: tracker
+                .aircraft_timeout
+                .map_or(defaults.aircraft_timeout, Duration::from_secs),
+            address_type_timeout: tracker
+                .address_type_timeout
+                .map_or(defaults.address_type_timeout, Duration::from_secs),
+        }
+    }
+
+    /// Check what the syntax alone can't, once command line flags are merged in
+    pub fn validate(&self) -> io::Result<()> {
+        let receiver = &self.receiver;
+        match (receiver.latitude, receiver.longitude) {
+            (Some(latitude), _) if latitude.abs() > 90.0 => {
+                return Err(invalid(format!(
+                    "receiver: latitude {} out of range",
+                    latitude
+                )))
+            }
+            (_, Some(longitude)) if longitude.abs() > 180.0 => {
+                return Err(invalid(format!(
+                    "receiver: longitude {} out of range",
+                    longitude
+                )))
+            }
+            (Some(_), None) | (None, Some(_)) => {
+                return Err(invalid(
+                    "receiver: latitude and longitude go together".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        if receiver.altitude.is_some() && receiver.latitude.is_none() {
+            return Err(invalid("receiver: altitude without a location".to_string()));
+        }
+        if let Some(max_range) = receiver.max_range.filter(|&nm| nm <= 0.0) {
+            return Err(invalid(format!(
+                "receiver: max_range {} must be positive",
+                max_range
+            )));
+        }
+
+        let tracker = &self.tracker;
+        for (name, timeout) in &[
+            ("aircraft_timeout", tracker.aircraft_timeout),
+            ("address_type_timeout", tracker.address_type_timeout),
+        ] {
+            if *timeout == Some(0) {
+                return Err(invalid(format!("tracker: {} must be at least 1s", name)));
+            }
+        }
+        if let Some(max_speed) = tracker.max_speed.filter(|&kt| kt <= 0.0) {
+            return Err(invalid(format!(
+                "tracker: max_speed {} must be positive",
+                max_speed
+            )));
+        }
+
+        for (i, radio) in self.radios.iter().enumerate() {
+            let serial = match &radio.serial {
+                Some(serial) => serial,
+                None => continue,
+            };
+            if self.radios[..i]
+                .iter()
+                .any(|r| r.serial.as_ref() == Some(serial))
+            {
+                return Err(invalid(format!("radio: serial {} used twice", serial)));
+            }
+        }
+
+        let outputs = &self.outputs;
+        let ports: Vec<(&str, u16)> = outputs
+            .gdl90
+            .iter()
+            .map(|target| ("gdl90", target.port()))
+            .chain(
+                vec![
+                    ("beast", outputs.beast),
+                    ("sbs", outputs.sbs),
+                    ("json", outputs.json),
+                    ("metrics", outputs.metrics),
+                ]
+                .into_iter()
+                .filter_map(|(name, port)| port.map(|port| (name, port))),
+            )
+            .collect();
+        for (i, (name, port)) in ports.iter().enumerate() {
+            if *port == 0 {
+                return Err(invalid(format!("outputs: {} port can't be 0", name)));
+            }
+            // GDL90 may well broadcast to several targets on the same port
+            if let Some((other, _)) = ports[..i]
+                .iter()
+                .find(|(other, p)| p == port && (*other, *name) != ("gdl90", "gdl90"))
+            {
+                return Err(invalid(format!(
+                    "outputs: {} and {} both on port {}",
+                    other, name, port
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
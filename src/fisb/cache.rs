@@ -0,0 +1,136 @@
+/* Latest FIS-B products, kept until they are replaced or too old.
+ *
+ * Ground stations repeat every product on a schedule (METARs every 5
+ * minutes, NEXRAD every 2.5 to 15), anything older than a few cycles has
+ * either been superseded or isn't being sent anymore.
+ */
+
+use log::*;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use super::{NexradBlock, Product, ReportKind, TextReport, Uplink};
+use crate::metrics::{Metrics, METRICS};
+
+pub const WEATHER_OBSERVATION_MAX_AGE: Duration = Duration::from_secs(2 * 3600);
+pub const WEATHER_FORECAST_MAX_AGE: Duration = Duration::from_secs(6 * 3600);
+pub const WEATHER_ADVISORY_MAX_AGE: Duration = Duration::from_secs(3600);
+pub const WEATHER_NEXRAD_MAX_AGE: Duration = Duration::from_secs(30 * 60);
+
+/// How long a report is kept without being repeated
+pub fn max_age(kind: ReportKind) -> Duration {
+    match kind {
+        ReportKind::Metar | ReportKind::Speci | ReportKind::Pirep => WEATHER_OBSERVATION_MAX_AGE,
+        ReportKind::Taf | ReportKind::Winds => WEATHER_FORECAST_MAX_AGE,
+        _ => WEATHER_ADVISORY_MAX_AGE,
+    }
+}
+
+/* Generic text reports are one per station, TWGO ones per report number */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ReportKey {
+    kind: ReportKind,
+    location: String,
+    number: Option<u16>,
+}
+
+#[derive(Default)]
+pub struct WeatherCache {
+    reports: HashMap<ReportKey, (TextReport, SystemTime)>,
+    // (CONUS, block number)
+    nexrad: HashMap<(bool, u32), (NexradBlock, SystemTime)>,
+}
+
+impl WeatherCache {
+    pub fn new() -> WeatherCache {
+        WeatherCache::default()
+    }
+
+    /// Store the products of an uplink received at `at`, returns how many
+    pub fn process(&mut self, uplink: &Uplink, at: SystemTime) -> usize {
+        let mut count = 0;
+
+        for apdu in &uplink.apdus {
+            match &apdu.product {
+                Product::Text(reports) => {
+                    for report in reports {
+                        let key = ReportKey {
+                            kind: report.kind,
+                            location: report.location.clone(),
+                            number: report.number,
+                        };
+                        if report.cancelled {
+                            self.reports.remove(&key);
+                        } else {
+                            trace!("fisb: {}", report);
+                            self.reports.insert(key, (report.clone(), at));
+                        }
+                        count += 1;
+                    }
+                }
+                Product::Nexrad(blocks) => {
+                    for block in blocks {
+                        let key = (block.conus, block.block_number);
+                        self.nexrad.insert(key, (block.clone(), at));
+                        count += 1;
+                    }
+                }
+                Product::Other => {}
+            }
+        }
+
+        Metrics::add(&METRICS.fisb_products, count as u64);
+        count
+    }
+
+    /// Drop products not repeated within their maximum age of `now`
+    pub fn expire(&mut self, now: SystemTime) {
+        let age = |at: &SystemTime| now.duration_since(*at).unwrap_or_default();
+        self.reports
+            .retain(|key, (_, at)| age(at) <= max_age(key.kind));
+        self.nexrad
+            .retain(|_, (_, at)| age(at) <= WEATHER_NEXRAD_MAX_AGE);
+    }
+
+    /// Latest report of a kind for a station, e.g. the METAR of "KSEA"
+    pub fn report(&self, kind: ReportKind, location: &str) -> Option<&TextReport> {
+        self.reports
+            .iter()
+            .filter(|(key, _)| key.kind == kind && key.location == location)
+            .max_by_key(|(_, (_, at))| *at)
+            .map(|(_, (report, _))| report)
+    }
+
+    pub fn metar(&self, station: &str) -> Option<&TextReport> {
+        self.report(ReportKind::Metar, station)
+            .or_else(|| self.report(ReportKind::Speci, station))
+    }
+
+    pub fn taf(&self, station: &str) -> Option<&TextReport> {
+        self.report(ReportKind::Taf, station)
+    }
+
+    /// All reports of a kind
+    pub fn reports(&self, kind: ReportKind) -> impl Iterator<Item = &TextReport> {
+        self.reports
+            .iter()
+            .filter(move |(key, _)| key.kind == kind)
+            .map(|(_, (report, _))| report)
+    }
+
+    /// NEXRAD blocks with precipitation, regional or CONUS
+    pub fn nexrad(&self, conus: bool) -> impl Iterator<Item = &NexradBlock> {
+        self.nexrad
+            .values()
+            .map(|(block, _)| block)
+            .filter(move |block| block.conus == conus && !block.is_empty())
+    }
+
+    pub fn len(&self) -> usize {
+        self.reports.len() + self.nexrad.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
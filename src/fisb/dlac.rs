@@ -0,0 +1,74 @@
+/* DLAC, the 6 bit character set of FIS-B text, four characters packed in
+ * every three bytes:
+ *
+ *   0      ETX, end of the text
+ *   1-26   A-Z
+ *   28     TAB, the next character is a count of spaces
+ *   29     RS, separates records
+ *   30     LF
+ *   32-63  space, punctuation and digits in ASCII order
+ */
+
+pub const DLAC_ETX: char = '\x03';
+pub const DLAC_RS: char = '\x1e';
+
+const DLAC_CHARSET: &[u8; 64] =
+    b"\x03ABCDEFGHIJKLMNOPQRSTUVWXYZ\x1a\t\x1e\n| !\"#$%&'()*+,-./0123456789:;<=>?";
+const DLAC_TAB: u8 = 28;
+
+/// Decode DLAC text up to its end, tabs expanded into spaces
+pub fn decode(data: &[u8]) -> String {
+    let mut text = String::with_capacity(data.len() * 4 / 3);
+    let mut tab = false;
+
+    for chunk in data.chunks_exact(3) {
+        let codes = [
+            chunk[0] >> 2,
+            (chunk[0] & 0x03) << 4 | chunk[1] >> 4,
+            (chunk[1] & 0x0f) << 2 | chunk[2] >> 6,
+            chunk[2] & 0x3f,
+        ];
+        for &code in &codes {
+            if tab {
+                text.push_str(&" ".repeat(code as usize));
+                tab = false;
+            } else if code == DLAC_TAB {
+                tab = true;
+            } else {
+                text.push(DLAC_CHARSET[code as usize] as char);
+            }
+        }
+    }
+
+    match text.find(DLAC_ETX) {
+        Some(end) => text[..end].to_string(),
+        None => text,
+    }
+}
+
+/// DLAC encode text (without tabs), padded with ETX to whole bytes
+pub fn encode(text: &str) -> Vec<u8> {
+    let mut codes: Vec<u8> = text
+        .bytes()
+        .map(|c| {
+            DLAC_CHARSET
+                .iter()
+                .position(|&d| d == c.to_ascii_uppercase())
+                .unwrap_or(32) as u8
+        })
+        .collect();
+    while !codes.len().is_multiple_of(4) {
+        codes.push(0);
+    }
+
+    codes
+        .chunks(4)
+        .flat_map(|c| {
+            vec![
+                c[0] << 2 | c[1] >> 4,
+                c[1] << 4 | c[2] >> 2,
+                c[2] << 6 | c[3],
+            ]
+        })
+        .collect()
+}
@@ -0,0 +1,216 @@
+/* FIS-B, weather and aeronautical information that ground stations send
+ * in UAT uplink frames. The 432 byte payload of an uplink is
+ *
+ *   ll ll ll nn nn nn us t-  station position (like ADS-B), flags, slot
+ *   ii ii [data]...          information frames up to the end
+ *
+ * An information frame has a 9 bit length and a 4 bit type, type 0 holds
+ * an APDU (one product, or a segment of one) with this header:
+ *
+ *   agps pppp pppp ppt ttttt...
+ *
+ *   a/g/p/s  flags, g: the product is segmented
+ *   p        product id (11 bits)
+ *   t        time option (2 bits) followed by the issue time, with
+ *            seconds for options 1 and 3 and the date for 2 and 3
+ *
+ * Segmented products aren't reassembled.
+ */
+
+pub mod cache;
+pub mod dlac;
+pub mod nexrad;
+pub mod text;
+
+use crate::geo::Position;
+use crate::sdr::uat::UAT_UPLINK_BYTES;
+pub use cache::WeatherCache;
+pub use nexrad::NexradBlock;
+pub use text::{ReportKind, TextReport};
+
+pub const FISB_NOTAM: u16 = 8;
+pub const FISB_AIRMET: u16 = 11;
+pub const FISB_SIGMET: u16 = 12;
+pub const FISB_SUA: u16 = 13;
+pub const FISB_CWA: u16 = 15;
+pub const FISB_NOTAM_TFR: u16 = 16;
+pub const FISB_NEXRAD_REGIONAL: u16 = 63;
+pub const FISB_NEXRAD_CONUS: u16 = 64;
+pub const FISB_GENERIC_TEXT: u16 = 413;
+
+const UPLINK_HEADER_BYTES: usize = 8;
+const INFO_FRAME_HEADER_BYTES: usize = 2;
+const INFO_FRAME_FISB: u8 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ApduTime {
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Product {
+    Text(Vec<TextReport>),
+    Nexrad(Vec<NexradBlock>),
+    /// Not decoded, or a segment
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Apdu {
+    pub product_id: u16,
+    pub time: ApduTime,
+    pub segmented: bool,
+    pub product: Product,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Uplink {
+    /// Ground station position, if it sends a valid one
+    pub position: Option<Position>,
+    pub utc_coupled: bool,
+    pub slot_id: u8,
+    pub tisb_site_id: u8,
+    pub apdus: Vec<Apdu>,
+}
+
+/// Decode an error corrected uplink payload
+pub fn decode(payload: &[u8]) -> Option<Uplink> {
+    if payload.len() != UAT_UPLINK_BYTES {
+        return None;
+    }
+
+    let raw_lat = (payload[0] as u32) << 15 | (payload[1] as u32) << 7 | (payload[2] >> 1) as u32;
+    let raw_lon = ((payload[2] & 0x01) as u32) << 23
+        | (payload[3] as u32) << 15
+        | (payload[4] as u32) << 7
+        | (payload[5] >> 1) as u32;
+    let position = match payload[5] & 0x01 {
+        0 => None,
+        _ => {
+            let mut latitude = raw_lat as f64 * 360.0 / (1 << 24) as f64;
+            if latitude > 90.0 {
+                latitude -= 180.0;
+            }
+            let mut longitude = raw_lon as f64 * 360.0 / (1 << 24) as f64;
+            if longitude > 180.0 {
+                longitude -= 360.0;
+            }
+            Some(Position::new(latitude, longitude))
+        }
+    };
+
+    let mut uplink = Uplink {
+        position,
+        utc_coupled: payload[6] & 0x80 != 0,
+        slot_id: payload[6] & 0x1f,
+        tisb_site_id: payload[7] >> 4,
+        apdus: vec![],
+    };
+    // no application data
+    if payload[6] & 0x20 == 0 {
+        return Some(uplink);
+    }
+
+    let mut data = &payload[UPLINK_HEADER_BYTES..];
+    while data.len() >= INFO_FRAME_HEADER_BYTES {
+        let length = (data[0] as usize) << 1 | (data[1] >> 7) as usize;
+        let frame_type = data[1] & 0x0f;
+        if length == 0 || data.len() < INFO_FRAME_HEADER_BYTES + length {
+            break;
+        }
+
+        let info = &data[INFO_FRAME_HEADER_BYTES..INFO_FRAME_HEADER_BYTES + length];
+        if frame_type == INFO_FRAME_FISB {
+            if let Some(apdu) = decode_apdu(info) {
+                uplink.apdus.push(apdu);
+            }
+        }
+        data = &data[INFO_FRAME_HEADER_BYTES + length..];
+    }
+
+    Some(uplink)
+}
+
+/// Decode the APDU of an information frame
+pub fn decode_apdu(data: &[u8]) -> Option<Apdu> {
+    if data.len() < 6 {
+        return None;
+    }
+
+    let segmented = data[0] & 0x40 != 0;
+    let product_id = ((data[0] & 0x1f) as u16) << 6 | (data[1] >> 2) as u16;
+    let time_option = (data[1] & 0x01) << 1 | data[2] >> 7;
+
+    let (time, header) = match time_option {
+        0 => (
+            ApduTime {
+                hours: (data[2] & 0x7c) >> 2,
+                minutes: (data[2] & 0x03) << 4 | data[3] >> 4,
+                ..ApduTime::default()
+            },
+            4,
+        ),
+        1 => (
+            ApduTime {
+                hours: (data[2] & 0x7c) >> 2,
+                minutes: (data[2] & 0x03) << 4 | data[3] >> 4,
+                seconds: Some((data[3] & 0x0f) << 2 | data[4] >> 6),
+                ..ApduTime::default()
+            },
+            5,
+        ),
+        2 => (
+            ApduTime {
+                month: Some((data[2] & 0x78) >> 3),
+                day: Some((data[2] & 0x07) << 2 | data[3] >> 6),
+                hours: (data[3] & 0x3e) >> 1,
+                minutes: (data[3] & 0x01) << 5 | data[4] >> 3,
+                seconds: None,
+            },
+            5,
+        ),
+        _ => (
+            ApduTime {
+                month: Some((data[2] & 0x78) >> 3),
+                day: Some((data[2] & 0x07) << 2 | data[3] >> 6),
+                hours: (data[3] & 0x3e) >> 1,
+                minutes: (data[3] & 0x01) << 5 | data[4] >> 3,
+                seconds: Some((data[4] & 0x07) << 3 | data[5] >> 5),
+            },
+            6,
+        ),
+    };
+
+    let body = &data[header..];
+    let product = match product_id {
+        _ if segmented => Product::Other,
+        FISB_GENERIC_TEXT | FISB_SUA => Product::Text(text::decode_generic(body)),
+        FISB_NOTAM | FISB_NOTAM_TFR => Product::Text(text::decode_twgo(ReportKind::Notam, body)),
+        FISB_AIRMET => Product::Text(text::decode_twgo(ReportKind::Airmet, body)),
+        FISB_SIGMET => Product::Text(text::decode_twgo(ReportKind::Sigmet, body)),
+        FISB_CWA => Product::Text(text::decode_twgo(ReportKind::ConvectiveWeather, body)),
+        FISB_NEXRAD_REGIONAL => Product::Nexrad(nexrad::decode(body, false)),
+        FISB_NEXRAD_CONUS => Product::Nexrad(nexrad::decode(body, true)),
+        _ => Product::Other,
+    };
+
+    Some(Apdu {
+        product_id,
+        time,
+        segmented,
+        product,
+    })
+}
+
+/// Uplink payload from a line of dump978 output, `+<hex>;rs=...;`
+pub fn parse_hex(line: &str) -> Option<Vec<u8>> {
+    let hex = line.trim().strip_prefix('+')?.split(';').next()?;
+    match hex::decode(hex) {
+        Ok(payload) if payload.len() == UAT_UPLINK_BYTES => Some(payload),
+        _ => None,
+    }
+}
@@ -0,0 +1,141 @@
+/* NEXRAD composite reflectivity, regional (product 63) and CONUS (64).
+ *
+ * The world is cut into blocks 4 arc minutes high and 48 arc minutes wide
+ * (96 above 60 degrees latitude), numbered west to east from 0 degrees
+ * longitude and south to north from the equator, mirrored for the
+ * southern hemisphere. Coarser products scale the blocks up by 5 or 9.
+ * Every block is 4 rows of 32 bins, north-west corner first, each bin an
+ * intensity from 0 (none) to 7.
+ *
+ *   bb bb bb  block number (20 bits) with RLE, hemisphere and scale flags
+ *   rr...     RLE: (run length - 1) << 3 | intensity
+ *
+ * Without RLE the block is empty, and the rest is a bitmap of following
+ * blocks in the same row that are empty as well.
+ */
+
+pub const NEXRAD_ROWS: usize = 4;
+pub const NEXRAD_COLUMNS: usize = 32;
+pub const NEXRAD_BINS: usize = NEXRAD_ROWS * NEXRAD_COLUMNS;
+
+/* Blocks per row of 4 arc minutes below and above 60 degrees latitude */
+const NEXRAD_ROW_BLOCKS: u32 = 450;
+const NEXRAD_POLAR_ROW_BLOCKS: u32 = 225;
+const NEXRAD_POLAR_BLOCK: u32 = 405_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NexradBlock {
+    pub block_number: u32,
+    pub southern: bool,
+    /// 0 for high, 1 for medium and 2 for low resolution
+    pub scale: u8,
+    /// CONUS instead of regional product
+    pub conus: bool,
+    pub intensity: [u8; NEXRAD_BINS],
+}
+
+impl NexradBlock {
+    fn scale_factor(&self) -> f64 {
+        match self.scale {
+            1 => 5.0,
+            2 => 9.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Latitude and longitude of the north-west corner
+    pub fn north_west(&self) -> (f64, f64) {
+        let (row, column, width) = match self.block_number {
+            n if n >= NEXRAD_POLAR_BLOCK => (
+                NEXRAD_POLAR_BLOCK / NEXRAD_ROW_BLOCKS
+                    + (n - NEXRAD_POLAR_BLOCK) / NEXRAD_POLAR_ROW_BLOCKS,
+                (n - NEXRAD_POLAR_BLOCK) % NEXRAD_POLAR_ROW_BLOCKS,
+                96.0,
+            ),
+            n => (n / NEXRAD_ROW_BLOCKS, n % NEXRAD_ROW_BLOCKS, 48.0),
+        };
+
+        let (height, _) = self.size();
+        let latitude = match self.southern {
+            true => -(row as f64 * 4.0) / 60.0,
+            false => row as f64 * 4.0 / 60.0 + height,
+        };
+        let mut longitude = column as f64 * width * self.scale_factor() / 60.0;
+        if longitude >= 180.0 {
+            longitude -= 360.0;
+        }
+        (latitude, longitude)
+    }
+
+    /// Height and width of the block in degrees
+    pub fn size(&self) -> (f64, f64) {
+        let width = match self.block_number >= NEXRAD_POLAR_BLOCK {
+            true => 96.0,
+            false => 48.0,
+        };
+        let scale = self.scale_factor();
+        (4.0 * scale / 60.0, width * scale / 60.0)
+    }
+
+    /// True if no bin has any precipitation
+    pub fn is_empty(&self) -> bool {
+        self.intensity.iter().all(|&i| i == 0)
+    }
+}
+
+/// Blocks of one NEXRAD APDU, one with data or a run of empty ones
+pub fn decode(data: &[u8], conus: bool) -> Vec<NexradBlock> {
+    if data.len() < 4 {
+        return vec![];
+    }
+
+    let block = NexradBlock {
+        block_number: ((data[0] & 0x0f) as u32) << 16 | (data[1] as u32) << 8 | data[2] as u32,
+        southern: data[0] & 0x40 != 0,
+        scale: (data[0] & 0x30) >> 4,
+        conus,
+        intensity: [0; NEXRAD_BINS],
+    };
+
+    if data[0] & 0x80 != 0 {
+        let mut block = block;
+        let mut bin = 0;
+        for &run in &data[3..] {
+            let length = (run >> 3) as usize + 1;
+            for i in bin..(bin + length).min(NEXRAD_BINS) {
+                block.intensity[i] = run & 0x07;
+            }
+            bin += length;
+        }
+        return vec![block];
+    }
+
+    /* The bitmap starts in the high nibble of the fourth byte, whose bit 3
+     * is the block itself, so bit j of bitmap byte i is block 8i + j - 3
+     * after this one. The low nibble is the bitmap length. */
+    let row_blocks = match block.block_number >= NEXRAD_POLAR_BLOCK {
+        true => NEXRAD_POLAR_ROW_BLOCKS,
+        false => NEXRAD_ROW_BLOCKS,
+    };
+    let row_start = block.block_number - block.block_number % row_blocks;
+    let length = (data[3] & 0x0f) as usize;
+
+    let mut blocks = vec![block.clone()];
+    for i in 0..length.min(data.len() - 3) {
+        let bitmap = match i {
+            0 => data[3] & 0xf0,
+            _ => data[3 + i],
+        };
+        for j in 0..8 {
+            if bitmap & (1 << j) != 0 {
+                let column = (block.block_number - row_start + (8 * i + j) as u32 - 3) % row_blocks;
+                blocks.push(NexradBlock {
+                    block_number: row_start + column,
+                    ..block.clone()
+                });
+            }
+        }
+    }
+
+    blocks
+}
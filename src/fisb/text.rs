@@ -0,0 +1,133 @@
+/* FIS-B text products.
+ *
+ * Generic text (METAR, TAF, PIREP, winds aloft) and SUA status are DLAC
+ * text, records separated by RS and each starting with the report type,
+ * the location and the issue time:
+ *
+ *   METAR KSEA 011753Z 17005KT 10SM FEW035 12/06 A3012 RMK AO2=
+ *
+ * NOTAMs, AIRMETs and SIGMETs come in the text with graphic overlay
+ * (TWGO) format. Its header names the location, then every text record is
+ *
+ *   ll ll nn nn ys text...
+ *
+ *   l  record length in bytes, header included
+ *   n  report number (14 bits), y report year (7 bits), s status (1 bit,
+ *      0 means the report was cancelled)
+ *
+ * Graphic overlay records aren't decoded.
+ */
+
+use std::fmt;
+
+use super::dlac;
+
+/* TWGO record formats */
+const TWGO_TEXT: u8 = 2;
+const TWGO_HEADER_BYTES: usize = 6;
+const TWGO_TEXT_HEADER_BYTES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportKind {
+    Metar,
+    Speci,
+    Taf,
+    Pirep,
+    Winds,
+    Notam,
+    Airmet,
+    Sigmet,
+    ConvectiveWeather,
+    Sua,
+    Other,
+}
+
+impl ReportKind {
+    /// Report type at the start of a generic text record, e.g. "TAF.AMD"
+    fn from_type(report_type: &str) -> ReportKind {
+        match report_type.split('.').next().unwrap_or("") {
+            "METAR" => ReportKind::Metar,
+            "SPECI" => ReportKind::Speci,
+            "TAF" => ReportKind::Taf,
+            "PIREP" => ReportKind::Pirep,
+            "WINDS" => ReportKind::Winds,
+            "SUA" => ReportKind::Sua,
+            _ => ReportKind::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextReport {
+    pub kind: ReportKind,
+    /// Station, or the issuing location of a TWGO product
+    pub location: String,
+    /// TWGO report number, None for generic text
+    pub number: Option<u16>,
+    /// TWGO report was cancelled
+    pub cancelled: bool,
+    pub text: String,
+}
+
+impl fmt::Display for TextReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} {}: {}", self.kind, self.location, self.text)
+    }
+}
+
+/// Records of a generic text or SUA product
+pub fn decode_generic(data: &[u8]) -> Vec<TextReport> {
+    dlac::decode(data)
+        .split(dlac::DLAC_RS)
+        .filter_map(|record| {
+            let record = record.trim();
+            let mut fields = record.splitn(3, ' ');
+            let report_type = fields.next()?;
+            let location = fields.next()?;
+
+            Some(TextReport {
+                kind: ReportKind::from_type(report_type),
+                location: location.to_string(),
+                number: None,
+                cancelled: false,
+                text: record.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Text records of a TWGO product, graphics are skipped
+pub fn decode_twgo(kind: ReportKind, data: &[u8]) -> Vec<TextReport> {
+    if data.len() < TWGO_HEADER_BYTES || data[0] >> 4 != TWGO_TEXT {
+        return vec![];
+    }
+    let count = (data[1] >> 4) as usize;
+    let location = dlac::decode(&data[2..5]).trim().to_string();
+
+    let mut reports = Vec::with_capacity(count);
+    let mut offset = TWGO_HEADER_BYTES;
+    for _ in 0..count {
+        if data.len() < offset + TWGO_TEXT_HEADER_BYTES {
+            break;
+        }
+        let record = &data[offset..];
+        let length = (record[0] as usize) << 8 | record[1] as usize;
+        if length < TWGO_TEXT_HEADER_BYTES || record.len() < length {
+            break;
+        }
+
+        reports.push(TextReport {
+            kind,
+            location: location.clone(),
+            number: Some((record[2] as u16) << 6 | (record[3] >> 2) as u16),
+            cancelled: record[4] & 0x04 == 0,
+            text: dlac::decode(&record[TWGO_TEXT_HEADER_BYTES..length])
+                .trim_end_matches(dlac::DLAC_RS)
+                .trim()
+                .to_string(),
+        });
+        offset += length;
+    }
+
+    reports
+}
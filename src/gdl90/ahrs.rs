@@ -0,0 +1,73 @@
+/* Attitude messages, neither is in the GDL90 spec proper.
+ *
+ * ForeFlight AHRS (0x65, sub id 0x01):
+ *
+ *   0x65 0x01 roll(2) pitch(2) heading(2) ias(2) tas(2)
+ *
+ * Stratux/Levil AHRS (0x4c), what most other EFBs understand:
+ *
+ *   0x4c 0x45 0x01 0x01 roll(2) pitch(2) heading(2) slip_skid(2)
+ *   turn_rate(2) g_load(2) ias(2) pressure_altitude(2) vs(2) reserved(2)
+ *
+ * Big endian, angles and rates in tenths, invalid fields 0x7fff (0xffff
+ * for unsigned ones).
+ */
+
+use super::{GDL90_FOREFLIGHT, GDL90_STRATUX_AHRS};
+use crate::ahrs::Attitude;
+
+pub const FOREFLIGHT_AHRS: u8 = 0x01;
+
+const INVALID: i16 = 0x7fff;
+
+fn tenths(value: f64, limit: f64) -> i16 {
+    (value.clamp(-limit, limit) * 10.0).round() as i16
+}
+
+pub fn foreflight(attitude: &Attitude) -> Vec<u8> {
+    // msb set for a magnetic heading
+    let heading = match attitude.heading {
+        Some(heading) => 0x8000 | ((heading.rem_euclid(360.0) * 10.0).round() as u16 % 3600),
+        None => 0xffff,
+    };
+
+    let mut m = vec![GDL90_FOREFLIGHT, FOREFLIGHT_AHRS];
+    m.extend_from_slice(&tenths(attitude.roll, 180.0).to_be_bytes());
+    m.extend_from_slice(&tenths(attitude.pitch, 180.0).to_be_bytes());
+    m.extend_from_slice(&heading.to_be_bytes());
+    // no airspeeds
+    m.extend_from_slice(&0xffffu16.to_be_bytes());
+    m.extend_from_slice(&0xffffu16.to_be_bytes());
+    m
+}
+
+/// `pressure_altitude` in feet, from a barometer
+pub fn stratux(attitude: &Attitude, pressure_altitude: Option<f64>) -> Vec<u8> {
+    let heading = match attitude.heading {
+        Some(heading) => tenths(heading.rem_euclid(360.0), 360.0),
+        None => INVALID,
+    };
+    let pressure_altitude = match pressure_altitude {
+        Some(altitude) => (altitude + 5000.5).clamp(0.0, 65534.0) as u16,
+        None => 0xffff,
+    };
+
+    let mut m = vec![GDL90_STRATUX_AHRS, 0x45, 0x01, 0x01];
+    for value in [
+        tenths(attitude.roll, 180.0),
+        tenths(attitude.pitch, 180.0),
+        heading,
+        tenths(attitude.slip_skid, 180.0),
+        tenths(attitude.turn_rate, 360.0),
+        tenths(attitude.g_load, 10.0),
+        INVALID,
+    ]
+    .iter()
+    {
+        m.extend_from_slice(&value.to_be_bytes());
+    }
+    m.extend_from_slice(&pressure_altitude.to_be_bytes());
+    m.extend_from_slice(&INVALID.to_be_bytes());
+    m.extend_from_slice(&INVALID.to_be_bytes());
+    m
+}
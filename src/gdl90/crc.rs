@@ -0,0 +1,35 @@
+/* CRC-16-CCITT frame check sequence of GDL90, generator polynomial
+ *
+ *   x^16 + x^12 + x^5 + 1
+ *
+ * Computed over the message id and data before byte stuffing, with an
+ * initial value of zero, and sent least significant byte first.
+ */
+pub const GDL90_GENERATOR_POLY: u16 = 0x1021;
+
+const GDL90_CRC_TABLE: [u16; 256] = crc_table();
+
+const fn crc_table() -> [u16; 256] {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ GDL90_GENERATOR_POLY,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+pub fn crc(message: &[u8]) -> u16 {
+    message.iter().fold(0u16, |crc, &byte| {
+        GDL90_CRC_TABLE[(crc >> 8) as usize] ^ (crc << 8) ^ byte as u16
+    })
+}
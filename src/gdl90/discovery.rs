@@ -0,0 +1,37 @@
+/* ForeFlight discovery. While it's open, ForeFlight broadcasts
+ *
+ *   {"App":"ForeFlight","GDL90":{"port":4000}}
+ *
+ * to UDP port 63093 every few seconds, asking for GDL90 at the address
+ * it was sent from on the port it names.
+ */
+
+pub const FOREFLIGHT_DISCOVERY_PORT: u16 = 63093;
+
+/// An EFB asking for GDL90
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovery {
+    pub app: String,
+    pub port: u16,
+}
+
+/* Raw value of a key in flat JSON, up to the next separator */
+fn value<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let start = json.find(&format!("\"{}\"", key))? + key.len() + 2;
+    let rest = json[start..].trim_start().strip_prefix(':')?.trim_start();
+    match rest.strip_prefix('"') {
+        Some(string) => string.split('"').next(),
+        None => rest.split(&[',', '}'][..]).next().map(str::trim),
+    }
+}
+
+/// Parse a discovery broadcast, None if it isn't one
+pub fn parse(datagram: &[u8]) -> Option<Discovery> {
+    let json = std::str::from_utf8(datagram).ok()?;
+    let gdl90 = &json[json.find("\"GDL90\"")?..];
+
+    Some(Discovery {
+        app: value(json, "App")?.to_string(),
+        port: value(gdl90, "port")?.parse().ok()?,
+    })
+}
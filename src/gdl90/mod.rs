@@ -0,0 +1,205 @@
+/* GDL90 data interface, what EFBs (ForeFlight, Garmin Pilot, ...) expect
+ * from a portable ADS-B receiver. Messages are framed as
+ *
+ *   0x7e id data... crc crc 0x7e
+ *
+ * with 0x7e and 0x7d in between escaped as 0x7d (byte ^ 0x20), and sent
+ * as UDP datagrams, by convention to port 4000.
+ *
+ * Once a second a heartbeat, ownship report and ownship geometric altitude
+ * go out, followed by a traffic report for every aircraft with a position
+ * except our own. Attitude goes out five times a second when there is an
+ * AHRS, and FIS-B uplinks are passed through as they are received for the
+ * EFB to decode itself.
+ */
+
+pub mod ahrs;
+pub mod crc;
+pub mod discovery;
+pub mod report;
+
+use log::*;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::sync::{broadcast, watch};
+
+use crate::adsb::SharedTracker;
+use crate::ahrs::Attitude;
+use crate::gps::Ownship;
+use crate::metrics::{Metrics, METRICS};
+use crate::sdr::uat;
+pub use report::Report;
+
+pub const GDL90_PORT: u16 = 4000;
+pub const GDL90_FLAG: u8 = 0x7e;
+pub const GDL90_ESCAPE: u8 = 0x7d;
+
+pub const GDL90_HEARTBEAT: u8 = 0x00;
+pub const GDL90_UPLINK: u8 = 0x07;
+pub const GDL90_OWNSHIP_REPORT: u8 = 0x0a;
+pub const GDL90_OWNSHIP_GEOMETRIC_ALTITUDE: u8 = 0x0b;
+pub const GDL90_TRAFFIC_REPORT: u8 = 0x14;
+pub const GDL90_STRATUX_AHRS: u8 = 0x4c;
+pub const GDL90_FOREFLIGHT: u8 = 0x65;
+
+const GDL90_INTERVAL: Duration = Duration::from_secs(1);
+const GDL90_AHRS_INTERVAL: Duration = Duration::from_millis(200);
+
+/* Vertical figure of merit not available, and the largest valid one (>= 32766 m) */
+const VFOM_UNAVAILABLE: u16 = 0x7fff;
+const VFOM_MAX: u16 = 0x7ffe;
+
+/* Uplink time of reception is counted in 80ns steps */
+const TOR_UNIT_NANOS: u32 = 80;
+const TOR_INVALID: u32 = 0xffffff;
+
+/// Add the crc, escape and flag a message (id and data)
+pub fn frame(message: &[u8]) -> Vec<u8> {
+    let crc = crc::crc(message).to_le_bytes();
+
+    let mut framed = Vec::with_capacity(message.len() + 6);
+    framed.push(GDL90_FLAG);
+    for &b in message.iter().chain(crc.iter()) {
+        if b == GDL90_FLAG || b == GDL90_ESCAPE {
+            framed.push(GDL90_ESCAPE);
+            framed.push(b ^ 0x20);
+        } else {
+            framed.push(b);
+        }
+    }
+    framed.push(GDL90_FLAG);
+    framed
+}
+
+/// Heartbeat, `at` gives the seconds since UTC midnight timestamp
+pub fn heartbeat(gps_valid: bool, at: SystemTime) -> Vec<u8> {
+    let secs = at
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |t| (t.as_secs() % 86400) as u32);
+
+    // GPS position valid, UAT initialized
+    let status1 = (gps_valid as u8) << 7 | 0x01;
+    // timestamp bit 16, UTC OK
+    let status2 = ((secs >> 16) as u8) << 7 | gps_valid as u8;
+    let timestamp = (secs as u16).to_le_bytes();
+
+    vec![
+        GDL90_HEARTBEAT,
+        status1,
+        status2,
+        timestamp[0],
+        timestamp[1],
+        0,
+        0,
+    ]
+}
+
+/// Ownship height above the WGS84 ellipsoid in feet
+pub fn geometric_altitude(altitude: f64, vertical_accuracy: Option<f64>) -> Vec<u8> {
+    let altitude = ((altitude / 5.0).round() as i32).clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    let vfom = match vertical_accuracy {
+        Some(accuracy) => (accuracy.round() as u16).min(VFOM_MAX),
+        None => VFOM_UNAVAILABLE,
+    };
+
+    let mut m = vec![GDL90_OWNSHIP_GEOMETRIC_ALTITUDE];
+    m.extend_from_slice(&altitude.to_be_bytes());
+    m.extend_from_slice(&vfom.to_be_bytes());
+    m
+}
+
+/// Uplink data, a UAT uplink payload with its time of reception within the second
+pub fn uplink(payload: &[u8], received_at: Option<SystemTime>) -> Vec<u8> {
+    let tor = received_at
+        .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+        .map_or(TOR_INVALID, |t| t.subsec_nanos() / TOR_UNIT_NANOS);
+
+    let mut m = Vec::with_capacity(4 + payload.len());
+    m.push(GDL90_UPLINK);
+    m.extend_from_slice(&tor.to_le_bytes()[..3]);
+    m.extend_from_slice(payload);
+    m
+}
+
+/// Everything that goes out in one second
+pub fn messages(tracker: &SharedTracker, gps: &Ownship, now: SystemTime) -> Vec<Vec<u8>> {
+    let snapshot = tracker.snapshot();
+    let mut messages = vec![heartbeat(gps.valid(now), now)];
+
+    if let Some(report) = Report::ownship(snapshot.ownship().map(|ac| &**ac), gps, now) {
+        messages.push(report.ownship_report());
+    }
+    if gps.valid(now) {
+        if let Some(height) = gps.height_above_ellipsoid {
+            messages.push(geometric_altitude(height, gps.vertical_accuracy));
+        }
+    }
+
+    for ac in snapshot.traffic().filter(|ac| ac.position().is_some()) {
+        messages.push(Report::from_aircraft(ac).traffic());
+    }
+
+    messages.iter().map(|m| frame(m)).collect()
+}
+
+/// Attitude in both the ForeFlight and Stratux flavours
+pub fn attitude_messages(attitude: &Attitude, gps: &Ownship, now: SystemTime) -> Vec<Vec<u8>> {
+    if !attitude.valid(now) {
+        return vec![];
+    }
+
+    vec![
+        frame(&ahrs::foreflight(attitude)),
+        frame(&ahrs::stratux(attitude, gps.baro_altitude(now))),
+    ]
+}
+
+/// Send GDL90 to `target` (an EFB or a broadcast address) until the socket fails
+pub async fn run(
+    tracker: SharedTracker,
+    ownship: watch::Receiver<Ownship>,
+    attitude: watch::Receiver<Attitude>,
+    mut uplinks: broadcast::Receiver<uat::Frame>,
+    target: SocketAddr,
+) -> io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.set_broadcast(true)?;
+    let _client = METRICS.gdl90_client();
+    let mut interval = tokio::time::interval(GDL90_INTERVAL);
+    let mut ahrs_interval = tokio::time::interval(GDL90_AHRS_INTERVAL);
+    let mut receiving_uplinks = true;
+
+    loop {
+        let gps = ownship.borrow().clone();
+        let messages = tokio::select! {
+            _ = interval.tick() => messages(&tracker, &gps, SystemTime::now()),
+            _ = ahrs_interval.tick() => {
+                let attitude = *attitude.borrow();
+                attitude_messages(&attitude, &gps, SystemTime::now())
+            }
+            received = uplinks.recv(), if receiving_uplinks => match received {
+                Ok(received) => vec![frame(&uplink(
+                    received.bytes(),
+                    Some(received.received_at()),
+                ))],
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("gdl90: dropped {} uplinks", n);
+                    vec![]
+                }
+                // no UAT receiver
+                Err(broadcast::error::RecvError::Closed) => {
+                    receiving_uplinks = false;
+                    vec![]
+                }
+            },
+        };
+
+        for message in messages {
+            socket.send_to(&message, target).await?;
+            Metrics::inc(&METRICS.gdl90_messages);
+        }
+        trace!("gdl90: sent to {}", target);
+    }
+}
@@ -0,0 +1,267 @@
+/* Traffic (0x14) and Ownship (0x0A) reports, both share one layout:
+ *
+ *   st aa aa aa ll ll ll nn nn nn dd dm ia hh hv vv tt ee cc cc cc cc cc cc cc cc px
+ *
+ *   s   traffic alert status       t   address type
+ *   a   participant address        l/n latitude/longitude, 24 bit semicircles
+ *   ddd (altitude + 1000) / 25 ft  m   airborne, extrapolated, track type
+ *   i   NIC                        a   NACp
+ *   hhh ground speed in knots      vvv vertical rate in 64 fpm
+ *   tt  track, 360/256 degrees     ee  emitter category
+ *   c   callsign, space padded     p   emergency/priority code
+ */
+
+use std::time::SystemTime;
+
+use super::{GDL90_OWNSHIP_REPORT, GDL90_TRAFFIC_REPORT};
+use crate::adsb::es::EmergencyState;
+use crate::adsb::{AddressType, Aircraft, SQUAWK_EMERGENCY, SQUAWK_HIJACK, SQUAWK_RADIO_FAILURE};
+use crate::geo::Position;
+use crate::gps::Ownship;
+
+pub const GDL90_REPORT_LEN: usize = 28;
+
+/* Invalid/unavailable field values */
+const ALTITUDE_INVALID: u16 = 0xfff;
+const GROUND_SPEED_INVALID: u16 = 0xfff;
+const VERTICAL_RATE_INVALID: u16 = 0x800;
+
+/* User equivalent range error (m) to turn an NMEA HDOP into an accuracy */
+const GPS_UERE: f64 = 4.0;
+
+/// Address type of a report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportAddress {
+    AdsbIcao = 0,
+    AdsbSelfAssigned = 1,
+    TisbIcao = 2,
+    TisbTrackFile = 3,
+    SurfaceVehicle = 4,
+    GroundStation = 5,
+}
+
+impl From<AddressType> for ReportAddress {
+    fn from(address_type: AddressType) -> ReportAddress {
+        match address_type {
+            AddressType::Icao | AddressType::AdsR => ReportAddress::AdsbIcao,
+            AddressType::NonIcao | AddressType::AdsROther | AddressType::Anonymous => {
+                ReportAddress::AdsbSelfAssigned
+            }
+            AddressType::TisB => ReportAddress::TisbIcao,
+            AddressType::TisBOther => ReportAddress::TisbTrackFile,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub alert: bool,
+    pub address_type: ReportAddress,
+    pub address: u32,
+    pub position: Option<Position>,
+    /// Pressure altitude in feet
+    pub altitude: Option<i32>,
+    pub airborne: bool,
+    /// Degrees true
+    pub track: Option<f64>,
+    pub nic: u8,
+    pub nac_p: u8,
+    /// Knots
+    pub ground_speed: Option<f64>,
+    /// Feet per minute
+    pub vertical_rate: Option<i32>,
+    pub emitter_category: u8,
+    pub callsign: Option<String>,
+    pub emergency: u8,
+}
+
+impl Report {
+    pub fn from_aircraft(ac: &Aircraft) -> Report {
+        Report {
+            alert: false,
+            address_type: ac.address_type.into(),
+            address: ac.address.address,
+            position: ac.position(),
+            altitude: match ac.alt_is_gnss {
+                Some(true) => None,
+                _ => ac.altitude,
+            },
+            airborne: ac.on_ground != Some(true),
+            track: ac.track,
+            nic: ac.integrity.nic.unwrap_or(0),
+            nac_p: ac.integrity.nac_p.unwrap_or(0),
+            ground_speed: ac.ground_speed,
+            vertical_rate: ac.vertical_rate,
+            emitter_category: ac.emitter_category.map_or(0, emitter_category),
+            callsign: ac.callsign.clone(),
+            emergency: emergency_code(ac),
+        }
+    }
+
+    /// Ownship report from the GPS, filled in with what our own transponder
+    /// says about us. The GPS is more current for position and motion, the
+    /// transponder has the pressure altitude and identity.
+    pub fn ownship(
+        transponder: Option<&Aircraft>,
+        gps: &Ownship,
+        now: SystemTime,
+    ) -> Option<Report> {
+        let mut report = match transponder {
+            Some(ac) => Report::from_aircraft(ac),
+            None if gps.valid(now) => Report {
+                alert: false,
+                address_type: ReportAddress::AdsbIcao,
+                address: 0,
+                position: None,
+                altitude: None,
+                airborne: true,
+                track: None,
+                nic: 0,
+                nac_p: 0,
+                ground_speed: None,
+                vertical_rate: None,
+                emitter_category: 0,
+                callsign: None,
+                emergency: 0,
+            },
+            None => return None,
+        };
+
+        // the transponder's is what ATC sees, a barometer of our own will do
+        report.altitude = report.altitude.or_else(|| {
+            gps.baro_altitude(now)
+                .map(|altitude| altitude.round() as i32)
+        });
+
+        if gps.valid(now) {
+            report.position = gps.position;
+            report.track = gps.track.or(report.track);
+            report.ground_speed = gps.ground_speed.or(report.ground_speed);
+            report.vertical_rate = gps
+                .vertical_speed
+                .map(|rate| rate.round() as i32)
+                .or(report.vertical_rate);
+
+            let accuracy = gps
+                .horizontal_accuracy
+                .or_else(|| gps.hdop.map(|hdop| hdop * GPS_UERE));
+            if let Some(accuracy) = accuracy {
+                report.nac_p = nac_p(accuracy);
+                // 95% accuracy bound is taken as the containment radius
+                report.nic = nic(accuracy * 2.0);
+            }
+        }
+
+        Some(report)
+    }
+
+    pub fn traffic(&self) -> Vec<u8> {
+        self.encode(GDL90_TRAFFIC_REPORT)
+    }
+
+    pub fn ownship_report(&self) -> Vec<u8> {
+        self.encode(GDL90_OWNSHIP_REPORT)
+    }
+
+    fn encode(&self, id: u8) -> Vec<u8> {
+        let mut m = Vec::with_capacity(GDL90_REPORT_LEN);
+        m.push(id);
+        m.push((self.alert as u8) << 4 | self.address_type as u8);
+        m.extend_from_slice(&self.address.to_be_bytes()[1..]);
+
+        let (lat, lon) = match self.position {
+            Some(position) => (
+                semicircles(position.latitude),
+                semicircles(position.longitude),
+            ),
+            None => (0, 0),
+        };
+        m.extend_from_slice(&lat.to_be_bytes()[1..]);
+        m.extend_from_slice(&lon.to_be_bytes()[1..]);
+
+        let altitude = match self.altitude {
+            Some(altitude) => ((altitude + 1000) / 25).clamp(0, 0xffe) as u16,
+            None => ALTITUDE_INVALID,
+        };
+        let misc = (self.airborne as u16) << 3 | self.track.is_some() as u16;
+        m.extend_from_slice(&(altitude << 4 | misc).to_be_bytes());
+
+        let (nic, nac_p) = match self.position {
+            Some(_) => (self.nic, self.nac_p),
+            None => (0, 0),
+        };
+        m.push(nic << 4 | nac_p & 0x0f);
+
+        let ground_speed = match self.ground_speed {
+            Some(speed) => (speed.round() as u16).min(0xffe),
+            None => GROUND_SPEED_INVALID,
+        };
+        let vertical_rate = match self.vertical_rate {
+            Some(rate) => ((rate as f64 / 64.0).round() as i16).clamp(-510, 510) as u16 & 0xfff,
+            None => VERTICAL_RATE_INVALID,
+        };
+        let velocity = (ground_speed as u32) << 12 | vertical_rate as u32;
+        m.extend_from_slice(&velocity.to_be_bytes()[1..]);
+
+        m.push(match self.track {
+            Some(track) => (track.rem_euclid(360.0) * 256.0 / 360.0).round() as u16 as u8,
+            None => 0,
+        });
+        m.push(self.emitter_category);
+
+        let mut callsign = [b' '; 8];
+        if let Some(cs) = &self.callsign {
+            for (c, b) in callsign.iter_mut().zip(cs.bytes()) {
+                *c = b;
+            }
+        }
+        m.extend_from_slice(&callsign);
+        m.push(self.emergency << 4);
+
+        m
+    }
+}
+
+/// 24 bit signed fraction of a half circle, truncated like the spec examples
+fn semicircles(degrees: f64) -> i32 {
+    ((degrees * (1 << 23) as f64 / 180.0) as i32).clamp(-(1 << 23), (1 << 23) - 1) & 0xffffff
+}
+
+/// GDL90 emitter category from an ADS-B set/category (0xA0..0xD7)
+fn emitter_category(category: u8) -> u8 {
+    let (set, category) = (category >> 4, category & 0x07);
+    match set {
+        0xa => category,
+        0xb => 8 + category,
+        0xc => 16 + category,
+        _ => 0,
+    }
+}
+
+/// Emergency/priority code, the emergency squawks say the same as the
+/// emergency state for transponders that don't send it
+fn emergency_code(ac: &Aircraft) -> u8 {
+    match (ac.emergency, ac.squawk) {
+        (Some(state), _) if state != EmergencyState::None => state as u8,
+        (_, Some(SQUAWK_EMERGENCY)) => EmergencyState::General as u8,
+        (_, Some(SQUAWK_RADIO_FAILURE)) => EmergencyState::NoCommunications as u8,
+        (_, Some(SQUAWK_HIJACK)) => EmergencyState::UnlawfulInterference as u8,
+        _ => 0,
+    }
+}
+
+/// NACp for a 95% horizontal accuracy in meters
+fn nac_p(accuracy: f64) -> u8 {
+    const BOUNDS: [f64; 11] = [
+        18520.0, 7408.0, 3704.0, 1852.0, 926.0, 555.6, 185.2, 92.6, 30.0, 10.0, 3.0,
+    ];
+    BOUNDS.iter().take_while(|&&bound| accuracy < bound).count() as u8
+}
+
+/// NIC for a containment radius in meters
+fn nic(rc: f64) -> u8 {
+    const BOUNDS: [f64; 11] = [
+        37040.0, 14816.0, 7408.0, 3704.0, 1852.0, 1111.2, 370.4, 185.2, 75.0, 25.0, 7.5,
+    ];
+    BOUNDS.iter().take_while(|&&bound| rc < bound).count() as u8
+}
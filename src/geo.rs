@@ -0,0 +1,47 @@
+/* Great circle math on a spherical earth, plenty accurate for traffic
+ * within radio range. */
+
+/// Mean earth radius in meters
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+pub const METERS_PER_NM: f64 = 1852.0;
+pub const METERS_PER_FOOT: f64 = 0.3048;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    /// Degrees, north positive
+    pub latitude: f64,
+    /// Degrees, east positive
+    pub longitude: f64,
+}
+
+impl Position {
+    pub fn new(latitude: f64, longitude: f64) -> Position {
+        Position {
+            latitude,
+            longitude,
+        }
+    }
+
+    /// Great circle distance in meters
+    pub fn distance(&self, other: &Position) -> f64 {
+        let lat1 = self.latitude.to_radians();
+        let lat2 = other.latitude.to_radians();
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().atan2((1.0 - a).sqrt())
+    }
+
+    /// Initial true bearing towards `other` in degrees, 0..360
+    pub fn bearing(&self, other: &Position) -> f64 {
+        let lat1 = self.latitude.to_radians();
+        let lat2 = other.latitude.to_radians();
+        let dlon = (other.longitude - self.longitude).to_radians();
+
+        let y = dlon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * dlon.cos();
+        (y.atan2(x).to_degrees() + 360.0) % 360.0
+    }
+}
@@ -0,0 +1,144 @@
+pub mod nmea;
+pub mod ownship;
+pub mod ubx;
+
+use bytes::{Buf, BytesMut};
+use log::*;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::AsyncRead;
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio_serial::SerialPortBuilderExt;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{Decoder, FramedRead};
+
+pub use ownship::Ownship;
+
+// longest NMEA sentence allowed is 82 characters, be generous with broken ones
+const NMEA_MAX_LEN: usize = 256;
+
+// what most GPS receivers talk NMEA at out of the box
+pub const GPS_DEFAULT_BAUD: u32 = 9600;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpsMessage {
+    Nmea(nmea::Sentence),
+    Ubx(ubx::UbxMessage),
+}
+
+/// Splits a GPS byte stream into NMEA sentences and UBX frames, u-blox
+/// receivers happily interleave both on one port. Anything that is neither
+/// or fails its checksum is skipped.
+pub struct GpsDecoder {}
+
+impl Default for GpsDecoder {
+    fn default() -> GpsDecoder {
+        GpsDecoder::new()
+    }
+}
+
+impl GpsDecoder {
+    pub fn new() -> GpsDecoder {
+        GpsDecoder {}
+    }
+}
+
+impl Decoder for GpsDecoder {
+    type Item = GpsMessage;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            // resync on the next sentence or frame start
+            let start = src
+                .iter()
+                .enumerate()
+                .position(|(i, &b)| b == b'$' || src[i..].starts_with(&ubx::UBX_SYNC));
+            match start {
+                Some(start) => src.advance(start),
+                None => {
+                    // keep a trailing half UBX sync
+                    let keep = if src.last() == Some(&ubx::UBX_SYNC[0]) {
+                        1
+                    } else {
+                        0
+                    };
+                    src.advance(src.len() - keep);
+                    return Ok(None);
+                }
+            }
+
+            if src[0] == b'$' {
+                let end = match src.iter().position(|&b| b == b'\n') {
+                    Some(end) => end,
+                    None if src.len() > NMEA_MAX_LEN => {
+                        src.advance(1);
+                        continue;
+                    }
+                    None => return Ok(None),
+                };
+
+                let line = src.split_to(end + 1);
+                match nmea::parse(&String::from_utf8_lossy(&line)) {
+                    Ok(sentence) => return Ok(Some(GpsMessage::Nmea(sentence))),
+                    Err(e) => debug!("{}", e),
+                }
+            } else {
+                let len = match ubx::frame_len(src) {
+                    Some(len) => len,
+                    None => return Ok(None),
+                };
+                if src.len() < len {
+                    src.reserve(len - src.len());
+                    return Ok(None);
+                }
+
+                match ubx::parse(&src[..len]) {
+                    Ok(message) => {
+                        src.advance(len);
+                        return Ok(Some(GpsMessage::Ubx(message)));
+                    }
+                    Err(e) => {
+                        // a bogus length could swallow good data, only skip the sync
+                        debug!("{}", e);
+                        src.advance(2);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Open a GPS source: `tcp://host:port`, a serial device or pty under /dev
+/// (at `baud`), or anything else as a file, e.g. a recorded NMEA/UBX log.
+pub async fn open(source: &str, baud: u32) -> io::Result<Pin<Box<dyn AsyncRead + Send>>> {
+    if let Some(address) = source.strip_prefix("tcp://") {
+        return Ok(Box::pin(TcpStream::connect(address).await?));
+    }
+
+    if source.starts_with("/dev/") {
+        let port = tokio_serial::new(source, baud)
+            .open_native_async()
+            .map_err(io::Error::other)?;
+        return Ok(Box::pin(port));
+    }
+
+    Ok(Box::pin(tokio::fs::File::open(source).await?))
+}
+
+/// Feed everything read from `src` into the ownship model until it ends.
+/// The model is shared with the barometer, hence updated in place.
+pub async fn run<T: AsyncRead>(src: T, ownship: Arc<watch::Sender<Ownship>>) -> io::Result<()> {
+    let mut messages = Box::pin(FramedRead::new(src, GpsDecoder::new()));
+
+    while let Some(message) = messages.next().await {
+        let message = message?;
+        trace!("gps: {:?}", message);
+
+        ownship.send_if_modified(|state| state.update(&message));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+/* NMEA-0183 sentence parsing.
+ *
+ *   $GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47
+ *
+ * Only the sentences needed for an ownship fix are decoded. Any talker
+ * (GP, GN, GL, GA, ...) is accepted, empty fields become None.
+ */
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sentence {
+    Gga(Gga),
+    Rmc(Rmc),
+    Vtg(Vtg),
+    Gsa(Gsa),
+    Gsv(Gsv),
+    Unknown(String),
+}
+
+/// Fix data
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Gga {
+    /// UTC seconds since midnight
+    pub time: Option<f64>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// 0 invalid, 1 GPS, 2 DGPS/SBAS, 4 RTK fixed, 5 RTK float, 6 estimated
+    pub quality: u8,
+    pub satellites: Option<u8>,
+    pub hdop: Option<f64>,
+    /// Meters above mean sea level
+    pub altitude: Option<f64>,
+    /// Height of the geoid above the WGS84 ellipsoid in meters
+    pub geoid_separation: Option<f64>,
+}
+
+/// Recommended minimum data
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Rmc {
+    pub time: Option<f64>,
+    /// Status A (valid), V is a receiver warning
+    pub valid: bool,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Knots
+    pub ground_speed: Option<f64>,
+    /// Degrees true
+    pub track: Option<f64>,
+    /// (day, month, year)
+    pub date: Option<(u8, u8, u16)>,
+    /// Degrees, east positive
+    pub magnetic_variation: Option<f64>,
+}
+
+/// Track and ground speed
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Vtg {
+    pub track: Option<f64>,
+    pub track_magnetic: Option<f64>,
+    /// Knots
+    pub ground_speed: Option<f64>,
+}
+
+/// DOP and active satellites
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Gsa {
+    /// 1 no fix, 2 2D fix, 3 3D fix
+    pub fix_type: u8,
+    pub prns: Vec<u16>,
+    pub pdop: Option<f64>,
+    pub hdop: Option<f64>,
+    pub vdop: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Satellite {
+    pub prn: u16,
+    /// Degrees
+    pub elevation: Option<u8>,
+    pub azimuth: Option<u16>,
+    /// dB-Hz, None when not tracked
+    pub snr: Option<u8>,
+}
+
+/// Satellites in view, one sentence of a group of `count`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Gsv {
+    pub talker: String,
+    pub count: u8,
+    pub index: u8,
+    pub in_view: u8,
+    pub satellites: Vec<Satellite>,
+}
+
+/// XOR of everything between '$' and '*'
+pub fn checksum(body: &str) -> u8 {
+    body.bytes().fold(0, |sum, b| sum ^ b)
+}
+
+/// Parse one sentence, with or without trailing line ending. The checksum is
+/// verified if present.
+pub fn parse(line: &str) -> Result<Sentence, String> {
+    let line = line.trim_end();
+    if !line.is_ascii() {
+        return Err(format!("garbage in nmea sentence: {:?}", line));
+    }
+    let body = line
+        .strip_prefix('$')
+        .ok_or_else(|| format!("not an nmea sentence: {}", line))?;
+
+    let body = match body.rfind('*') {
+        Some(star) => {
+            let expected = u8::from_str_radix(&body[star + 1..], 16)
+                .map_err(|_| format!("bad checksum field: {}", line))?;
+            let body = &body[..star];
+            if checksum(body) != expected {
+                return Err(format!("checksum mismatch: {}", line));
+            }
+            body
+        }
+        None => body,
+    };
+
+    let fields: Vec<&str> = body.split(',').collect();
+    if fields[0].len() < 5 {
+        // proprietary sentences like $PUBX
+        return Ok(Sentence::Unknown(fields[0].to_string()));
+    }
+    let (talker, kind) = fields[0].split_at(fields[0].len() - 3);
+    let f = Fields(&fields);
+
+    let sentence = match kind {
+        "GGA" => Sentence::Gga(Gga {
+            time: f.time(1),
+            latitude: f.coordinate(2, 3),
+            longitude: f.coordinate(4, 5),
+            quality: f.number(6).unwrap_or(0),
+            satellites: f.number(7),
+            hdop: f.number(8),
+            altitude: f.number(9),
+            geoid_separation: f.number(11),
+        }),
+        "RMC" => Sentence::Rmc(Rmc {
+            time: f.time(1),
+            valid: f.get(2) == Some("A"),
+            latitude: f.coordinate(3, 4),
+            longitude: f.coordinate(5, 6),
+            ground_speed: f.number(7),
+            track: f.number(8),
+            date: f.date(9),
+            magnetic_variation: f.number(10).map(|v: f64| match f.get(11) {
+                Some("W") => -v,
+                _ => v,
+            }),
+        }),
+        "VTG" => Sentence::Vtg(Vtg {
+            track: f.number(1),
+            track_magnetic: f.number(3),
+            ground_speed: f.number(5),
+        }),
+        "GSA" => Sentence::Gsa(Gsa {
+            fix_type: f.number(2).unwrap_or(1),
+            prns: (3..15).filter_map(|i| f.number(i)).collect(),
+            pdop: f.number(15),
+            hdop: f.number(16),
+            vdop: f.number(17),
+        }),
+        "GSV" => Sentence::Gsv(Gsv {
+            talker: talker.to_string(),
+            count: f.number(1).unwrap_or(1),
+            index: f.number(2).unwrap_or(1),
+            in_view: f.number(3).unwrap_or(0),
+            satellites: (0..4)
+                .map(|i| 4 + i * 4)
+                .filter_map(|i| {
+                    Some(Satellite {
+                        prn: f.number(i)?,
+                        elevation: f.number(i + 1),
+                        azimuth: f.number(i + 2),
+                        snr: f.number(i + 3),
+                    })
+                })
+                .collect(),
+        }),
+        _ => Sentence::Unknown(fields[0].to_string()),
+    };
+
+    Ok(sentence)
+}
+
+struct Fields<'a>(&'a [&'a str]);
+
+impl<'a> Fields<'a> {
+    fn get(&self, i: usize) -> Option<&'a str> {
+        match self.0.get(i) {
+            Some(field) if !field.is_empty() => Some(field),
+            _ => None,
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&self, i: usize) -> Option<T> {
+        self.get(i)?.parse().ok()
+    }
+
+    // hhmmss.sss
+    fn time(&self, i: usize) -> Option<f64> {
+        let field = self.get(i)?;
+        if field.len() < 6 {
+            return None;
+        }
+        let hours: f64 = field[0..2].parse().ok()?;
+        let minutes: f64 = field[2..4].parse().ok()?;
+        let seconds: f64 = field[4..].parse().ok()?;
+        Some(hours * 3600.0 + minutes * 60.0 + seconds)
+    }
+
+    // ddmmyy
+    fn date(&self, i: usize) -> Option<(u8, u8, u16)> {
+        let field = self.get(i)?;
+        if field.len() != 6 {
+            return None;
+        }
+        Some((
+            field[0..2].parse().ok()?,
+            field[2..4].parse().ok()?,
+            2000 + field[4..6].parse::<u16>().ok()?,
+        ))
+    }
+
+    // (d)ddmm.mmmm plus hemisphere
+    fn coordinate(&self, i: usize, hemisphere: usize) -> Option<f64> {
+        let field = self.get(i)?;
+        let dot = field.find('.').unwrap_or(field.len());
+        if dot < 3 {
+            return None;
+        }
+        let degrees: f64 = field[..dot - 2].parse().ok()?;
+        let minutes: f64 = field[dot - 2..].parse().ok()?;
+        let value = degrees + minutes / 60.0;
+
+        match self.get(hemisphere)? {
+            "N" | "E" => Some(value),
+            "S" | "W" => Some(-value),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,239 @@
+/* Ownship state assembled from GPS messages.
+ *
+ * NMEA spreads a fix over several sentences (GGA for position and
+ * altitude, RMC/VTG for motion, GSA/GSV for satellites), UBX NAV-PVT has
+ * it all at once. Either way the latest values end up in here, along with
+ * pressure altitude from a barometer if there is one.
+ */
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use super::nmea::{Satellite, Sentence};
+use super::ubx::UbxMessage;
+use super::GpsMessage;
+use crate::adsb::{OwnshipFix, ReceiverLocation};
+use crate::baro;
+use crate::geo::{Position, METERS_PER_FOOT, METERS_PER_NM};
+
+/* A fix older than this is no longer used */
+pub const OWNSHIP_FIX_TIMEOUT: Duration = Duration::from_secs(5);
+/* Neither is a barometer reading */
+pub const OWNSHIP_BARO_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Fix {
+    #[default]
+    None,
+    Fix2D,
+    Fix3D,
+}
+
+
+#[derive(Debug, Clone, Default)]
+pub struct Ownship {
+    pub fix: Fix,
+    /// NMEA GGA quality indicator, 2 is differential (SBAS/WAAS)
+    pub quality: u8,
+    pub position: Option<Position>,
+    /// Feet above mean sea level
+    pub altitude: Option<f64>,
+    /// Feet above the WGS84 ellipsoid, what GDL90 ownship geometric altitude wants
+    pub height_above_ellipsoid: Option<f64>,
+    /// Knots
+    pub ground_speed: Option<f64>,
+    /// Degrees true
+    pub track: Option<f64>,
+    /// Feet per minute, UBX only
+    pub vertical_speed: Option<f64>,
+    pub satellites_used: Option<u8>,
+    pub hdop: Option<f64>,
+    pub vdop: Option<f64>,
+    pub pdop: Option<f64>,
+    /// Estimated position accuracy in meters, UBX only
+    pub horizontal_accuracy: Option<f64>,
+    pub vertical_accuracy: Option<f64>,
+    /// Satellites in view per talker (GP, GL, GA, ...)
+    pub satellites: HashMap<String, Vec<Satellite>>,
+    /// When the last position was received
+    pub last_fix: Option<SystemTime>,
+    /// Feet in the standard atmosphere, same reference as Mode C/S altitude
+    pub pressure_altitude: Option<f64>,
+    /// When the last barometer reading was received
+    pub last_baro: Option<SystemTime>,
+
+    gsv_pending: HashMap<String, Vec<Satellite>>,
+}
+
+impl Ownship {
+    /// Position with a recent valid fix
+    pub fn valid(&self, now: SystemTime) -> bool {
+        self.fix != Fix::None
+            && self.position.is_some()
+            && self.last_fix.is_some_and(|at| {
+                now.duration_since(at).unwrap_or_default() <= OWNSHIP_FIX_TIMEOUT
+            })
+    }
+
+    /// Recent pressure altitude from the barometer
+    pub fn baro_altitude(&self, now: SystemTime) -> Option<f64> {
+        let fresh = self.last_baro.is_some_and(|at| {
+            now.duration_since(at).unwrap_or_default() <= OWNSHIP_BARO_TIMEOUT
+        });
+        self.pressure_altitude.filter(|_| fresh)
+    }
+
+    /// Altitude to compare with traffic: pressure altitude if there is a
+    /// barometer, GPS altitude as the next best thing
+    pub fn reference_altitude(&self, now: SystemTime) -> Option<f64> {
+        self.baro_altitude(now).or(self.altitude)
+    }
+
+    /// The current fix for ownship detection, if valid
+    pub fn fix(&self, now: SystemTime) -> Option<OwnshipFix> {
+        if !self.valid(now) {
+            return None;
+        }
+        Some(OwnshipFix {
+            position: self.position?,
+            altitude: self.reference_altitude(now),
+            at: self.last_fix?,
+        })
+    }
+
+    /// Where the receiver is if it moves along with us
+    pub fn location(&self) -> Option<ReceiverLocation> {
+        let altitude = self.reference_altitude(SystemTime::now());
+        self.position.map(|position| ReceiverLocation {
+            position,
+            altitude: altitude.map(|altitude| altitude.round() as i32),
+        })
+    }
+
+    pub fn update_baro(&mut self, reading: &baro::Reading) {
+        self.pressure_altitude = Some(reading.pressure_altitude());
+        self.last_baro = Some(SystemTime::now());
+    }
+
+    /// Apply a message, returns true if anything other than a partial
+    /// satellite list changed
+    pub fn update(&mut self, message: &GpsMessage) -> bool {
+        match message {
+            GpsMessage::Nmea(sentence) => self.update_nmea(sentence),
+            GpsMessage::Ubx(message) => self.update_ubx(message),
+        }
+    }
+
+    fn update_nmea(&mut self, sentence: &Sentence) -> bool {
+        let now = SystemTime::now();
+
+        match sentence {
+            Sentence::Gga(gga) => {
+                self.quality = gga.quality;
+                if gga.quality == 0 {
+                    self.fix = Fix::None;
+                    return true;
+                }
+                if let (Some(lat), Some(lon)) = (gga.latitude, gga.longitude) {
+                    self.position = Some(Position::new(lat, lon));
+                    self.last_fix = Some(now);
+                    if self.fix == Fix::None {
+                        // GSA will tell 2D from 3D
+                        self.fix = Fix::Fix2D;
+                    }
+                }
+                self.satellites_used = gga.satellites.or(self.satellites_used);
+                self.hdop = gga.hdop.or(self.hdop);
+                if let Some(altitude) = gga.altitude {
+                    self.altitude = Some(altitude / METERS_PER_FOOT);
+                    self.height_above_ellipsoid = gga
+                        .geoid_separation
+                        .map(|separation| (altitude + separation) / METERS_PER_FOOT);
+                }
+            }
+            Sentence::Rmc(rmc) => {
+                if !rmc.valid {
+                    self.fix = Fix::None;
+                    return true;
+                }
+                if let (Some(lat), Some(lon)) = (rmc.latitude, rmc.longitude) {
+                    self.position = Some(Position::new(lat, lon));
+                    self.last_fix = Some(now);
+                    if self.fix == Fix::None {
+                        self.fix = Fix::Fix2D;
+                    }
+                }
+                self.ground_speed = rmc.ground_speed.or(self.ground_speed);
+                self.track = rmc.track.or(self.track);
+            }
+            Sentence::Vtg(vtg) => {
+                self.ground_speed = vtg.ground_speed.or(self.ground_speed);
+                self.track = vtg.track.or(self.track);
+            }
+            Sentence::Gsa(gsa) => {
+                self.fix = match gsa.fix_type {
+                    2 => Fix::Fix2D,
+                    3 => Fix::Fix3D,
+                    _ => Fix::None,
+                };
+                self.pdop = gsa.pdop.or(self.pdop);
+                self.hdop = gsa.hdop.or(self.hdop);
+                self.vdop = gsa.vdop.or(self.vdop);
+            }
+            Sentence::Gsv(gsv) => {
+                let pending = self
+                    .gsv_pending
+                    .entry(gsv.talker.clone())
+                    .or_default();
+                if gsv.index <= 1 {
+                    pending.clear();
+                }
+                pending.extend(gsv.satellites.iter().cloned());
+
+                if gsv.index < gsv.count {
+                    return false;
+                }
+                let complete = self.gsv_pending.remove(&gsv.talker).unwrap_or_default();
+                self.satellites.insert(gsv.talker.clone(), complete);
+            }
+            Sentence::Unknown(_) => return false,
+        }
+
+        true
+    }
+
+    fn update_ubx(&mut self, message: &UbxMessage) -> bool {
+        match message {
+            UbxMessage::NavPvt(pvt) => {
+                self.fix = match (pvt.fix_ok, pvt.fix_type) {
+                    (true, 2) => Fix::Fix2D,
+                    (true, 3) | (true, 4) => Fix::Fix3D,
+                    _ => Fix::None,
+                };
+                self.satellites_used = Some(pvt.satellites);
+                self.pdop = Some(pvt.pdop);
+                if self.fix == Fix::None {
+                    return true;
+                }
+
+                self.position = Some(Position::new(pvt.latitude, pvt.longitude));
+                self.last_fix = Some(SystemTime::now());
+                self.altitude = Some(pvt.height_msl / METERS_PER_FOOT);
+                self.height_above_ellipsoid = Some(pvt.height / METERS_PER_FOOT);
+                self.ground_speed = Some(pvt.ground_speed * 3600.0 / METERS_PER_NM);
+                self.track = Some(pvt.track);
+                self.vertical_speed = Some(-pvt.velocity_down / METERS_PER_FOOT * 60.0);
+                self.horizontal_accuracy = Some(pvt.horizontal_accuracy);
+                self.vertical_accuracy = Some(pvt.vertical_accuracy);
+            }
+            UbxMessage::NavDop(dop) => {
+                self.pdop = Some(dop.pdop);
+                self.hdop = Some(dop.hdop);
+                self.vdop = Some(dop.vdop);
+            }
+            UbxMessage::Unknown { .. } => return false,
+        }
+
+        true
+    }
+}
@@ -0,0 +1,162 @@
+/* u-blox UBX binary protocol.
+ *
+ *   0xb5 0x62 class id length(u16 le) payload ck_a ck_b
+ *
+ * The checksum is an 8 bit Fletcher over class, id, length and payload.
+ * NAV-PVT carries everything an ownship fix needs in one message.
+ */
+
+pub const UBX_SYNC: [u8; 2] = [0xb5, 0x62];
+/// Sync, class, id and length before the payload
+pub const UBX_HEADER_LEN: usize = 6;
+
+const NAV: u8 = 0x01;
+const NAV_DOP: u8 = 0x04;
+const NAV_PVT: u8 = 0x07;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UbxMessage {
+    NavPvt(NavPvt),
+    NavDop(NavDop),
+    Unknown { class: u8, id: u8 },
+}
+
+/// Navigation position velocity time solution (UBX-NAV-PVT)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NavPvt {
+    /// GPS time of week in ms
+    pub itow: u32,
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// 0 no fix, 1 dead reckoning, 2 2D, 3 3D, 4 GNSS + dead reckoning, 5 time only
+    pub fix_type: u8,
+    /// gnssFixOK flag
+    pub fix_ok: bool,
+    pub satellites: u8,
+    /// Degrees
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Height above ellipsoid and above mean sea level in meters
+    pub height: f64,
+    pub height_msl: f64,
+    /// Horizontal and vertical accuracy estimates in meters
+    pub horizontal_accuracy: f64,
+    pub vertical_accuracy: f64,
+    /// NED velocity in m/s
+    pub velocity_north: f64,
+    pub velocity_east: f64,
+    pub velocity_down: f64,
+    /// m/s
+    pub ground_speed: f64,
+    /// Heading of motion in degrees
+    pub track: f64,
+    pub pdop: f64,
+}
+
+/// Dilution of precision (UBX-NAV-DOP)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NavDop {
+    pub itow: u32,
+    pub gdop: f64,
+    pub pdop: f64,
+    pub tdop: f64,
+    pub vdop: f64,
+    pub hdop: f64,
+}
+
+pub fn checksum(data: &[u8]) -> (u8, u8) {
+    data.iter().fold((0u8, 0u8), |(a, b), &byte| {
+        let a = a.wrapping_add(byte);
+        (a, b.wrapping_add(a))
+    })
+}
+
+/// Total length of the frame at the start of `buf` once its header is there
+pub fn frame_len(buf: &[u8]) -> Option<usize> {
+    if buf.len() < UBX_HEADER_LEN {
+        return None;
+    }
+    Some(UBX_HEADER_LEN + u16::from_le_bytes([buf[4], buf[5]]) as usize + 2)
+}
+
+/// Parse one complete frame, sync bytes included
+pub fn parse(frame: &[u8]) -> Result<UbxMessage, String> {
+    if frame.len() < UBX_HEADER_LEN + 2 || frame[0..2] != UBX_SYNC {
+        return Err("not a ubx frame".to_string());
+    }
+    if frame_len(frame) != Some(frame.len()) {
+        return Err(format!("ubx length mismatch ({} bytes)", frame.len()));
+    }
+
+    let (ck_a, ck_b) = checksum(&frame[2..frame.len() - 2]);
+    if [ck_a, ck_b] != frame[frame.len() - 2..] {
+        return Err("ubx checksum mismatch".to_string());
+    }
+
+    let (class, id) = (frame[2], frame[3]);
+    let p = Payload(&frame[UBX_HEADER_LEN..frame.len() - 2]);
+
+    let message = match (class, id) {
+        (NAV, NAV_PVT) if p.0.len() >= 92 => UbxMessage::NavPvt(NavPvt {
+            itow: p.u32(0),
+            year: p.u16(4),
+            month: p.0[6],
+            day: p.0[7],
+            hour: p.0[8],
+            minute: p.0[9],
+            second: p.0[10],
+            fix_type: p.0[20],
+            fix_ok: p.0[21] & 0x01 != 0,
+            satellites: p.0[23],
+            longitude: p.i32(24) as f64 * 1e-7,
+            latitude: p.i32(28) as f64 * 1e-7,
+            height: p.i32(32) as f64 / 1000.0,
+            height_msl: p.i32(36) as f64 / 1000.0,
+            horizontal_accuracy: p.u32(40) as f64 / 1000.0,
+            vertical_accuracy: p.u32(44) as f64 / 1000.0,
+            velocity_north: p.i32(48) as f64 / 1000.0,
+            velocity_east: p.i32(52) as f64 / 1000.0,
+            velocity_down: p.i32(56) as f64 / 1000.0,
+            ground_speed: p.i32(60) as f64 / 1000.0,
+            track: p.i32(64) as f64 * 1e-5,
+            pdop: p.u16(76) as f64 * 0.01,
+        }),
+        (NAV, NAV_DOP) if p.0.len() >= 18 => UbxMessage::NavDop(NavDop {
+            itow: p.u32(0),
+            gdop: p.u16(4) as f64 * 0.01,
+            pdop: p.u16(6) as f64 * 0.01,
+            tdop: p.u16(8) as f64 * 0.01,
+            vdop: p.u16(10) as f64 * 0.01,
+            hdop: p.u16(12) as f64 * 0.01,
+        }),
+        _ => UbxMessage::Unknown {
+            class,
+            id,
+        },
+    };
+
+    Ok(message)
+}
+
+// little endian payload fields
+struct Payload<'a>(&'a [u8]);
+
+impl<'a> Payload<'a> {
+    fn u16(&self, offset: usize) -> u16 {
+        u16::from_le_bytes([self.0[offset], self.0[offset + 1]])
+    }
+
+    fn u32(&self, offset: usize) -> u32 {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&self.0[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn i32(&self, offset: usize) -> i32 {
+        self.u32(offset) as i32
+    }
+}
@@ -1,2 +1,10 @@
 pub mod adsb;
+pub mod ahrs;
+pub mod baro;
+pub mod config;
+pub mod fisb;
+pub mod gdl90;
+pub mod geo;
+pub mod gps;
+pub mod metrics;
 pub mod sdr;
@@ -0,0 +1,447 @@
+/* Counters across the receive pipeline, from samples to outputs.
+ *
+ * Every stage bumps the process wide METRICS as it goes, they are plain
+ * atomics so the decoders don't have to know who is looking. Aircraft
+ * tracked and max range are taken from the tracker when asked for.
+ *
+ * They can be scraped by Prometheus from `http://<host>:<port>/metrics`
+ * and are summarized in the log every so often.
+ */
+
+use lazy_static::lazy_static;
+use log::*;
+use std::fmt::Write;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::adsb::SharedTracker;
+use crate::geo::METERS_PER_NM;
+use crate::sdr::repair::RepairResult;
+use crate::sdr::uat::FrameKind;
+
+/* Between log summaries unless configured */
+pub const METRICS_SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+
+/* Largest HTTP request header we bother reading */
+const METRICS_MAX_REQUEST: usize = 4096;
+
+const UAT_KINDS: [&str; 3] = ["basic_adsb", "long_adsb", "uplink"];
+
+const REPAIR_RESULTS: [&str; 5] = [
+    "skipped",
+    "corrected_1bit",
+    "corrected_2bit",
+    "refused",
+    "failed",
+];
+
+lazy_static! {
+    pub static ref METRICS: Metrics = Metrics::default();
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// 1090 MHz samples run through the demodulator
+    pub mode_s_samples: AtomicU64,
+    /// Sample windows correlating with a Mode S preamble
+    pub mode_s_preambles: AtomicU64,
+    /// Mode S frames out of the demodulator, valid or not
+    pub mode_s_demodulated: AtomicU64,
+    /// Mode S frames passing CRC as demodulated
+    pub mode_s_crc_ok: AtomicU64,
+    pub mode_s_corrected_1bit: AtomicU64,
+    pub mode_s_corrected_2bit: AtomicU64,
+    mode_s_rejected: [AtomicU64; 32],
+    mode_s_messages: [AtomicU64; 32],
+    mode_s_repairs: [[AtomicU64; 5]; 32],
+
+    /// 978 MHz samples run through the demodulator
+    pub uat_samples: AtomicU64,
+    /// UAT sync words found
+    pub uat_syncs: AtomicU64,
+    /// UAT frames with bytes fixed by Reed-Solomon
+    pub uat_corrected: AtomicU64,
+    uat_messages: [AtomicU64; 3],
+
+    /// FIS-B products stored in the weather cache, and how many it holds
+    pub fisb_products: AtomicU64,
+    pub fisb_cached: AtomicU64,
+
+    /// Times the sample ring buffer of a radio was full, and what was lost
+    pub radio_overflows: AtomicU64,
+    pub radio_dropped_samples: AtomicU64,
+
+    /// GDL90 targets being sent to, and the messages sent
+    pub gdl90_clients: AtomicU64,
+    pub gdl90_messages: AtomicU64,
+}
+
+/// Client of an output, counted until dropped
+pub struct Client<'a> {
+    gauge: &'a AtomicU64,
+}
+
+impl Drop for Client<'_> {
+    fn drop(&mut self) {
+        self.gauge.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn get(counter: &AtomicU64) -> u64 {
+    counter.load(Ordering::Relaxed)
+}
+
+impl Metrics {
+    pub fn add(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn inc(counter: &AtomicU64) {
+        Metrics::add(counter, 1);
+    }
+
+    pub fn set(gauge: &AtomicU64, n: u64) {
+        gauge.store(n, Ordering::Relaxed);
+    }
+
+    /// A Mode S frame dropped by address filtering and repair
+    pub fn mode_s_rejected(&self, df: u8) {
+        Metrics::inc(&self.mode_s_rejected[df as usize & 31]);
+    }
+
+    /// A Mode S frame handed to the tracker
+    pub fn mode_s_message(&self, df: u8) {
+        Metrics::inc(&self.mode_s_messages[df as usize & 31]);
+    }
+
+    /// An invalid Mode S frame handed to error correction, and what became of it
+    pub fn mode_s_repair(&self, df: u8, result: RepairResult) {
+        Metrics::inc(&self.mode_s_repairs[df as usize & 31][result as usize]);
+    }
+
+    /// Mode S frames of a downlink format that error correction ended with `result`
+    pub fn mode_s_repairs(&self, df: u8, result: RepairResult) -> u64 {
+        get(&self.mode_s_repairs[df as usize & 31][result as usize])
+    }
+
+    /// A UAT frame decoded
+    pub fn uat_message(&self, kind: FrameKind) {
+        let i = match kind {
+            FrameKind::BasicAdsb => 0,
+            FrameKind::LongAdsb => 1,
+            FrameKind::Uplink => 2,
+        };
+        Metrics::inc(&self.uat_messages[i]);
+    }
+
+    /// Count a GDL90 client for as long as the guard lives
+    pub fn gdl90_client(&self) -> Client<'_> {
+        Metrics::inc(&self.gdl90_clients);
+        Client {
+            gauge: &self.gdl90_clients,
+        }
+    }
+
+    /// Frames handed on by all inputs
+    pub fn messages(&self) -> u64 {
+        self.mode_s_messages
+            .iter()
+            .chain(self.uat_messages.iter())
+            .map(get)
+            .sum()
+    }
+
+    /// Mode S frames dropped
+    pub fn rejected(&self) -> u64 {
+        self.mode_s_rejected.iter().map(get).sum()
+    }
+
+    /// Prometheus text exposition, with the aircraft and range of `tracker`
+    pub fn prometheus(&self, tracker: &SharedTracker) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, values: Vec<(String, u64)>| {
+            let _ = writeln!(out, "# HELP fishfinder_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE fishfinder_{} {}", name, kind);
+            for (labels, value) in values {
+                let _ = writeln!(out, "fishfinder_{}{} {}", name, labels, value);
+            }
+        };
+        let counter = |c: &AtomicU64| vec![(String::new(), get(c))];
+        let per_df = |counters: &[AtomicU64; 32]| {
+            counters
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| get(c) > 0)
+                .map(|(df, c)| (format!("{{df=\"{}\"}}", df), get(c)))
+                .collect()
+        };
+
+        metric(
+            "samples_total",
+            "counter",
+            "IQ samples demodulated",
+            vec![
+                ("{band=\"1090\"}".to_string(), get(&self.mode_s_samples)),
+                ("{band=\"978\"}".to_string(), get(&self.uat_samples)),
+            ],
+        );
+        metric(
+            "mode_s_preambles_total",
+            "counter",
+            "Sample windows correlating with a Mode S preamble",
+            counter(&self.mode_s_preambles),
+        );
+        metric(
+            "mode_s_demodulated_total",
+            "counter",
+            "Mode S frames demodulated, valid or not",
+            counter(&self.mode_s_demodulated),
+        );
+        metric(
+            "mode_s_crc_ok_total",
+            "counter",
+            "Mode S frames passing CRC as demodulated",
+            counter(&self.mode_s_crc_ok),
+        );
+        metric(
+            "mode_s_corrected_total",
+            "counter",
+            "Mode S frames repaired by error correction",
+            vec![
+                ("{bits=\"1\"}".to_string(), get(&self.mode_s_corrected_1bit)),
+                ("{bits=\"2\"}".to_string(), get(&self.mode_s_corrected_2bit)),
+            ],
+        );
+        metric(
+            "mode_s_repairs_total",
+            "counter",
+            "Invalid Mode S frames handed to error correction by result",
+            self.mode_s_repairs
+                .iter()
+                .enumerate()
+                .flat_map(|(df, results)| {
+                    REPAIR_RESULTS
+                        .iter()
+                        .zip(results.iter())
+                        .filter(|(_, c)| get(c) > 0)
+                        .map(move |(result, c)| {
+                            (format!("{{df=\"{}\",result=\"{}\"}}", df, result), get(c))
+                        })
+                })
+                .collect(),
+        );
+        metric(
+            "mode_s_rejected_total",
+            "counter",
+            "Mode S frames rejected by address filtering and error correction",
+            per_df(&self.mode_s_rejected),
+        );
+        metric(
+            "mode_s_messages_total",
+            "counter",
+            "Mode S frames passed to the tracker",
+            per_df(&self.mode_s_messages),
+        );
+        metric(
+            "uat_syncs_total",
+            "counter",
+            "UAT sync words found",
+            counter(&self.uat_syncs),
+        );
+        metric(
+            "uat_corrected_total",
+            "counter",
+            "UAT frames with bytes fixed by Reed-Solomon",
+            counter(&self.uat_corrected),
+        );
+        metric(
+            "uat_messages_total",
+            "counter",
+            "UAT frames decoded",
+            UAT_KINDS
+                .iter()
+                .zip(self.uat_messages.iter())
+                .map(|(kind, c)| (format!("{{kind=\"{}\"}}", kind), get(c)))
+                .collect(),
+        );
+        metric(
+            "fisb_products_total",
+            "counter",
+            "FIS-B weather products received",
+            counter(&self.fisb_products),
+        );
+        metric(
+            "fisb_cached_products",
+            "gauge",
+            "FIS-B weather products currently cached",
+            counter(&self.fisb_cached),
+        );
+        metric(
+            "radio_overflows_total",
+            "counter",
+            "Times the sample buffer of a radio was full",
+            counter(&self.radio_overflows),
+        );
+        metric(
+            "radio_dropped_samples_total",
+            "counter",
+            "IQ samples lost to full radio buffers",
+            counter(&self.radio_dropped_samples),
+        );
+        metric(
+            "output_clients",
+            "gauge",
+            "Clients of each output",
+            vec![("{output=\"gdl90\"}".to_string(), get(&self.gdl90_clients))],
+        );
+        metric(
+            "output_messages_total",
+            "counter",
+            "Messages sent by each output",
+            vec![("{output=\"gdl90\"}".to_string(), get(&self.gdl90_messages))],
+        );
+
+        let snapshot = tracker.snapshot();
+        metric(
+            "aircraft",
+            "gauge",
+            "Aircraft tracked",
+            vec![(String::new(), snapshot.len() as u64)],
+        );
+        metric(
+            "aircraft_with_position",
+            "gauge",
+            "Aircraft tracked with a known position",
+            vec![(
+                String::new(),
+                snapshot.iter().filter(|ac| ac.position().is_some()).count() as u64,
+            )],
+        );
+        metric(
+            "max_range_meters",
+            "gauge",
+            "Furthest position received from the receiver location",
+            vec![(String::new(), max_range(tracker).round() as u64)],
+        );
+
+        out
+    }
+}
+
+/* Furthest of all coverage sectors in meters, 0 without a receiver location */
+fn max_range(tracker: &SharedTracker) -> f64 {
+    tracker
+        .coverage()
+        .sectors()
+        .map(|(_, range)| range)
+        .fold(0.0, f64::max)
+}
+
+/// Totals at one point in time, for rates between log summaries
+#[derive(Debug, Clone, Copy)]
+pub struct Summary {
+    at: Instant,
+    samples: u64,
+    messages: u64,
+    crc_ok: u64,
+    corrected: u64,
+    rejected: u64,
+    overflows: u64,
+}
+
+impl Summary {
+    pub fn take() -> Summary {
+        let m = &*METRICS;
+        Summary {
+            at: Instant::now(),
+            samples: get(&m.mode_s_samples) + get(&m.uat_samples),
+            messages: m.messages(),
+            crc_ok: get(&m.mode_s_crc_ok),
+            corrected: get(&m.mode_s_corrected_1bit) + get(&m.mode_s_corrected_2bit),
+            rejected: m.rejected(),
+            overflows: get(&m.radio_overflows),
+        }
+    }
+
+    /// One line of what happened since `previous`
+    pub fn since(&self, previous: &Summary, tracker: &SharedTracker) -> String {
+        let seconds = self.at.duration_since(previous.at).as_secs_f64().max(1e-3);
+        let rate = |now: u64, before: u64| now.saturating_sub(before) as f64 / seconds;
+
+        format!(
+            "{:.0} samples/s, {:.1} msgs/s ({} total), crc ok {}, corrected {}, rejected {}, {} aircraft, max range {:.0}nm, {} overflows",
+            rate(self.samples, previous.samples),
+            rate(self.messages, previous.messages),
+            self.messages,
+            self.crc_ok,
+            self.corrected,
+            self.rejected,
+            tracker.snapshot().len(),
+            max_range(tracker) / METERS_PER_NM,
+            self.overflows,
+        )
+    }
+}
+
+/// Log a summary every `interval` for as long as the stack runs
+pub async fn log_summaries(tracker: SharedTracker, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.tick().await;
+    let mut previous = Summary::take();
+
+    loop {
+        ticks.tick().await;
+        let summary = Summary::take();
+        info!("stats: {}", summary.since(&previous, &tracker));
+        previous = summary;
+    }
+}
+
+/* Answer one request, anything but GET /metrics is a 404 */
+async fn respond(mut stream: TcpStream, tracker: &SharedTracker) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 512];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || request.len() > METRICS_MAX_REQUEST {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut line = request.lines().next().unwrap_or_default().split(' ');
+    let (status, body) = match (line.next(), line.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", METRICS.prometheus(tracker)),
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Serve `/metrics` over HTTP on `addr`
+pub async fn run(tracker: SharedTracker, addr: SocketAddr) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("metrics on http://{}/metrics", listener.local_addr()?);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let tracker = tracker.clone();
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, &tracker).await {
+                debug!("metrics: {}: {}", peer, e);
+            }
+        });
+    }
+}
@@ -1,80 +1,166 @@
+use lazy_static::lazy_static;
+use std::collections::hash_map::Entry::Vacant;
+use std::collections::HashMap;
+
 use super::mode_s;
 
-/* Parity table for MODE S Messages.
- * The table contains 112 elements, every element corresponds to a bit set
- * in the message, starting from the first bit of actual data after the
- * preamble.
+/* CRC-24 used by Mode S, generator polynomial
  *
- * For messages of 112 bit, the whole table is used.
- * For messages of 56 bits only the last 56 elements are used.
+ *   x^24 + x^23 + x^22 + x^21 + x^20 + x^19 + x^18 + x^17 +
+ *   x^16 + x^15 + x^14 + x^13 + x^12 + x^10 + x^3 + 1
  *
- * The algorithm is as simple as xoring all the elements in this table
- * for which the corresponding bit on the message is set to 1.
+ * The CRC is computed MSB first over all bits of the message except the
+ * last 24 which carry the parity. A message is valid when its parity
+ * equals the CRC of the rest of the message.
  *
- * The latest 24 elements in this table are set to 0 as the checksum at the
- * end of the message should not affect the computation.
+ * Note: this only holds for DF11 and DF17/18, other formats have the CRC
+ * xored with the sender address as they are reply to interrogations, but
+ * a casual listener can't split the address from the checksum.
+ */
+pub const MODES_GENERATOR_POLY: u32 = 0xfff409;
+
+/* Most bits error correction will ever flip in a single frame */
+pub const MODES_MAX_CORRECTABLE_BITS: usize = 2;
+
+/* The parity (PI/AP) field at the end of every frame */
+pub const MODES_PARITY_BITS: usize = 24;
+
+const MODES_CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 0x800000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ MODES_GENERATOR_POLY,
+            };
+            bit += 1;
+        }
+        table[i] = crc & 0xffffff;
+        i += 1;
+    }
+    table
+}
+
+/* Syndromes of every error pattern of up to MODES_MAX_CORRECTABLE_BITS
+ * flipped bits in the data of a frame, one table per message length. All
+ * of them are unique, so a syndrome maps back to exactly one pattern.
  *
- * Note: this function can be used with DF11 and DF17, other modes have
- * the CRC xored with the sender address as they are reply to interrogations,
- * but a casual listener can't split the address from the checksum.
-*/
-
-const MODES_CHECKSUM_TABLE: [u32; 112] = [
-    0x3935ea, 0x1c9af5, 0xf1b77e, 0x78dbbf, 0xc397db, 0x9e31e9, 0xb0e2f0, 0x587178, 0x2c38bc,
-    0x161c5e, 0x0b0e2f, 0xfa7d13, 0x82c48d, 0xbe9842, 0x5f4c21, 0xd05c14, 0x682e0a, 0x341705,
-    0xe5f186, 0x72f8c3, 0xc68665, 0x9cb936, 0x4e5c9b, 0xd8d449, 0x939020, 0x49c810, 0x24e408,
-    0x127204, 0x093902, 0x049c81, 0xfdb444, 0x7eda22, 0x3f6d11, 0xe04c8c, 0x702646, 0x381323,
-    0xe3f395, 0x8e03ce, 0x4701e7, 0xdc7af7, 0x91c77f, 0xb719bb, 0xa476d9, 0xadc168, 0x56e0b4,
-    0x2b705a, 0x15b82d, 0xf52612, 0x7a9309, 0xc2b380, 0x6159c0, 0x30ace0, 0x185670, 0x0c2b38,
-    0x06159c, 0x030ace, 0x018567, 0xff38b7, 0x80665f, 0xbfc92b, 0xa01e91, 0xaff54c, 0x57faa6,
-    0x2bfd53, 0xea04ad, 0x8af852, 0x457c29, 0xdd4410, 0x6ea208, 0x375104, 0x1ba882, 0x0dd441,
-    0xf91024, 0x7c8812, 0x3e4409, 0xe0d800, 0x706c00, 0x383600, 0x1c1b00, 0x0e0d80, 0x0706c0,
-    0x038360, 0x01c1b0, 0x00e0d8, 0x00706c, 0x003836, 0x001c1b, 0xfff409, 0x000000, 0x000000,
-    0x000000, 0x000000, 0x000000, 0x000000, 0x000000, 0x000000, 0x000000, 0x000000, 0x000000,
-    0x000000, 0x000000, 0x000000, 0x000000, 0x000000, 0x000000, 0x000000, 0x000000, 0x000000,
-    0x000000, 0x000000, 0x000000, 0x000000,
-];
-
-pub fn modes_checksum(frame: &Vec<u8>) -> u32 {
-    let mut crc: u32 = 0;
-    let offset: usize = match frame.len() == mode_s::MODES_LONG_MSG_BYTES {
-        true => 0,
-        false => (112 - 56),
-    };
+ * Errors in the parity field aren't in the tables. The data would be
+ * intact, but a syndrome that only points at the parity is as likely to
+ * be a bigger error elsewhere that happens to look like it. */
+lazy_static! {
+    static ref MODES_SHORT_SYNDROMES: HashMap<u32, Vec<usize>> =
+        syndrome_table(mode_s::MODES_SHORT_MSG_BITS);
+    static ref MODES_LONG_SYNDROMES: HashMap<u32, Vec<usize>> =
+        syndrome_table(mode_s::MODES_LONG_MSG_BITS);
+}
 
-    for j in 0..(frame.len() * 8) {
-        let byte = j / 8;
-        let bit = j % 8;
-        let bitmask = 1 << (7 - bit);
+fn syndrome_table(bits: usize) -> HashMap<u32, Vec<usize>> {
+    // the crc is linear, the syndrome of a pattern is the xor of its single bit syndromes
+    let mut frame = vec![0u8; bits / 8];
+    let bits = bits - MODES_PARITY_BITS;
+    let single: Vec<u32> = (0..bits)
+        .map(|j| {
+            flip_bit(&mut frame, j);
+            let syndrome = modes_syndrome(&frame);
+            flip_bit(&mut frame, j);
+            syndrome
+        })
+        .collect();
 
-        /* If bit is set, xor with corresponding table entry. */
-        if frame[byte] & bitmask != 0 {
-            crc ^= MODES_CHECKSUM_TABLE[j + offset];
+    let mut table = HashMap::new();
+    for (i, &syndrome) in single.iter().enumerate() {
+        table.insert(syndrome, vec![i]);
+    }
+    for (i, &a) in single.iter().enumerate() {
+        for (j, &b) in single.iter().enumerate().skip(i + 1) {
+            // keep the simpler explanation should two patterns ever collide
+            if let Vacant(entry) = table.entry(a ^ b) {
+                entry.insert(vec![i, j]);
+            }
         }
     }
-    return crc; /* 24 bit checksum. */
+
+    table
 }
 
-// bit repair of frame
-pub fn modes_repair_single_bit(frame: &Vec<u8>) -> Option<Vec<u8>> {
-    for j in 0..(frame.len() * 8) {
-        let byte = j / 8;
-        let bitmask = 1 << (7 - (j % 8));
+fn flip_bit(frame: &mut [u8], j: usize) {
+    frame[j / 8] ^= 1 << (7 - (j % 8));
+}
 
-        let mut new_frame = frame.to_vec();
-        new_frame[byte] ^= bitmask; /* Flip j-th bit. */
+/// Byte-wise CRC-24 of `data`
+pub fn modes_crc(data: &[u8]) -> u32 {
+    data.iter().fold(0, |crc, &byte| {
+        let index = ((crc >> 16) as u8 ^ byte) as usize;
+        ((crc << 8) ^ MODES_CRC_TABLE[index]) & 0xffffff
+    })
+}
 
-        // check crc24 checksum
-        let crc: u32 = ((new_frame[new_frame.len() - 3] as u32) << 16)
-            | ((new_frame[new_frame.len() - 2] as u32) << 8)
-            | (new_frame[new_frame.len() - 1] as u32);
-        let crc2: u32 = modes_checksum(&new_frame);
+/// Parity field (last 24 bits) of a frame
+pub fn modes_parity(frame: &[u8]) -> u32 {
+    ((frame[frame.len() - 3] as u32) << 16)
+        | ((frame[frame.len() - 2] as u32) << 8)
+        | (frame[frame.len() - 1] as u32)
+}
 
-        if crc == crc2 {
-            return Some(new_frame);
-        }
+/// Difference between computed CRC and parity, zero for a valid frame
+pub fn modes_syndrome(frame: &[u8]) -> u32 {
+    modes_crc(&frame[0..frame.len() - 3]) ^ modes_parity(frame)
+}
+
+/// Correct up to `max_bits` (at most MODES_MAX_CORRECTABLE_BITS) flipped bits in place.
+/// Returns the corrected bit positions, empty if the frame was already valid.
+pub fn modes_correct(frame: &mut [u8], max_bits: usize) -> Option<Vec<usize>> {
+    let syndrome = modes_syndrome(frame);
+    if syndrome == 0 {
+        return Some(vec![]);
     }
 
-    None
+    let table = match frame.len() * 8 {
+        mode_s::MODES_SHORT_MSG_BITS => &*MODES_SHORT_SYNDROMES,
+        mode_s::MODES_LONG_MSG_BITS => &*MODES_LONG_SYNDROMES,
+        _ => return None,
+    };
+
+    match table.get(&syndrome) {
+        Some(bits) if bits.len() <= max_bits => {
+            for &j in bits {
+                flip_bit(frame, j);
+            }
+            Some(bits.clone())
+        }
+        _ => None,
+    }
 }
 
+/// Like modes_correct, but allows one bit more than `max_bits` if it is one of
+/// `hints` (e.g. the bits the demodulator was least sure of). A pattern the
+/// syndrome alone can't be trusted with is accepted when it lands on a weak bit.
+/// Never more than MODES_MAX_CORRECTABLE_BITS in total.
+pub fn modes_correct_with_hints(
+    frame: &mut [u8],
+    hints: &[usize],
+    max_bits: usize,
+) -> Option<Vec<usize>> {
+    let max_bits = max_bits.min(MODES_MAX_CORRECTABLE_BITS);
+    if let Some(bits) = modes_correct(frame, max_bits) {
+        return Some(bits);
+    }
+    if max_bits == MODES_MAX_CORRECTABLE_BITS {
+        return None;
+    }
+
+    let mut attempt = frame.to_vec();
+    match modes_correct(&mut attempt, max_bits + 1) {
+        Some(bits) if bits.iter().any(|j| hints.contains(j)) => {
+            frame.copy_from_slice(&attempt);
+            Some(bits)
+        }
+        _ => None,
+    }
+}
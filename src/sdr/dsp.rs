@@ -12,10 +12,9 @@ pub struct IQ {
 
 impl IQ {
     pub fn magnitude(&self) -> u8 {
-        let i: f32 = (self.i as i16 - 127 as i16).into();
-        let q: f32 = (self.q as i16 - 127 as i16).into();
-        let mag: u8 = (i * i + q * q).sqrt().round() as u8;
-        return mag;
+        let i: f32 = (self.i as i16 - 127).into();
+        let q: f32 = (self.q as i16 - 127).into();
+        (i * i + q * q).sqrt().round() as u8
     }
 }
 
@@ -24,10 +23,8 @@ pub struct IQMagnitudeReader<T: AsyncRead> {
 }
 
 impl<T: AsyncRead> IQMagnitudeReader<T> {
-    pin_utils::unsafe_pinned!(inner: T);
-
     pub fn new(inner: T) -> IQMagnitudeReader<T> {
-        IQMagnitudeReader { inner: inner }
+        IQMagnitudeReader { inner }
     }
 }
 
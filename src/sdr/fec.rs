@@ -0,0 +1,220 @@
+/* Reed-Solomon codes protecting UAT frames, over GF(256) with the field
+ * generator polynomial
+ *
+ *   x^8 + x^7 + x^2 + x + 1
+ *
+ * and the generator polynomial roots starting at alpha^120. All UAT codes
+ * are shortened from RS(255, 255 - parity), the missing leading data bytes
+ * are zero, so they don't change the parity and are never sent.
+ *
+ *   basic ADS-B  RS(30, 18)   6 byte errors
+ *   long ADS-B   RS(48, 34)   7 byte errors
+ *   uplink       RS(92, 72)  10 byte errors per block
+ *
+ * Decoding is the usual Berlekamp-Massey for the error locator, a Chien
+ * search for the error positions and Forney for the error values.
+ */
+pub const UAT_RS_FIELD_POLY: u16 = 0x187;
+pub const UAT_RS_FIRST_ROOT: usize = 120;
+
+const GF_ORDER: usize = 255;
+const GF_EXP: [u8; GF_ORDER] = gf_tables().0;
+const GF_LOG: [u8; GF_ORDER + 1] = gf_tables().1;
+
+const fn gf_tables() -> ([u8; GF_ORDER], [u8; GF_ORDER + 1]) {
+    let mut exp = [0u8; GF_ORDER];
+    let mut log = [0u8; GF_ORDER + 1];
+    let mut x: u16 = 1;
+    let mut i = 0;
+    while i < GF_ORDER {
+        exp[i] = x as u8;
+        log[x as usize] = i as u8;
+        x <<= 1;
+        if x & 0x100 != 0 {
+            x ^= UAT_RS_FIELD_POLY;
+        }
+        i += 1;
+    }
+    (exp, log)
+}
+
+fn alpha(power: usize) -> u8 {
+    GF_EXP[power % GF_ORDER]
+}
+
+fn mul(a: u8, b: u8) -> u8 {
+    match (a, b) {
+        (0, _) | (_, 0) => 0,
+        _ => alpha(GF_LOG[a as usize] as usize + GF_LOG[b as usize] as usize),
+    }
+}
+
+fn div(a: u8, b: u8) -> u8 {
+    match a {
+        0 => 0,
+        _ => alpha(GF_LOG[a as usize] as usize + GF_ORDER - GF_LOG[b as usize] as usize),
+    }
+}
+
+/* Polynomials are coefficient vectors, lowest degree first */
+fn eval(poly: &[u8], x: u8) -> u8 {
+    poly.iter().rev().fold(0, |acc, &c| mul(acc, x) ^ c)
+}
+
+pub struct ReedSolomon {
+    parity: usize,
+    // generator polynomial, highest degree first without the leading 1
+    generator: Vec<u8>,
+}
+
+impl ReedSolomon {
+    pub fn new(parity: usize) -> ReedSolomon {
+        let mut generator = vec![1u8];
+        for j in 0..parity {
+            // multiply by (x + alpha^(first root + j))
+            let root = alpha(UAT_RS_FIRST_ROOT + j);
+            let mut next = vec![0u8; generator.len() + 1];
+            for (i, &c) in generator.iter().enumerate() {
+                next[i + 1] ^= c;
+                next[i] ^= mul(c, root);
+            }
+            generator = next;
+        }
+        generator.pop();
+        generator.reverse();
+
+        ReedSolomon {
+            parity,
+            generator,
+        }
+    }
+
+    /// Parity bytes
+    pub fn parity(&self) -> usize {
+        self.parity
+    }
+
+    /// Parity to send after `data`
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let mut parity = vec![0u8; self.parity];
+        for &b in data {
+            let feedback = b ^ parity[0];
+            parity.rotate_left(1);
+            parity[self.parity - 1] = 0;
+            for (p, &g) in parity.iter_mut().zip(self.generator.iter()) {
+                *p ^= mul(feedback, g);
+            }
+        }
+        parity
+    }
+
+    /// Correct a received codeword (data followed by parity) in place,
+    /// returns the number of corrected bytes or None if there were too many
+    pub fn decode(&self, codeword: &mut [u8]) -> Option<usize> {
+        let n = codeword.len();
+        if n <= self.parity || n > GF_ORDER {
+            return None;
+        }
+
+        let syndromes: Vec<u8> = (0..self.parity)
+            .map(|j| {
+                let root = alpha(UAT_RS_FIRST_ROOT + j);
+                codeword.iter().fold(0, |acc, &c| mul(acc, root) ^ c)
+            })
+            .collect();
+        if syndromes.iter().all(|&s| s == 0) {
+            return Some(0);
+        }
+
+        let locator = ReedSolomon::berlekamp_massey(&syndromes);
+        let errors = locator.len() - 1;
+        if errors > self.parity / 2 {
+            return None;
+        }
+
+        // Chien search, byte i is the coefficient of x^(n - 1 - i)
+        let positions: Vec<usize> = (0..n)
+            .filter(|&i| {
+                let degree = n - 1 - i;
+                eval(&locator, alpha(GF_ORDER - degree % GF_ORDER)) == 0
+            })
+            .collect();
+        // roots in the shortened (zero) part of the code mean a miscorrection
+        if positions.len() != errors {
+            return None;
+        }
+
+        // error evaluator, S(x) * locator(x) mod x^parity
+        let mut evaluator = vec![0u8; self.parity];
+        for (i, &s) in syndromes.iter().enumerate() {
+            for (j, &l) in locator.iter().enumerate() {
+                if i + j < self.parity {
+                    evaluator[i + j] ^= mul(s, l);
+                }
+            }
+        }
+        // formal derivative, only the odd powers survive in GF(2^m)
+        let derivative: Vec<u8> = locator
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, &l)| if i % 2 == 1 { l } else { 0 })
+            .collect();
+
+        for &i in &positions {
+            let degree = (n - 1 - i) % GF_ORDER;
+            let x_inv = alpha(GF_ORDER - degree);
+            let denominator = eval(&derivative, x_inv);
+            if denominator == 0 {
+                return None;
+            }
+            // X^(1 - first root) * evaluator(X^-1) / locator'(X^-1)
+            let scale = alpha(degree * (GF_ORDER + 1 - UAT_RS_FIRST_ROOT % GF_ORDER));
+            codeword[i] ^= mul(scale, div(eval(&evaluator, x_inv), denominator));
+        }
+
+        Some(errors)
+    }
+
+    /* Shortest LFSR generating the syndromes, its connection polynomial is
+     * the error locator with roots at the inverse error positions. */
+    fn berlekamp_massey(syndromes: &[u8]) -> Vec<u8> {
+        let mut locator = vec![1u8];
+        let mut previous = vec![1u8];
+        let mut length = 0;
+        let mut shift = 1;
+        let mut last_discrepancy = 1u8;
+
+        for k in 0..syndromes.len() {
+            let discrepancy = (1..=length.min(locator.len() - 1))
+                .fold(syndromes[k], |d, i| d ^ mul(locator[i], syndromes[k - i]));
+            if discrepancy == 0 {
+                shift += 1;
+                continue;
+            }
+
+            let scale = div(discrepancy, last_discrepancy);
+            let mut next = locator.clone();
+            if next.len() < previous.len() + shift {
+                next.resize(previous.len() + shift, 0);
+            }
+            for (i, &b) in previous.iter().enumerate() {
+                next[i + shift] ^= mul(scale, b);
+            }
+
+            if 2 * length <= k {
+                length = k + 1 - length;
+                previous = locator;
+                last_discrepancy = discrepancy;
+                shift = 1;
+            } else {
+                shift += 1;
+            }
+            locator = next;
+        }
+
+        locator.truncate(length + 1);
+        locator.resize(length + 1, 0);
+        locator
+    }
+}
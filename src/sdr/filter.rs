@@ -0,0 +1,111 @@
+use log::*;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use super::crc;
+use super::mode_s::Frame;
+
+/* How long an address seen in a DF11/17 stays known. Aircraft squitter
+ * every second or so, a minute covers a few missed ones without keeping
+ * departed aircraft around. */
+pub const ICAO_FILTER_TTL: Duration = Duration::from_secs(60);
+
+/* Control field of a DF18 sent by an ADS-B device with an ICAO address */
+const DF18_CF_ADSB_ICAO: u8 = 0;
+
+/* All-call replies (DF11) to an interrogator with a non-zero II or SI code
+ * have that code xored into the low bits of their parity. A syndrome below
+ * this is such a code, not an error. */
+const DF11_MAX_IC_SYNDROME: u32 = 0x80;
+
+/// Addresses recently seen in frames with a verified CRC (DF11/17/18).
+///
+/// Address/parity replies (DF0/4/5/16/20/21) can't be checked on their own,
+/// every corrupted one still yields "an" address. Only accepting those that
+/// resolve to an aircraft we recently heard from weeds out the garbage.
+pub struct IcaoFilter {
+    seen: HashMap<u32, SystemTime>,
+    ttl: Duration,
+    last_expire: SystemTime,
+}
+
+impl IcaoFilter {
+    pub fn new(ttl: Duration) -> IcaoFilter {
+        IcaoFilter {
+            seen: HashMap::new(),
+            ttl,
+            last_expire: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    pub fn insert(&mut self, icao: u32, at: SystemTime) {
+        self.seen.insert(icao, at);
+
+        if self.age(self.last_expire, at) > self.ttl {
+            self.expire(at);
+        }
+    }
+
+    pub fn contains(&self, icao: u32, at: SystemTime) -> bool {
+        match self.seen.get(&icao) {
+            Some(&seen) => self.age(seen, at) <= self.ttl,
+            None => false,
+        }
+    }
+
+    /// Forget addresses not seen within the ttl
+    pub fn expire(&mut self, at: SystemTime) {
+        let ttl = self.ttl;
+        let before = self.seen.len();
+        self.seen
+            .retain(|_, &mut seen| at.duration_since(seen).unwrap_or_default() <= ttl);
+        self.last_expire = at;
+        trace!("expired {} icao addresses", before - self.seen.len());
+    }
+
+    pub fn len(&self) -> usize {
+        self.seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Records the address of valid DF11/17 frames and accepts them, accepts
+    /// valid DF18 and address/parity frames only if their address is known.
+    /// Everything else is rejected.
+    ///
+    /// DF11 counts as valid when its syndrome is an interrogator code, see
+    /// `DF11_MAX_IC_SYNDROME`.
+    ///
+    /// DF18 is accepted too, but only an ADS-B device with an ICAO address
+    /// (CF 0) is remembered. TIS-B, ADS-R and anonymous addresses aren't
+    /// transponders that could answer an interrogation.
+    pub fn accept(&mut self, frame: &Frame) -> bool {
+        match frame.downlink_format() {
+            11 if crc::modes_syndrome(frame.bytes()) < DF11_MAX_IC_SYNDROME => {
+                self.insert(frame.icao().unwrap(), frame.received_at());
+                true
+            }
+            17 if frame.valid() => {
+                self.insert(frame.icao().unwrap(), frame.received_at());
+                true
+            }
+            18 if frame.valid() => {
+                if frame.bytes()[0] & 0x7 == DF18_CF_ADSB_ICAO {
+                    self.insert(frame.icao().unwrap(), frame.received_at());
+                }
+                true
+            }
+            0 | 4 | 5 | 16 | 20 | 21 => match frame.icao_from_parity() {
+                Some(icao) => self.contains(icao, frame.received_at()),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn age(&self, seen: SystemTime, at: SystemTime) -> Duration {
+        at.duration_since(seen).unwrap_or_default()
+    }
+}
@@ -0,0 +1,233 @@
+/* Inputs of the tracker: radios and recordings on either band.
+ *
+ * A typical install has one dongle on 1090 MHz and one on 978, every input
+ * runs its own decoder and they all feed the same tracker, which tags each
+ * aircraft with the links it was heard on.
+ */
+
+use log::*;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::str::FromStr;
+use tokio::io::AsyncRead;
+use tokio::sync::broadcast;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::codec::FramedRead;
+
+use super::rtl::{RadioConfig, RTL_SDR_BUFFER_SIZE};
+use super::{dsp, filter, mode_s, repair, uat};
+use crate::adsb::SharedTracker;
+use crate::metrics::METRICS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Band {
+    /// 1090 MHz Mode S and extended squitter
+    ModeS,
+    /// 978 MHz UAT
+    Uat,
+}
+
+impl Band {
+    pub fn radio(&self, device_index: u8) -> RadioConfig {
+        match self {
+            Band::ModeS => RadioConfig::mode_s(device_index),
+            Band::Uat => RadioConfig::uat(device_index),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.radio(0).sample_rate()
+    }
+}
+
+impl FromStr for Band {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1090" | "modes" | "mode-s" => Ok(Band::ModeS),
+            "978" | "uat" => Ok(Band::Uat),
+            _ => Err(format!("unknown band '{}' (expected 1090 or 978)", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Band {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Band::ModeS => write!(f, "1090"),
+            Band::Uat => write!(f, "978"),
+        }
+    }
+}
+
+/// A dongle to receive a band with, `<band>[:<serial>[:<ppm>]]`, e.g.
+/// `978:00000978:-2`, or a `[[radio]]` table of the config file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RadioSpec {
+    pub band: Band,
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub ppm: i32,
+}
+
+impl RadioSpec {
+    /// The first dongle without a serial number, no frequency correction
+    pub fn new(band: Band) -> RadioSpec {
+        RadioSpec {
+            band,
+            serial: None,
+            ppm: 0,
+        }
+    }
+
+    /// Configuration of the radio, `device_index` is used without a serial number
+    pub fn config(&self, device_index: u8) -> io::Result<RadioConfig> {
+        let config = self.band.radio(device_index).ppm(self.ppm);
+        match &self.serial {
+            Some(serial) => config.serial(serial),
+            None => Ok(config),
+        }
+    }
+}
+
+impl FromStr for RadioSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let band = parts.next().unwrap_or_default().parse()?;
+        let serial = parts
+            .next()
+            .filter(|serial| !serial.is_empty())
+            .map(|serial| serial.to_string());
+        let ppm = match parts.next() {
+            Some(ppm) => ppm
+                .parse()
+                .map_err(|_| format!("invalid ppm correction '{}'", ppm))?,
+            None => 0,
+        };
+
+        Ok(RadioSpec {
+            band,
+            serial,
+            ppm,
+        })
+    }
+}
+
+/// Mode S frames from 1090 MHz IQ samples that passed CRC, address
+/// filtering or error correction
+pub fn mode_s_frames<T: 'static + AsyncRead + Send>(
+    iq_sample_src: T,
+    repair_policy: repair::RepairPolicy,
+) -> Pin<Box<dyn Stream<Item = mode_s::Frame> + Send>> {
+    let magnitude_src = dsp::IQMagnitudeReader::new(iq_sample_src);
+
+    let mode_s_frame_stream = FramedRead::with_capacity(
+        magnitude_src,
+        mode_s::FrameDecoder::new(),
+        RTL_SDR_BUFFER_SIZE,
+    );
+
+    let mut icao_filter = filter::IcaoFilter::new(filter::ICAO_FILTER_TTL);
+    let mut repairer = repair::Repairer::new(repair_policy);
+
+    let valid_frame_stream = mode_s_frame_stream
+        .filter_map(|f| f.ok())
+        .filter_map(move |frame| {
+            let df = frame.downlink_format();
+            let accepted = match icao_filter.accept(&frame) {
+                true => Some(frame),
+                false => match frame.icao_from_parity() {
+                    // address/parity frames can't be repaired, the syndrome is the address
+                    Some(_) => None,
+                    None => repairer.repair(&frame, &icao_filter),
+                },
+            };
+            match accepted {
+                Some(_) => METRICS.mode_s_message(df),
+                None => METRICS.mode_s_rejected(df),
+            }
+            accepted
+        });
+
+    Box::pin(valid_frame_stream)
+}
+
+/// Decode 1090 MHz IQ samples into the tracker until the source ends,
+/// returns the number of frames
+pub async fn run_mode_s<T: 'static + AsyncRead + Send>(
+    iq_sample_src: T,
+    tracker: SharedTracker,
+    repair_policy: repair::RepairPolicy,
+) -> u64 {
+    let mut stream = mode_s_frames(iq_sample_src, repair_policy);
+    let mut frame_count = 0;
+
+    while let Some(frame) = stream.next().await {
+        if let Some(message) = frame.parse() {
+            debug!("got frame: {:#?}", message);
+        }
+        tracker.process(&frame);
+        frame_count += 1;
+    }
+
+    frame_count
+}
+
+/// Decode 978 MHz IQ samples into the tracker until the source ends, FIS-B
+/// uplinks go to `uplinks`. Returns the number of frames.
+pub async fn run_uat<T: AsyncRead + Unpin>(
+    iq_sample_src: T,
+    tracker: SharedTracker,
+    uplinks: broadcast::Sender<uat::Frame>,
+) -> u64 {
+    let mut stream =
+        FramedRead::with_capacity(iq_sample_src, uat::FrameDecoder::new(), RTL_SDR_BUFFER_SIZE)
+            .filter_map(|f| f.ok());
+    let mut frame_count = 0;
+
+    while let Some(frame) = stream.next().await {
+        debug!("got uat frame: {}", frame);
+        if frame.is_adsb() {
+            tracker.process_uat(&frame);
+        } else {
+            // nobody listening is fine
+            let _ = uplinks.send(frame);
+        }
+        frame_count += 1;
+    }
+
+    frame_count
+}
+
+/// Decode a band into the tracker until the source ends
+pub async fn run<T: 'static + AsyncRead + Send + Unpin>(
+    iq_sample_src: T,
+    band: Band,
+    tracker: SharedTracker,
+    repair_policy: repair::RepairPolicy,
+    uplinks: broadcast::Sender<uat::Frame>,
+) -> u64 {
+    let frame_count = match band {
+        Band::ModeS => run_mode_s(iq_sample_src, tracker, repair_policy).await,
+        Band::Uat => run_uat(iq_sample_src, tracker, uplinks).await,
+    };
+    info!("{} MHz input ended after {} frames", band, frame_count);
+
+    frame_count
+}
@@ -0,0 +1,89 @@
+/* Frames as lines of text for other tools:
+ *
+ *   avr   Mode S as `*<hex>;` like dump1090's raw output, UAT as dump978
+ *         does it, `-<hex>;rs=<n>;` for ADS-B and `+<hex>;rs=<n>;` for
+ *         uplinks (which `fisb::parse_hex` reads back)
+ *   json  one object per frame with the address, signal level, receive
+ *         time and the MLAT clock of Mode S frames
+ */
+
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{mode_s, uat};
+use crate::adsb::uat as uat_payload;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineFormat {
+    Avr,
+    Json,
+}
+
+impl FromStr for LineFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "avr" => Ok(LineFormat::Avr),
+            "json" => Ok(LineFormat::Json),
+            _ => Err(format!("unknown format '{}' (expected avr or json)", s)),
+        }
+    }
+}
+
+fn unix_time(at: SystemTime) -> f64 {
+    at.duration_since(UNIX_EPOCH)
+        .map_or(0.0, |since| since.as_secs_f64())
+}
+
+fn json_address(address: Option<u32>) -> String {
+    match address {
+        Some(address) => format!("\"{:06x}\"", address),
+        None => "null".to_string(),
+    }
+}
+
+pub fn mode_s(frame: &mode_s::Frame, format: LineFormat) -> String {
+    let hex = hex::encode(frame.bytes());
+    match format {
+        LineFormat::Avr => format!("*{};", hex),
+        LineFormat::Json => format!(
+            "{{\"type\":\"mode_s\",\"df\":{},\"address\":{},\"hex\":\"{}\",\"signal\":{:.1},\"corrected\":{},\"mlat\":{},\"time\":{:.6}}}",
+            frame.downlink_format(),
+            json_address(frame.icao()),
+            hex,
+            frame.signal_level(),
+            frame.corrected_bits(),
+            frame.mlat_timestamp(),
+            unix_time(frame.received_at()),
+        ),
+    }
+}
+
+pub fn uat(frame: &uat::Frame, format: LineFormat) -> String {
+    let hex = hex::encode(frame.bytes());
+    match format {
+        LineFormat::Avr => match frame.is_adsb() {
+            true => format!("-{};rs={};", hex, frame.corrected()),
+            false => format!("+{};rs={};", hex, frame.corrected()),
+        },
+        LineFormat::Json => {
+            let (kind, address) = match frame.is_adsb() {
+                true => (
+                    "uat_adsb",
+                    uat_payload::decode(frame.bytes()).map(|p| p.address),
+                ),
+                false => ("uat_uplink", None),
+            };
+            format!(
+                "{{\"type\":\"{}\",\"address\":{},\"hex\":\"{}\",\"signal\":{:.1},\"corrected\":{},\"time\":{:.6}}}",
+                kind,
+                json_address(address),
+                hex,
+                frame.signal_level(),
+                frame.corrected(),
+                unix_time(frame.received_at()),
+            )
+        }
+    }
+}
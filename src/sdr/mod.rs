@@ -1,5 +1,12 @@
 pub mod crc;
 pub mod dsp;
+pub mod fec;
+pub mod filter;
+pub mod input;
+pub mod lines;
 pub mod mode_s;
+pub mod playback;
+pub mod repair;
 pub mod rtl;
-
+pub mod stats;
+pub mod uat;
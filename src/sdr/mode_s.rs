@@ -1,8 +1,11 @@
 use bytes::{Buf, BytesMut};
 use log::*;
+use std::time::SystemTime;
 use tokio_util::codec;
 
 use super::crc;
+use crate::adsb::es::{self, EsMessage};
+use crate::metrics::{Metrics, METRICS};
 
 pub const MODES_PREAMBLE_BITS: usize = 8;
 pub const MODES_SHORT_MSG_BITS: usize = 56;
@@ -10,98 +13,180 @@ pub const MODES_LONG_MSG_BITS: usize = 112;
 pub const MODES_SHORT_MSG_BYTES: usize = MODES_SHORT_MSG_BITS / 8;
 pub const MODES_LONG_MSG_BYTES: usize = MODES_LONG_MSG_BITS / 8;
 
+/* Samples are 2 MS/s magnitudes, timestamps are handed out on the 12 MHz
+ * clock used by Beast output and MLAT servers. */
+pub const MODES_SAMPLE_RATE: u64 = 2_000_000;
+pub const MODES_MLAT_CLOCK: u64 = 12_000_000;
+
+/* Magnitude of a full scale IQ sample, the reference for dBFS levels. */
+pub const MODES_FULL_SCALE_MAGNITUDE: f32 = 128.0;
+
 pub type FrameBits = [u8; MODES_LONG_MSG_BITS];
+pub type FrameConfidence = [u8; MODES_LONG_MSG_BITS];
 pub type FrameSamples = [u8; MODES_LONG_MSG_BITS * 2];
-pub struct Frame(Vec<u8>);
+pub type PreambleSamples = [u8; MODES_PREAMBLE_BITS * 2];
+
+/* Sub-sample phases the demodulator tries for every preamble candidate.
+ * At 2 MS/s a 0.5us pulse is exactly one sample wide, so a pulse that
+ * straddles two samples smears its energy across both of them. Instead of
+ * nudging samples after the fact we resample the window at several
+ * fractional offsets and keep the phase that demodulates best. */
+pub const MODES_DEMOD_PHASES: usize = 4;
+
+/* Minimum preamble correlation score (see FrameDecoder::preamble_score)
+ * before a candidate phase is demodulated at all. */
+pub const MODES_PREAMBLE_MIN_SCORE: f32 = 0.5;
+
+/* Bits whose two halves differ by less than this fraction of their sum
+ * are considered ambiguous by the demodulator. */
+pub const MODES_AMBIGUOUS_RATIO: f32 = 0.05;
+
+/* Frame::repair flips as many bits as it is allowed to where the CRC
+ * syndrome points, and one more if that lands on one of the
+ * MODES_REPAIR_WEAK_BITS lowest confidence bits of the frame. Never more
+ * than crc::MODES_MAX_CORRECTABLE_BITS in total. */
+pub const MODES_REPAIR_WEAK_BITS: usize = 8;
+
+// preamble pulse positions and the samples between them that should be quiet
+const PREAMBLE_PULSES: [usize; 4] = [0, 2, 7, 9];
+const PREAMBLE_QUIET: [usize; 8] = [1, 4, 5, 8, 11, 12, 13, 14];
+
+// samples needed to demodulate a long frame at any phase
+const MODES_WINDOW_SAMPLES: usize = (MODES_PREAMBLE_BITS + MODES_LONG_MSG_BITS) * 2 + 1;
+
+#[derive(Clone)]
+pub struct Frame {
+    bytes: Vec<u8>,
+    confidence: Vec<u8>,
+    score: f32,
+
+    sample_index: u64,
+    received_at: SystemTime,
+    phase: u8,
+    signal_level: f32,
+    noise_level: f32,
+    corrected: Vec<usize>,
+}
 
-pub struct FrameDecoder {}
+pub struct FrameDecoder {
+    // absolute index of the first sample in the decode buffer
+    position: u64,
+    // best candidate that failed CRC, and where the first candidate of its burst started
+    pending: Option<(u64, Frame)>,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> FrameDecoder {
+        FrameDecoder::new()
+    }
+}
 
 impl FrameDecoder {
     pub fn new() -> FrameDecoder {
-        FrameDecoder {}
-    }
-
-    fn detect_preamble(m: &[u8; MODES_PREAMBLE_BITS * 2]) -> bool {
-        /* First check of relations between the first 10 samples
-         * representing a valid preamble. We don't even investigate further
-         * if this simple test is not passed. */
-        if !(m[0] > m[1]
-            && m[1] < m[2]
-            && m[2] > m[3]
-            && m[3] < m[0]
-            && m[4] < m[0]
-            && m[5] < m[0]
-            && m[6] < m[0]
-            && m[7] > m[8]
-            && m[8] < m[9]
-            && m[9] > m[6])
-        {
-            // trace!("Unexpected ratio among first 10 samples");
-            return false;
+        FrameDecoder {
+            position: 0,
+            pending: None,
         }
+    }
+
+    fn consume(&mut self, src: &mut BytesMut, samples: usize) {
+        src.advance(samples);
+        self.position += samples as u64;
+        Metrics::add(&METRICS.mode_s_samples, samples as u64);
+    }
 
-        /* The samples between the two spikes must be < than the average
-         * of the high spikes level. We don't test bits too near to
-         * the high levels as signals can be out of phase so part of the
-         * energy can be in the near samples. */
-        let high = ((m[0] as i32 + m[2] as i32 + m[7] as i32 + m[9] as i32) / 6) as u8;
-        if m[4] >= high || m[5] >= high {
-            trace!("Too high level in samples between 3 and 6 {:?}", m);
-            return false;
+    fn emit(frame: Frame) -> Option<Frame> {
+        debug!(
+            "read raw frame: {} (score {:.3}, {:.1} dBFS)",
+            frame, frame.score, frame.signal_level
+        );
+        Metrics::inc(&METRICS.mode_s_demodulated);
+        if frame.valid() {
+            Metrics::inc(&METRICS.mode_s_crc_ok);
         }
+        Some(frame)
+    }
 
-        /* Similarly samples in the range 11-14 must be low, as it is the
-         * space between the preamble and real data. Again we don't test
-         * bits too near to high levels, see above. */
-        if m[11] >= high || m[12] >= high || m[13] >= high || m[14] >= high {
-            trace!("Too high level in samples between 10 and 15 {:?}", m);
-            return false;
+    /* Resample `m` at a fractional offset of `phase / MODES_DEMOD_PHASES`
+     * samples by linear interpolation between neighbouring samples. */
+    fn resample(m: &[u8], phase: usize, out: &mut [u8]) {
+        let w1 = phase as u16;
+        let w0 = MODES_DEMOD_PHASES as u16 - w1;
+        for (i, o) in out.iter_mut().enumerate() {
+            let v = (m[i] as u16 * w0 + m[i + 1] as u16 * w1 + w0 / 2) / MODES_DEMOD_PHASES as u16;
+            *o = v as u8;
         }
+    }
 
-        return true;
+    /* Correlate the first 16 samples against the ideal preamble:
+     *
+     *   pulses at 0, 1.0, 3.5 and 4.5us => samples 0, 2, 7 and 9
+     *   everything else in the 8us window should be quiet
+     *
+     * The score is (high - low) / (high + low) of the mean pulse and mean
+     * quiet levels, so 1.0 is a perfect preamble and <= 0 is noise. Samples
+     * directly next to a pulse (3, 6, 10 and 15) are not counted as quiet
+     * since they pick up energy from pulses that are slightly out of phase. */
+    fn preamble_score(m: &PreambleSamples) -> f32 {
+        let high = PREAMBLE_PULSES.iter().map(|&i| m[i] as f32).sum::<f32>()
+            / PREAMBLE_PULSES.len() as f32;
+        let low =
+            PREAMBLE_QUIET.iter().map(|&i| m[i] as f32).sum::<f32>() / PREAMBLE_QUIET.len() as f32;
+        if high <= 0.0 {
+            return 0.0;
+        }
+
+        /* The four pulses have to be of similar strength and every quiet
+         * sample has to stay below the weakest of them, otherwise a single
+         * strong spike (e.g. from an overlapping frame) can carry the score.
+         * Pulses half a sample out of phase put half their energy into the
+         * samples between them (1 and 8). Those may be as strong as long as
+         * the rest is well below the pulses, noise rarely manages that. */
+        let weakest = PREAMBLE_PULSES.iter().map(|&i| m[i]).min().unwrap_or(0);
+        let smeared = m[1] == weakest || m[8] == weakest;
+        let too_loud = |i: usize| match i {
+            1 | 8 => m[i] > weakest,
+            _ if smeared => m[i] >= weakest / 2,
+            _ => m[i] >= weakest,
+        };
+        if (weakest as f32) < high / 2.0 || PREAMBLE_QUIET.iter().any(|&i| too_loud(i)) {
+            return 0.0;
+        }
+
+        (high - low) / (high + low)
     }
 
-    fn demodulate_samples_to_bits(frame_samples: FrameSamples) -> FrameBits {
-        // decode bits from pulses
+    /* Demodulate the manchester encoded bits, a 1 is a high sample followed
+     * by a low one and a 0 the other way around. The confidence of every bit
+     * is the magnitude difference between its two halves.
+     *
+     * When both halves are within MODES_AMBIGUOUS_RATIO of each other we
+     * can't tell what was sent. Energy from the previous bit leaking into
+     * this one makes that most likely when the bit repeats, so ambiguous
+     * bits take the value of their predecessor and keep their (low)
+     * confidence so error correction knows where to look. */
+    fn demodulate_samples_to_bits(frame_samples: FrameSamples) -> (FrameBits, FrameConfidence) {
         let mut bits: FrameBits = [0; MODES_LONG_MSG_BITS];
+        let mut confidence: FrameConfidence = [0; MODES_LONG_MSG_BITS];
         for i in (0..frame_samples.len()).step_by(2) {
-            let low = frame_samples[i];
-            let high = frame_samples[i + 1];
-            let mut delta = low as i32 - high as i32;
-            if delta < 0 {
-                delta = -1 * delta;
-            }
-            if i > 0 && delta < 256 {
+            let first = frame_samples[i] as i32;
+            let second = frame_samples[i + 1] as i32;
+            let delta = (first - second).abs();
+
+            confidence[i / 2] = delta as u8;
+            if i > 0 && (delta as f32) < (first + second) as f32 * MODES_AMBIGUOUS_RATIO {
                 bits[i / 2] = bits[i / 2 - 1];
-            }
-            if low > high {
+            } else if first > second {
                 bits[i / 2] = 1;
             } else {
                 bits[i / 2] = 0;
             }
         }
 
-        return bits;
+        (bits, confidence)
     }
 
-    // phase correction (sorta) from dump1090
-    // @todo replace this with a proper phase correction
-    // on the upstream IQ samples
-    fn apply_phase_correction(s: FrameSamples) -> FrameSamples {
-        let mut m: FrameSamples = s;
-
-        for i in (0..(MODES_LONG_MSG_BITS - 1 * 2)).step_by(2) {
-            if m[i] > m[i + 1] {
-                m[i + 2] = m[i + 2] * 5 / 4;
-            } else {
-                m[i + 2] = m[i + 2] * 4 / 5;
-            }
-        }
-
-        m
-    }
-
-    fn pack_bits(bits: FrameBits) -> Frame {
+    fn pack_bits(bits: FrameBits) -> Vec<u8> {
         /* Pack bits into bytes */
         let mut frame_bytes = [0; MODES_LONG_MSG_BYTES];
         for i in (0..bits.len()).step_by(8) {
@@ -117,11 +202,90 @@ impl FrameDecoder {
 
         let msgtype = frame_bytes[0] >> 3;
         let msglen = match msgtype {
-            16 | 17 | 19 | 20 | 21 => MODES_LONG_MSG_BYTES,
+            16 | 17 | 18 | 19 | 20 | 21 | 24..=31 => MODES_LONG_MSG_BYTES,
             _ => MODES_SHORT_MSG_BYTES,
         };
 
-        Frame(frame_bytes[0..msglen].into())
+        frame_bytes[0..msglen].into()
+    }
+
+    /* Signal and noise power in dBFS. Every manchester bit has one sample
+     * carrying the pulse and one that should be empty, the pulses give the
+     * signal level and the empty halves plus the quiet preamble samples
+     * the noise floor around this frame. */
+    fn signal_levels(preamble: &PreambleSamples, frame_samples: &[u8]) -> (f32, f32) {
+        let power = |m: u8| {
+            let m = m as f32 / MODES_FULL_SCALE_MAGNITUDE;
+            m * m
+        };
+
+        let mut signal = 0.0;
+        let mut noise: f32 = PREAMBLE_QUIET.iter().map(|&i| power(preamble[i])).sum();
+        for pair in frame_samples.chunks(2) {
+            signal += power(std::cmp::max(pair[0], pair[1]));
+            noise += power(std::cmp::min(pair[0], pair[1]));
+        }
+
+        let bits = (frame_samples.len() / 2) as f32;
+        let to_dbfs = |p: f32| 10.0 * p.max(1e-10).log10();
+        (
+            to_dbfs(signal / bits),
+            to_dbfs(noise / (bits + PREAMBLE_QUIET.len() as f32)),
+        )
+    }
+
+    /* Try every demodulation phase for a window starting at a possible
+     * preamble. The first phase that yields a frame passing CRC wins,
+     * otherwise the frame from the phase with the best preamble score is
+     * returned. None if no phase looks like a preamble at all.
+     *
+     * Most windows are noise, so only the preamble is resampled up front
+     * and the data bits only for phases whose preamble scores well enough. */
+    fn demodulate(m: &[u8], position: u64) -> Option<Frame> {
+        let mut best: Option<Frame> = None;
+
+        for phase in 0..MODES_DEMOD_PHASES {
+            let mut preamble: PreambleSamples = Default::default();
+            FrameDecoder::resample(m, phase, &mut preamble);
+            let score = FrameDecoder::preamble_score(&preamble);
+            if score < MODES_PREAMBLE_MIN_SCORE {
+                continue;
+            }
+            if best.is_none() {
+                Metrics::inc(&METRICS.mode_s_preambles);
+            }
+
+            let mut frame_samples: FrameSamples = [0; MODES_LONG_MSG_BITS * 2];
+            FrameDecoder::resample(&m[MODES_PREAMBLE_BITS * 2..], phase, &mut frame_samples);
+
+            let (frame_bits, confidence) = FrameDecoder::demodulate_samples_to_bits(frame_samples);
+            let bytes = FrameDecoder::pack_bits(frame_bits);
+            let frame_samples = &frame_samples[0..bytes.len() * 8 * 2];
+            let (signal_level, noise_level) = FrameDecoder::signal_levels(&preamble, frame_samples);
+            let frame = Frame {
+                confidence: confidence[0..bytes.len() * 8].into(),
+                bytes,
+                score,
+                sample_index: position,
+                received_at: SystemTime::now(),
+                phase: phase as u8,
+                signal_level,
+                noise_level,
+                corrected: vec![],
+            };
+
+            trace!("phase {} score {:.3} => {}", phase, score, frame);
+            if frame.valid() {
+                return Some(frame);
+            }
+
+            match &best {
+                Some(b) if b.score >= frame.score => {}
+                _ => best = Some(frame),
+            }
+        }
+
+        best
     }
 }
 
@@ -129,42 +293,60 @@ impl codec::Decoder for FrameDecoder {
     type Item = Frame;
     type Error = std::io::Error;
 
+    /* Frames that pass CRC consume their preamble and exactly as many
+     * samples as their length, short frames don't swallow a following one.
+     *
+     * A frame that fails CRC doesn't consume anything but its first sample,
+     * since the "preamble" may just as well have been data bits of a frame
+     * starting a few samples later. Those candidates are held back until a
+     * preamble length has passed without finding a valid frame, and only
+     * the best scoring one of them is handed out for repair. */
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // consume buffer looking for a preamble
         loop {
-            if src.len() < (MODES_PREAMBLE_BITS + MODES_LONG_MSG_BITS) * 2 {
+            if let Some((start, _)) = &self.pending {
+                if self.position >= start + (MODES_PREAMBLE_BITS * 2) as u64 {
+                    let (_, frame) = self.pending.take().unwrap();
+                    return Ok(FrameDecoder::emit(frame));
+                }
+            }
+
+            if src.len() < MODES_WINDOW_SAMPLES {
                 // Not enough data
                 return Ok(None);
             }
 
-            let mut preamble: [u8; (MODES_PREAMBLE_BITS * 2)] = Default::default();
-            preamble.clone_from_slice(&src[0..(MODES_PREAMBLE_BITS * 2)]);
-
-            if FrameDecoder::detect_preamble(&preamble) {
-                src.advance(MODES_PREAMBLE_BITS * 2);
-                break;
+            match FrameDecoder::demodulate(&src[0..MODES_WINDOW_SAMPLES], self.position) {
+                Some(frame) if frame.valid() => {
+                    // advance the buffer by the preamble and length of the actual decoded frame
+                    self.pending = None;
+                    self.consume(src, (MODES_PREAMBLE_BITS + frame.bytes.len() * 8) * 2);
+                    return Ok(FrameDecoder::emit(frame));
+                }
+                Some(frame) => match &mut self.pending {
+                    Some((_, best)) if best.score >= frame.score => {}
+                    Some((_, best)) => *best = frame,
+                    None => self.pending = Some((self.position, frame)),
+                },
+                None => {}
             }
 
             // We slide the buffer window 1 sample at a time until this function detects a preamble
-            src.advance(1);
+            self.consume(src, 1);
         }
+    }
 
-        // We have a valid preamble, read full sized frame
-        let mut frame_samples: [u8; MODES_LONG_MSG_BITS * 2] = [0; MODES_LONG_MSG_BITS * 2];
-        let s = &src[0..(MODES_LONG_MSG_BITS * 2)];
-        frame_samples.clone_from_slice(s);
-
-        //let frame_samples = FrameDecoder::apply_phase_correction(frame_samples);
-
-        let frame_bits = FrameDecoder::demodulate_samples_to_bits(frame_samples);
-        let frame = FrameDecoder::pack_bits(frame_bits);
-
-        debug!("read raw frame: {}", frame);
-
-        // advance the buffer by the preamble and length of the actual decoded frame
-        src.advance(MODES_LONG_MSG_BITS * 2);
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
+        }
 
-        Ok(Some(frame))
+        // whatever is left is too short to hold a frame
+        let remaining = src.len();
+        self.consume(src, remaining);
+        Ok(self
+            .pending
+            .take()
+            .and_then(|(_, frame)| FrameDecoder::emit(frame)))
     }
 }
 
@@ -174,44 +356,158 @@ impl std::fmt::Display for Frame {
             true => "valid",
             false => "invalid",
         };
-        write!(f, "({},{})", hex::encode(&self.0), valid)
+        write!(f, "({},{})", hex::encode(&self.bytes), valid)
     }
 }
 
 impl Frame {
-    pub fn valid(&self) -> bool {
-        let frame_bytes = &self.0;
-        let crc: u32 = ((frame_bytes[frame_bytes.len() - 3] as u32) << 16)
-            | ((frame_bytes[frame_bytes.len() - 2] as u32) << 8)
-            | (frame_bytes[frame_bytes.len() - 1] as u32);
+    /// A frame that arrived as bytes rather than samples (AVR, Beast or a
+    /// test), every bit fully confident and no signal levels
+    pub fn from_bytes(bytes: &[u8], received_at: SystemTime) -> Frame {
+        Frame {
+            bytes: bytes.to_vec(),
+            confidence: vec![u8::MAX; bytes.len() * 8],
+            score: 1.0,
+            sample_index: 0,
+            received_at,
+            phase: 0,
+            signal_level: 0.0,
+            noise_level: 0.0,
+            corrected: vec![],
+        }
+    }
 
-        let crc2: u32 = crc::modes_checksum(&frame_bytes);
-        let valid = crc == crc2;
-        debug!("crc: {:#x} crc2:{:#x} match: {}", crc, crc2, valid);
-        valid
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
     }
 
-    pub fn try_repair(&self) -> Option<Frame> {
-        debug!("attempting repair {}", self);
-        if let Some(repaired_frame) = crc::modes_repair_single_bit(&self.0) {
-            info!(
-                "repaired frame {} => {}",
-                hex::encode(&self.0),
-                hex::encode(&repaired_frame)
-            );
-            return Some(Frame(repaired_frame));
+    /// Preamble correlation score of the phase this frame was demodulated at
+    pub fn score(&self) -> f32 {
+        self.score
+    }
+
+    /// Per-bit demodulator confidence, one entry for every bit of the frame
+    pub fn confidence(&self) -> &[u8] {
+        &self.confidence
+    }
+
+    /// Absolute index of the first preamble sample since the decoder started
+    pub fn sample_index(&self) -> u64 {
+        self.sample_index
+    }
+
+    /// Start of the preamble on the 12 MHz MLAT clock, including the sub-sample demod phase
+    pub fn mlat_timestamp(&self) -> u64 {
+        let phases = MODES_DEMOD_PHASES as u64;
+        (self.sample_index * phases + self.phase as u64) * MODES_MLAT_CLOCK
+            / (MODES_SAMPLE_RATE * phases)
+    }
+
+    /// System time at which the frame was demodulated
+    pub fn received_at(&self) -> SystemTime {
+        self.received_at
+    }
+
+    /// Sub-sample phase (in 1 / MODES_DEMOD_PHASES samples) the frame was demodulated at
+    pub fn phase(&self) -> u8 {
+        self.phase
+    }
+
+    /// Signal level (RSSI) in dBFS
+    pub fn signal_level(&self) -> f32 {
+        self.signal_level
+    }
+
+    /// Noise floor around the frame in dBFS
+    pub fn noise_level(&self) -> f32 {
+        self.noise_level
+    }
+
+    /// Number of bits flipped by error correction
+    pub fn corrected_bits(&self) -> u8 {
+        self.corrected.len() as u8
+    }
+
+    /// Positions of the bits flipped by error correction
+    pub fn corrected(&self) -> &[usize] {
+        &self.corrected
+    }
+
+    // bit positions sorted from least to most confident, fully confident bits aren't weak
+    fn weakest_bits(&self, n: usize) -> Vec<usize> {
+        let mut positions: Vec<usize> = (0..self.confidence.len())
+            .filter(|&i| self.confidence[i] < u8::MAX)
+            .collect();
+        positions.sort_by_key(|&i| self.confidence[i]);
+        positions.truncate(n);
+        positions
+    }
+
+    pub fn downlink_format(&self) -> u8 {
+        // DF24 and up only use the first two bits
+        std::cmp::min(self.bytes[0] >> 3, 24)
+    }
+
+    /// ICAO address of the sender, from the AA field of all-call replies and
+    /// extended squitters or recovered from the parity of other replies.
+    pub fn icao(&self) -> Option<u32> {
+        match self.downlink_format() {
+            11 | 17 | 18 => Some(
+                (self.bytes[1] as u32) << 16 | (self.bytes[2] as u32) << 8 | self.bytes[3] as u32,
+            ),
+            _ => self.icao_from_parity(),
         }
+    }
 
-        None
+    /// Replies to interrogations (DF0/4/5/16/20/21) don't carry the address,
+    /// instead their parity is the CRC xored with it (address/parity, AP).
+    /// Xoring the CRC back out leaves the address, which is only trustworthy
+    /// if we have seen it before, see `filter::IcaoFilter`.
+    pub fn icao_from_parity(&self) -> Option<u32> {
+        match self.downlink_format() {
+            0 | 4 | 5 | 16 | 20 | 21 => Some(crc::modes_syndrome(&self.bytes)),
+            _ => None,
+        }
     }
 
-    pub fn parse(&self) -> Option<adsb::Message> {
-        match adsb::parse_binary(&self.0) {
-            Ok((message, _)) => Some(message),
-            Err(error) => {
-                error!("error parsing ads-b frame {:#?}", error);
-                None
-            }
+    /// True if the CRC checks out. Always false for address/parity formats,
+    /// see `icao_from_parity`.
+    pub fn valid(&self) -> bool {
+        crc::modes_syndrome(&self.bytes) == 0
+    }
+
+    /// Correct up to `max_bits` bit errors, or one more if it is among the bits the
+    /// demodulator was least sure of (at most crc::MODES_MAX_CORRECTABLE_BITS),
+    /// see `crc::modes_correct_with_hints`
+    pub fn repair(&self, max_bits: usize) -> Option<Frame> {
+        debug!("attempting repair {}", self);
+        let max_bits = max_bits.min(crc::MODES_MAX_CORRECTABLE_BITS);
+
+        // the demodulator tells us which bits it wasn't sure about, use those as hints
+        let weak_bits = self.weakest_bits(MODES_REPAIR_WEAK_BITS);
+        let mut repaired_frame = self.bytes.clone();
+        let corrected = crc::modes_correct_with_hints(&mut repaired_frame, &weak_bits, max_bits)?;
+
+        info!(
+            "repaired frame {} => {} (bits {:?})",
+            hex::encode(&self.bytes),
+            hex::encode(&repaired_frame),
+            corrected
+        );
+
+        let mut frame = self.clone();
+        frame.bytes = repaired_frame;
+        frame.corrected = corrected;
+        Some(frame)
+    }
+
+    /// The ME field of an extended squitter (DF17/18) decoded, None for other
+    /// formats and coarse TIS-B (DF18 CF 3) which has a format of its own
+    pub fn parse(&self) -> Option<EsMessage> {
+        match self.downlink_format() {
+            18 if self.bytes[0] & 0x7 == 3 => None,
+            17 | 18 => Some(es::decode(&self.bytes[4..11])),
+            _ => None,
         }
     }
 }
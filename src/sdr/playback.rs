@@ -0,0 +1,192 @@
+use log::*;
+use std::future::Future;
+use std::io;
+use std::io::SeekFrom;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+use tokio::time::{self, Duration, Instant, Sleep};
+
+// every sample is an [i, q] byte pair
+const BYTES_PER_SAMPLE: u64 = 2;
+
+// how far ahead of the playback clock we are allowed to wake up and deliver
+const PLAYBACK_TICK: Duration = Duration::from_millis(10);
+
+/// Paces IQ samples from a recording (usually a `tokio::fs::File`) so they are
+/// delivered at the rate they were captured, optionally sped up, slowed down or looped.
+pub struct Playback<T: AsyncRead + AsyncSeek + Unpin> {
+    inner: T,
+    sample_rate: u32,
+    speed: f64,
+    looping: bool,
+
+    started: Option<Instant>,
+    delivered: u64,
+    delivered_since_rewind: u64,
+    rewinding: bool,
+    sleep: Pin<Box<Sleep>>,
+    // half of a sample read but held back to end the read on a sample boundary
+    carry: Option<u8>,
+}
+
+impl<T: AsyncRead + AsyncSeek + Unpin> Playback<T> {
+    pub fn new(inner: T, sample_rate: u32) -> Playback<T> {
+        Playback {
+            inner,
+            sample_rate,
+            speed: 1.0,
+            looping: false,
+            started: None,
+            delivered: 0,
+            delivered_since_rewind: 0,
+            rewinding: false,
+            sleep: Box::pin(time::sleep(Duration::from_secs(0))),
+            carry: None,
+        }
+    }
+
+    /// Playback speed multiplier, e.g. 0.5, 2.0 or 10.0
+    pub fn speed(mut self, speed: f64) -> Playback<T> {
+        assert!(speed > 0.0, "playback speed must be positive");
+        self.speed = speed;
+        self
+    }
+
+    /// Rewind to the start of the recording instead of signaling EOF
+    pub fn looping(mut self, looping: bool) -> Playback<T> {
+        self.looping = looping;
+        self
+    }
+
+    fn bytes_per_second(&self) -> f64 {
+        self.sample_rate as f64 * BYTES_PER_SAMPLE as f64 * self.speed
+    }
+
+    // number of bytes the playback clock allows us to have delivered at `now`
+    fn budget(&self, started: Instant, now: Instant) -> u64 {
+        let elapsed = (now - started).as_secs_f64();
+        (elapsed * self.bytes_per_second()) as u64
+    }
+
+    // the instant at which `bytes` will have been allowed by the playback clock
+    fn deadline(&self, started: Instant, bytes: u64) -> Instant {
+        started + Duration::from_secs_f64(bytes as f64 / self.bytes_per_second())
+    }
+
+    // put back a byte carried into `buf` at `start` when nothing else could be read
+    fn hold_back(&mut self, buf: &mut ReadBuf<'_>, start: usize) {
+        if buf.filled().len() > start {
+            self.carry = Some(buf.filled()[start]);
+            buf.set_filled(start);
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncSeek + Unpin> AsyncRead for Playback<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        trace!("Playback poll_read");
+        let this = self.get_mut();
+
+        loop {
+            if this.rewinding {
+                match Pin::new(&mut this.inner).poll_complete(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                    Poll::Ready(Ok(_)) => {
+                        debug!("Playback rewound to start of recording");
+                        this.rewinding = false;
+                        this.delivered_since_rewind = 0;
+                    }
+                }
+            }
+
+            let now = Instant::now();
+            let started = *this.started.get_or_insert(now);
+
+            // wait until the clock allows at least one more whole sample
+            let budget = this.budget(started, now + PLAYBACK_TICK);
+            if budget < this.delivered + BYTES_PER_SAMPLE {
+                let deadline = this.deadline(started, this.delivered + BYTES_PER_SAMPLE);
+                this.sleep.as_mut().reset(deadline);
+                match this.sleep.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(()) => continue,
+                }
+            }
+
+            /* Reads always end on an [i, q] pair boundary for IQMagnitudeReader.
+             * A caller without room for a whole sample is made to wait, handing
+             * it a single byte would split the pair and an empty read would be
+             * taken for EOF. */
+            if buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+            let allowed = budget - this.delivered;
+            let limit = std::cmp::min(allowed, buf.remaining() as u64);
+            let limit = (limit - limit % BYTES_PER_SAMPLE) as usize;
+            if limit == 0 {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            let start = buf.filled().len();
+            if let Some(byte) = this.carry.take() {
+                buf.put_slice(&[byte]);
+            }
+
+            let carried = buf.filled().len() - start;
+            let mut inner_buf = buf.take(limit - carried);
+            let result = Pin::new(&mut this.inner).poll_read(cx, &mut inner_buf);
+            let n = inner_buf.filled().len();
+            match result {
+                Poll::Pending => {
+                    this.hold_back(buf, start);
+                    return Poll::Pending;
+                }
+                Poll::Ready(Err(e)) => {
+                    this.hold_back(buf, start);
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Ready(Ok(())) if n == 0 => {
+                    // EOF, half a sample left over at the end of a recording can't be played
+                    buf.set_filled(start);
+                    if this.looping && this.delivered_since_rewind > 0 {
+                        Pin::new(&mut this.inner).start_seek(SeekFrom::Start(0))?;
+                        this.rewinding = true;
+                        continue;
+                    }
+                    trace!("Playback reached end of recording");
+                    return Poll::Ready(Ok(()));
+                }
+                Poll::Ready(Ok(())) => {
+                    // filled region of `inner_buf` aliases the unfilled region of `buf`
+                    unsafe {
+                        buf.assume_init(n);
+                    }
+                    buf.advance(n);
+                }
+            }
+
+            // an odd read from the inner reader leaves half a sample, keep it for next time
+            let mut n = buf.filled().len() - start;
+            if !(n as u64).is_multiple_of(BYTES_PER_SAMPLE) {
+                this.carry = Some(buf.filled()[start + n - 1]);
+                n -= 1;
+                buf.set_filled(start + n);
+            }
+            if n == 0 {
+                continue;
+            }
+
+            this.delivered += n as u64;
+            this.delivered_since_rewind += n as u64;
+            trace!("Playback wrote {} bytes into buf", n);
+            return Poll::Ready(Ok(()));
+        }
+    }
+}
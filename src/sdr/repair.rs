@@ -0,0 +1,238 @@
+use log::*;
+use std::fmt;
+use std::str::FromStr;
+
+use super::filter::IcaoFilter;
+use super::mode_s::Frame;
+use crate::metrics::{Metrics, METRICS};
+
+/* Bits error correction is never allowed to touch. Flipping a bit of the
+ * downlink format turns the frame into a different message type with a
+ * different length and CRC coverage, any "fix" there is bogus. */
+pub const REPAIR_PROTECTED_BITS: usize = 5;
+
+/// Which frames error correction is attempted on, and how hard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepairPolicy {
+    /// Never repair frames
+    Off,
+    /// Correct single bit errors in DF17/18 extended squitters. In DF17 a
+    /// second bit may be flipped if the demodulator was unsure of it and the
+    /// address is one we recently heard from.
+    SingleBit,
+    /// Like SingleBit, additionally correct two bit errors in DF17
+    /// if the repaired address is one we recently heard from
+    TwoBitKnownIcao,
+}
+
+impl RepairPolicy {
+    // most bits that may be corrected in a frame of the given downlink format
+    fn max_bits(&self, df: u8) -> usize {
+        match (self, df) {
+            (RepairPolicy::Off, _) => 0,
+            (RepairPolicy::SingleBit, 17) | (RepairPolicy::SingleBit, 18) => 1,
+            (RepairPolicy::TwoBitKnownIcao, 17) => 2,
+            (RepairPolicy::TwoBitKnownIcao, 18) => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl FromStr for RepairPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" | "none" => Ok(RepairPolicy::Off),
+            "1bit" => Ok(RepairPolicy::SingleBit),
+            "2bit" => Ok(RepairPolicy::TwoBitKnownIcao),
+            _ => Err(format!(
+                "unknown repair policy '{}' (expected off, 1bit or 2bit)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RepairPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepairPolicy::Off => write!(f, "off"),
+            RepairPolicy::SingleBit => write!(f, "1bit"),
+            RepairPolicy::TwoBitKnownIcao => write!(f, "2bit"),
+        }
+    }
+}
+
+/// What became of a frame handed to the `Repairer`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepairResult {
+    Skipped,
+    Corrected1Bit,
+    Corrected2Bit,
+    Refused,
+    Failed,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RepairCounts {
+    /// Invalid frames handed to the repairer
+    pub attempted: u64,
+    /// Frames the policy doesn't allow repairing for this DF
+    pub skipped: u64,
+    /// Successful corrections by number of bits flipped
+    pub corrected_1bit: u64,
+    pub corrected_2bit: u64,
+    /// Corrections found but refused by a safeguard
+    pub refused: u64,
+    /// No correction found
+    pub failed: u64,
+}
+
+/// Repair statistics per downlink format
+pub struct RepairStats {
+    per_df: [RepairCounts; 25],
+}
+
+impl RepairStats {
+    fn new() -> RepairStats {
+        RepairStats {
+            per_df: [RepairCounts::default(); 25],
+        }
+    }
+
+    pub fn df(&self, df: u8) -> &RepairCounts {
+        &self.per_df[df as usize]
+    }
+
+    pub fn total(&self) -> RepairCounts {
+        self.per_df
+            .iter()
+            .fold(RepairCounts::default(), |a, c| RepairCounts {
+                attempted: a.attempted + c.attempted,
+                skipped: a.skipped + c.skipped,
+                corrected_1bit: a.corrected_1bit + c.corrected_1bit,
+                corrected_2bit: a.corrected_2bit + c.corrected_2bit,
+                refused: a.refused + c.refused,
+                failed: a.failed + c.failed,
+            })
+    }
+}
+
+impl fmt::Display for RepairStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (df, c) in self.per_df.iter().enumerate() {
+            if c.attempted == 0 {
+                continue;
+            }
+            writeln!(
+                f,
+                "DF{}: attempted {} skipped {} 1bit {} 2bit {} refused {} failed {}",
+                df, c.attempted, c.skipped, c.corrected_1bit, c.corrected_2bit, c.refused, c.failed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Applies a `RepairPolicy` to frames that failed CRC
+pub struct Repairer {
+    policy: RepairPolicy,
+    stats: RepairStats,
+}
+
+impl Repairer {
+    pub fn new(policy: RepairPolicy) -> Repairer {
+        Repairer {
+            policy,
+            stats: RepairStats::new(),
+        }
+    }
+
+    pub fn policy(&self) -> RepairPolicy {
+        self.policy
+    }
+
+    pub fn stats(&self) -> &RepairStats {
+        &self.stats
+    }
+
+    /// Try to repair an invalid frame within the limits of the policy. `known` decides
+    /// whether the address of a two bit correction is trusted. Valid frames are
+    /// returned as they are and not counted.
+    pub fn repair(&mut self, frame: &Frame, known: &IcaoFilter) -> Option<Frame> {
+        // nothing to repair, and nothing to count
+        if frame.valid() {
+            return Some(frame.clone());
+        }
+
+        let df = frame.downlink_format();
+        let (repaired, result) = self.try_repair(frame, known);
+        self.count(df, result);
+        repaired
+    }
+
+    fn try_repair(&self, frame: &Frame, known: &IcaoFilter) -> (Option<Frame>, RepairResult) {
+        let df = frame.downlink_format();
+        let max_bits = self.policy.max_bits(df);
+        if max_bits == 0 {
+            return (None, RepairResult::Skipped);
+        }
+
+        let repaired = match frame.repair(max_bits) {
+            Some(repaired) => repaired,
+            None => return (None, RepairResult::Failed),
+        };
+
+        if repaired
+            .corrected()
+            .iter()
+            .any(|&bit| bit < REPAIR_PROTECTED_BITS)
+        {
+            debug!("refusing repair in DF field {:?}", repaired.corrected());
+            return (None, RepairResult::Refused);
+        }
+
+        if repaired.corrected_bits() > 1 {
+            /* A weak bit may take a repair past the policy limit, but DF18
+             * addresses (TIS-B, ADS-R) can't be vouched for by `known`, those
+             * are held to a single bit. */
+            let icao = repaired.icao();
+            let trusted =
+                df != 18 && icao.is_some_and(|icao| known.contains(icao, repaired.received_at()));
+            if !trusted {
+                debug!(
+                    "refusing {}bit repair of DF{} from {:06x?}",
+                    repaired.corrected_bits(),
+                    df,
+                    icao
+                );
+                return (None, RepairResult::Refused);
+            }
+        }
+
+        match repaired.corrected_bits() {
+            1 => (Some(repaired), RepairResult::Corrected1Bit),
+            _ => (Some(repaired), RepairResult::Corrected2Bit),
+        }
+    }
+
+    fn count(&mut self, df: u8, result: RepairResult) {
+        let counts = &mut self.stats.per_df[df as usize];
+        counts.attempted += 1;
+        match result {
+            RepairResult::Skipped => counts.skipped += 1,
+            RepairResult::Corrected1Bit => {
+                counts.corrected_1bit += 1;
+                Metrics::inc(&METRICS.mode_s_corrected_1bit);
+            }
+            RepairResult::Corrected2Bit => {
+                counts.corrected_2bit += 1;
+                Metrics::inc(&METRICS.mode_s_corrected_2bit);
+            }
+            RepairResult::Refused => counts.refused += 1,
+            RepairResult::Failed => counts.failed += 1,
+        }
+        METRICS.mode_s_repair(df, result);
+    }
+}
@@ -1,7 +1,10 @@
 use log::*;
 use ringbuf::{Consumer, RingBuffer};
+use std::ffi::{CStr, CString};
 use std::io;
+use std::os::raw::{c_char, c_int};
 use std::pin::Pin;
+use std::ptr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll, Waker};
@@ -10,8 +13,114 @@ use tokio::{
     task,
 };
 
+use super::uat::{UAT_CENTER_FREQ, UAT_SAMPLE_RATE};
+use crate::metrics::{Metrics, METRICS};
+
 pub const RTL_SDR_BUFFER_SIZE: usize = 512000;
 
+/* librtlsdr calls rtlsdr_mt doesn't wrap, the library is linked through it */
+#[repr(C)]
+struct RtlSdrDevice {
+    _private: [u8; 0],
+}
+
+extern "C" {
+    fn rtlsdr_open(device: *mut *mut RtlSdrDevice, index: u32) -> c_int;
+    fn rtlsdr_close(device: *mut RtlSdrDevice) -> c_int;
+    fn rtlsdr_get_tuner_gains(device: *mut RtlSdrDevice, gains: *mut c_int) -> c_int;
+    fn rtlsdr_get_device_count() -> u32;
+    fn rtlsdr_get_device_usb_strings(
+        index: u32,
+        manufacturer: *mut c_char,
+        product: *mut c_char,
+        serial: *mut c_char,
+    ) -> c_int;
+    fn rtlsdr_get_index_by_serial(serial: *const c_char) -> c_int;
+}
+
+/* librtlsdr wants 256 byte buffers for the USB strings */
+const USB_STRING_BYTES: usize = 256;
+
+/// A connected dongle, as listed by `devices`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub index: u32,
+    pub manufacturer: String,
+    pub product: String,
+    pub serial: String,
+}
+
+/// Dongles plugged in right now
+pub fn devices() -> Vec<DeviceInfo> {
+    let count = unsafe { rtlsdr_get_device_count() };
+
+    (0..count)
+        .filter_map(|index| {
+            let mut strings = [[0 as c_char; USB_STRING_BYTES]; 3];
+            let [manufacturer, product, serial] = &mut strings;
+            let result = unsafe {
+                rtlsdr_get_device_usb_strings(
+                    index,
+                    manufacturer.as_mut_ptr(),
+                    product.as_mut_ptr(),
+                    serial.as_mut_ptr(),
+                )
+            };
+            if result != 0 {
+                warn!("rtl-sdr {}: can't read usb strings ({})", index, result);
+                return None;
+            }
+
+            let string = |s: &[c_char]| {
+                unsafe { CStr::from_ptr(s.as_ptr()) }
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            Some(DeviceInfo {
+                index,
+                manufacturer: string(manufacturer),
+                product: string(product),
+                serial: string(serial),
+            })
+        })
+        .collect()
+}
+
+/// Index of the dongle with a serial number (set with `rtl_eeprom -s`)
+pub fn device_index(serial: &str) -> io::Result<u8> {
+    let serial_c = CString::new(serial)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "serial contains a nul byte"))?;
+
+    match unsafe { rtlsdr_get_index_by_serial(serial_c.as_ptr()) } {
+        index if index >= 0 => Ok(index as u8),
+        _ => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no rtl-sdr with serial {}", serial),
+        )),
+    }
+}
+
+/// Gains the tuner of a dongle supports in dB, it must not be in use
+pub fn tuner_gains(index: u32) -> io::Result<Vec<f64>> {
+    let mut device = ptr::null_mut();
+    if unsafe { rtlsdr_open(&mut device, index) } != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("can't open rtl-sdr {}", index),
+        ));
+    }
+
+    // asked once for the count, then for the gains in tenths of a dB
+    let count = unsafe { rtlsdr_get_tuner_gains(device, ptr::null_mut()) };
+    let mut gains = vec![0 as c_int; count.max(0) as usize];
+    if count > 0 {
+        unsafe { rtlsdr_get_tuner_gains(device, gains.as_mut_ptr()) };
+    }
+    unsafe { rtlsdr_close(device) };
+
+    Ok(gains.into_iter().map(|gain| gain as f64 / 10.0).collect())
+}
+
 pub struct RadioConfig {
     device_index: u8,
     sample_rate: u32,
@@ -22,12 +131,37 @@ pub struct RadioConfig {
 impl RadioConfig {
     pub fn mode_s(device_index: u8) -> RadioConfig {
         RadioConfig {
-            device_index: device_index,
+            device_index,
             sample_rate: 2_000_000,
             center_freq: 1_090_000_000,
             ppm: 0,
         }
     }
+
+    pub fn uat(device_index: u8) -> RadioConfig {
+        RadioConfig {
+            device_index,
+            sample_rate: UAT_SAMPLE_RATE,
+            center_freq: UAT_CENTER_FREQ,
+            ppm: 0,
+        }
+    }
+
+    /// Use the dongle with this serial number instead of the device index
+    pub fn serial(mut self, serial: &str) -> io::Result<RadioConfig> {
+        self.device_index = device_index(serial)?;
+        Ok(self)
+    }
+
+    /// Frequency correction of the dongle's crystal
+    pub fn ppm(mut self, ppm: i32) -> RadioConfig {
+        self.ppm = ppm;
+        self
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
 }
 
 pub struct Radio {
@@ -38,7 +172,7 @@ pub struct Radio {
 }
 
 impl Radio {
-    pub fn open(cfg: RadioConfig) -> Radio {
+    pub fn open(cfg: RadioConfig) -> io::Result<Radio> {
         debug!("starting rtl-sdr with device-id {}", cfg.device_index);
 
         // setup iq sample buffer
@@ -49,7 +183,12 @@ impl Radio {
         let shared_waker_slot = Arc::new(Mutex::new(Option::<Waker>::None));
         let closed_flag = Arc::new(AtomicBool::new(false));
 
-        let (mut ctl, mut reader) = rtlsdr_mt::open(cfg.device_index.into()).unwrap();
+        let (mut ctl, mut reader) = rtlsdr_mt::open(cfg.device_index.into()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("can't open rtl-sdr {}", cfg.device_index),
+            )
+        })?;
         ctl.enable_agc().unwrap();
         ctl.set_ppm(cfg.ppm).unwrap();
         ctl.set_sample_rate(cfg.sample_rate).unwrap();
@@ -61,7 +200,19 @@ impl Radio {
         task::spawn_blocking(move || {
             let res = reader.read_async(12, RTL_SDR_BUFFER_SIZE as u32, |bytes| {
                 trace!("got buffer from rtl-sdr iq");
-                iq_producer.push_slice(bytes);
+                let pushed = iq_producer.push_slice(bytes);
+                if pushed < bytes.len() {
+                    // the decoder can't keep up, what didn't fit is lost
+                    warn!(
+                        "rtl-sdr buffer overflow, dropped {} bytes",
+                        bytes.len() - pushed
+                    );
+                    Metrics::inc(&METRICS.radio_overflows);
+                    Metrics::add(
+                        &METRICS.radio_dropped_samples,
+                        (bytes.len() - pushed) as u64 / 2,
+                    );
+                }
 
                 let mut guard = rtl_shared_waker_slot.lock().unwrap();
                 if let Some(waker) = &*guard {
@@ -79,16 +230,14 @@ impl Radio {
             }
 
             debug!("rtl-sdr reader thread finished ({:?})", res);
-
-            ()
         });
 
-        Radio {
+        Ok(Radio {
             consumer: iq_consumer,
             waker: shared_waker_slot,
-            ctl: ctl,
+            ctl,
             closed: closed_flag,
-        }
+        })
     }
 }
 
@@ -109,8 +258,8 @@ impl AsyncRead for Radio {
             return Poll::Pending;
         }
 
-        let mut remaining = buf.initialize_unfilled();
-        let n = self.get_mut().consumer.pop_slice(&mut remaining);
+        let remaining = buf.initialize_unfilled();
+        let n = self.get_mut().consumer.pop_slice(remaining);
         buf.advance(n);
         trace!("rtl-sdr AsyncRead wrote {} into buf", n);
         Poll::Ready(Ok(()))
@@ -119,8 +268,7 @@ impl AsyncRead for Radio {
 
 impl Drop for Radio {
     fn drop(&mut self) {
-        &self.ctl.cancel_async_read();
+        self.ctl.cancel_async_read();
         trace!("rtl-sdr reader thread canceled");
     }
 }
-
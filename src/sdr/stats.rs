@@ -0,0 +1,175 @@
+/* What's in a recording: frames by type, CRC failures, error correction,
+ * addresses heard and signal levels. Mode S frames are counted straight
+ * from the demodulator, before address filtering and repair, so this
+ * shows what the radio picked up rather than what the tracker took.
+ */
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+use tokio::io::AsyncRead;
+use tokio_stream::StreamExt;
+use tokio_util::codec::FramedRead;
+
+use super::input::Band;
+use super::rtl::RTL_SDR_BUFFER_SIZE;
+use super::{dsp, mode_s, uat};
+use crate::adsb::uat as uat_payload;
+
+#[derive(Debug, Clone)]
+pub struct RecordingStats {
+    pub band: Band,
+    /// IQ samples in the recording, if the caller knows
+    pub samples: Option<u64>,
+    /// Frames by type, `DF17`, `UAT long ADS-B` etc.
+    pub frames: BTreeMap<String, u64>,
+    /// Frames failing CRC (Mode S replies with address/parity aren't counted)
+    pub invalid: u64,
+    /// Frames with bits or bytes fixed by error correction
+    pub corrected: u64,
+    addresses: HashSet<u32>,
+    // min, max and sum of signal levels in dBFS
+    signal: Option<(f32, f32, f64)>,
+}
+
+impl RecordingStats {
+    pub fn new(band: Band) -> RecordingStats {
+        RecordingStats {
+            band,
+            samples: None,
+            frames: BTreeMap::new(),
+            invalid: 0,
+            corrected: 0,
+            addresses: HashSet::new(),
+            signal: None,
+        }
+    }
+
+    fn add(&mut self, kind: String, signal_level: f32) {
+        *self.frames.entry(kind).or_insert(0) += 1;
+        self.signal = Some(match self.signal {
+            Some((min, max, sum)) => (
+                min.min(signal_level),
+                max.max(signal_level),
+                sum + signal_level as f64,
+            ),
+            None => (signal_level, signal_level, signal_level as f64),
+        });
+    }
+
+    pub fn add_mode_s(&mut self, frame: &mode_s::Frame) {
+        let df = frame.downlink_format();
+        if frame.icao_from_parity().is_none() && !frame.valid() {
+            self.invalid += 1;
+            return;
+        }
+        if frame.corrected_bits() > 0 {
+            self.corrected += 1;
+        }
+        // addresses from parity are only trustworthy once seen in the clear
+        if let 11 | 17 | 18 = df {
+            self.addresses.extend(frame.icao());
+        }
+        self.add(format!("DF{}", df), frame.signal_level());
+    }
+
+    pub fn add_uat(&mut self, frame: &uat::Frame) {
+        if frame.corrected() > 0 {
+            self.corrected += 1;
+        }
+        if frame.is_adsb() {
+            self.addresses
+                .extend(uat_payload::decode(frame.bytes()).map(|p| p.address));
+        }
+        let kind = match frame.kind() {
+            uat::FrameKind::BasicAdsb => "UAT basic ADS-B",
+            uat::FrameKind::LongAdsb => "UAT long ADS-B",
+            uat::FrameKind::Uplink => "UAT uplink",
+        };
+        self.add(kind.to_string(), frame.signal_level());
+    }
+
+    /// Frames that passed CRC or FEC
+    pub fn valid(&self) -> u64 {
+        self.frames.values().sum()
+    }
+
+    /// Distinct addresses heard
+    pub fn aircraft(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// Mean signal level in dBFS
+    pub fn mean_signal(&self) -> Option<f32> {
+        self.signal
+            .map(|(_, _, sum)| (sum / self.valid() as f64) as f32)
+    }
+
+    /// Length of the recording
+    pub fn duration(&self) -> Option<Duration> {
+        let sample_rate = self.band.sample_rate() as f64;
+        self.samples
+            .map(|samples| Duration::from_secs_f64(samples as f64 / sample_rate))
+    }
+}
+
+impl fmt::Display for RecordingStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "band:       {} MHz", self.band)?;
+        if let Some(duration) = self.duration() {
+            let seconds = duration.as_secs_f64();
+            writeln!(f, "duration:   {:.1}s", seconds)?;
+            if seconds > 0.0 {
+                writeln!(
+                    f,
+                    "rate:       {:.1} frames/s",
+                    self.valid() as f64 / seconds
+                )?;
+            }
+        }
+        writeln!(f, "valid:      {}", self.valid())?;
+        writeln!(f, "invalid:    {}", self.invalid)?;
+        writeln!(f, "corrected:  {}", self.corrected)?;
+        writeln!(f, "aircraft:   {}", self.aircraft())?;
+        if let (Some((min, max, _)), Some(mean)) = (self.signal, self.mean_signal()) {
+            writeln!(
+                f,
+                "signal:     {:.1} / {:.1} / {:.1} dBFS (min/mean/max)",
+                min, mean, max
+            )?;
+        }
+        for (kind, count) in &self.frames {
+            writeln!(f, "  {:<16}{}", kind, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Demodulate a whole recording of a band and count what's in it
+pub async fn analyse<T: AsyncRead + Unpin>(src: T, band: Band) -> RecordingStats {
+    let mut stats = RecordingStats::new(band);
+
+    match band {
+        Band::ModeS => {
+            let mut frames = FramedRead::with_capacity(
+                dsp::IQMagnitudeReader::new(src),
+                mode_s::FrameDecoder::new(),
+                RTL_SDR_BUFFER_SIZE,
+            )
+            .filter_map(|f| f.ok());
+            while let Some(frame) = frames.next().await {
+                stats.add_mode_s(&frame);
+            }
+        }
+        Band::Uat => {
+            let mut frames =
+                FramedRead::with_capacity(src, uat::FrameDecoder::new(), RTL_SDR_BUFFER_SIZE)
+                    .filter_map(|f| f.ok());
+            while let Some(frame) = frames.next().await {
+                stats.add_uat(&frame);
+            }
+        }
+    }
+
+    stats
+}
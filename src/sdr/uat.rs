@@ -0,0 +1,369 @@
+/* UAT (978 MHz) demodulator.
+ *
+ * UAT is binary CPFSK at 1.041667 Mbit/s with a modulation index of 0.6:
+ * the carrier is shifted up by 312.5 kHz for a one and down for a zero.
+ * Sampled at twice the bit rate every sample advances the phase by about
+ * +-54 degrees, so the sign of the phase difference between the two
+ * samples of a bit is the bit.
+ *
+ * Frames start with a 36 bit synchronization word, its complement marks a
+ * ground uplink instead of an ADS-B frame:
+ *
+ *   ADS-B   sync  basic (RS(30,18)) or long (RS(48,34)) ADS-B
+ *   uplink  sync  6 interleaved RS(92,72) blocks, 432 bytes of payload
+ *
+ * Basic and long ADS-B frames only differ in the payload type in their
+ * first byte, so a long frame is tried first and a basic one if that
+ * doesn't decode.
+ */
+
+use bytes::{Buf, BytesMut};
+use lazy_static::lazy_static;
+use log::*;
+use std::time::SystemTime;
+use tokio_util::codec;
+
+use super::fec::ReedSolomon;
+use crate::metrics::{Metrics, METRICS};
+
+pub const UAT_SAMPLE_RATE: u32 = 2_083_334;
+pub const UAT_CENTER_FREQ: u32 = 978_000_000;
+pub const UAT_SAMPLES_PER_BIT: usize = 2;
+
+pub const UAT_SYNC_BITS: usize = 36;
+pub const UAT_ADSB_SYNC: u64 = 0xeacdda4e2;
+pub const UAT_UPLINK_SYNC: u64 = 0x153225b1d;
+
+/* Bit errors tolerated in a sync word, the RS code catches the false
+ * positives this lets through. */
+pub const UAT_SYNC_MAX_ERRORS: u32 = 4;
+
+pub const UAT_BASIC_ADSB_BYTES: usize = 18;
+pub const UAT_BASIC_ADSB_PARITY: usize = 12;
+pub const UAT_LONG_ADSB_BYTES: usize = 34;
+pub const UAT_LONG_ADSB_PARITY: usize = 14;
+pub const UAT_UPLINK_BLOCKS: usize = 6;
+pub const UAT_UPLINK_BLOCK_BYTES: usize = 72;
+pub const UAT_UPLINK_BLOCK_PARITY: usize = 20;
+pub const UAT_UPLINK_BYTES: usize = UAT_UPLINK_BLOCKS * UAT_UPLINK_BLOCK_BYTES;
+
+/* Magnitude of a full scale IQ sample, the reference for dBFS levels. */
+pub const UAT_FULL_SCALE_MAGNITUDE: f32 = 128.0;
+
+const UAT_SYNC_MASK: u64 = (1 << UAT_SYNC_BITS) - 1;
+const UAT_SYNC_SAMPLES: usize = UAT_SYNC_BITS * UAT_SAMPLES_PER_BIT;
+const UAT_LONG_ADSB_CODED_BYTES: usize = UAT_LONG_ADSB_BYTES + UAT_LONG_ADSB_PARITY;
+const UAT_BASIC_ADSB_CODED_BYTES: usize = UAT_BASIC_ADSB_BYTES + UAT_BASIC_ADSB_PARITY;
+const UAT_UPLINK_BLOCK_CODED_BYTES: usize = UAT_UPLINK_BLOCK_BYTES + UAT_UPLINK_BLOCK_PARITY;
+const UAT_UPLINK_CODED_BYTES: usize = UAT_UPLINK_BLOCKS * UAT_UPLINK_BLOCK_CODED_BYTES;
+
+lazy_static! {
+    static ref UAT_RS_BASIC_ADSB: ReedSolomon = ReedSolomon::new(UAT_BASIC_ADSB_PARITY);
+    static ref UAT_RS_LONG_ADSB: ReedSolomon = ReedSolomon::new(UAT_LONG_ADSB_PARITY);
+    static ref UAT_RS_UPLINK: ReedSolomon = ReedSolomon::new(UAT_UPLINK_BLOCK_PARITY);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameKind {
+    BasicAdsb,
+    LongAdsb,
+    Uplink,
+}
+
+impl FrameKind {
+    /// Payload and parity as sent
+    pub fn coded_bytes(&self) -> usize {
+        match self {
+            FrameKind::BasicAdsb => UAT_BASIC_ADSB_CODED_BYTES,
+            FrameKind::LongAdsb => UAT_LONG_ADSB_CODED_BYTES,
+            FrameKind::Uplink => UAT_UPLINK_CODED_BYTES,
+        }
+    }
+
+    // bits demodulated after the sync word, ADS-B frames at the long length
+    fn demod_bits(&self) -> usize {
+        match self {
+            FrameKind::BasicAdsb | FrameKind::LongAdsb => UAT_LONG_ADSB_CODED_BYTES * 8,
+            FrameKind::Uplink => UAT_UPLINK_CODED_BYTES * 8,
+        }
+    }
+
+    fn sync_word(&self) -> u64 {
+        match self {
+            FrameKind::BasicAdsb | FrameKind::LongAdsb => UAT_ADSB_SYNC,
+            FrameKind::Uplink => UAT_UPLINK_SYNC,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Frame {
+    kind: FrameKind,
+    bytes: Vec<u8>,
+
+    sample_index: u64,
+    received_at: SystemTime,
+    signal_level: f32,
+    corrected: usize,
+}
+
+pub struct FrameDecoder {
+    // absolute index of the first sample in the decode buffer
+    position: u64,
+    // samples at the start of the buffer already shifted into the sync registers
+    scanned: usize,
+    // last 36 bits demodulated at even and odd sample positions
+    sync: [u64; 2],
+    // sync word found, ending with the bit at this sample of the buffer
+    candidate: Option<(FrameKind, usize)>,
+}
+
+/* Centered IQ sample n of a buffer of interleaved u8 I and Q */
+fn iq(src: &[u8], n: usize) -> (f32, f32) {
+    (src[2 * n] as f32 - 127.5, src[2 * n + 1] as f32 - 127.5)
+}
+
+/* Phase change in radians from sample n to n + 1 */
+fn phase_delta(src: &[u8], n: usize) -> f32 {
+    let (i0, q0) = iq(src, n);
+    let (i1, q1) = iq(src, n + 1);
+    (i0 * q1 - q0 * i1).atan2(i0 * i1 + q0 * q1)
+}
+
+impl Default for FrameDecoder {
+    fn default() -> FrameDecoder {
+        FrameDecoder::new()
+    }
+}
+
+impl FrameDecoder {
+    pub fn new() -> FrameDecoder {
+        FrameDecoder {
+            position: 0,
+            scanned: 0,
+            sync: [0, 0],
+            candidate: None,
+        }
+    }
+
+    fn consume(&mut self, src: &mut BytesMut, samples: usize) {
+        src.advance(samples * 2);
+        self.position += samples as u64;
+        Metrics::add(&METRICS.uat_samples, samples as u64);
+        self.scanned = self.scanned.saturating_sub(samples);
+    }
+
+    /* Demodulate and error correct the frame following a sync word ending
+     * at sample `end`. The decision threshold is the middle of the phase
+     * changes seen for ones and zeros of the sync word, which takes out
+     * the frequency offset of the receiver. */
+    fn demodulate(src: &[u8], kind: FrameKind, end: usize, position: u64) -> Option<Frame> {
+        let sync_start = end + UAT_SAMPLES_PER_BIT - UAT_SYNC_SAMPLES;
+        let (mut ones, mut zeros) = ((0.0, 0), (0.0, 0));
+        for bit in 0..UAT_SYNC_BITS {
+            let delta = phase_delta(src, sync_start + bit * UAT_SAMPLES_PER_BIT);
+            match (kind.sync_word() >> (UAT_SYNC_BITS - 1 - bit)) & 1 {
+                1 => ones = (ones.0 + delta, ones.1 + 1),
+                _ => zeros = (zeros.0 + delta, zeros.1 + 1),
+            }
+        }
+        let center = (ones.0 / ones.1 as f32 + zeros.0 / zeros.1 as f32) / 2.0;
+
+        let start = end + UAT_SAMPLES_PER_BIT;
+        let bits = kind.demod_bits();
+        let mut coded = vec![0u8; bits / 8];
+        for bit in 0..bits {
+            if phase_delta(src, start + bit * UAT_SAMPLES_PER_BIT) > center {
+                coded[bit / 8] |= 0x80 >> (bit % 8);
+            }
+        }
+
+        let (kind, bytes, corrected) = match kind {
+            FrameKind::Uplink => {
+                let (bytes, corrected) = FrameDecoder::decode_uplink(&coded)?;
+                (FrameKind::Uplink, bytes, corrected)
+            }
+            _ => FrameDecoder::decode_adsb(&mut coded)?,
+        };
+
+        let samples = (UAT_SYNC_BITS + kind.coded_bytes() * 8) * UAT_SAMPLES_PER_BIT;
+        let power = (sync_start..sync_start + samples)
+            .map(|n| {
+                let (i, q) = iq(src, n);
+                i * i + q * q
+            })
+            .sum::<f32>()
+            / samples as f32;
+
+        Some(Frame {
+            kind,
+            bytes,
+            sample_index: position + sync_start as u64,
+            received_at: SystemTime::now(),
+            signal_level: 10.0
+                * (power / (UAT_FULL_SCALE_MAGNITUDE * UAT_FULL_SCALE_MAGNITUDE)).log10(),
+            corrected,
+        })
+    }
+
+    /* Payload type 0 is the only basic frame, every other one is long */
+    fn decode_adsb(coded: &mut [u8]) -> Option<(FrameKind, Vec<u8>, usize)> {
+        let mut long = coded[..UAT_LONG_ADSB_CODED_BYTES].to_vec();
+        if let Some(corrected) = UAT_RS_LONG_ADSB.decode(&mut long) {
+            if long[0] >> 3 != 0 {
+                long.truncate(UAT_LONG_ADSB_BYTES);
+                return Some((FrameKind::LongAdsb, long, corrected));
+            }
+        }
+
+        let basic = &mut coded[..UAT_BASIC_ADSB_CODED_BYTES];
+        let corrected = UAT_RS_BASIC_ADSB.decode(basic)?;
+        if basic[0] >> 3 != 0 {
+            return None;
+        }
+        Some((
+            FrameKind::BasicAdsb,
+            basic[..UAT_BASIC_ADSB_BYTES].to_vec(),
+            corrected,
+        ))
+    }
+
+    /* The uplink blocks are interleaved byte by byte so a burst of errors
+     * is spread over all six of them. */
+    fn decode_uplink(coded: &[u8]) -> Option<(Vec<u8>, usize)> {
+        let mut payload = Vec::with_capacity(UAT_UPLINK_BYTES);
+        let mut corrected = 0;
+        for block in 0..UAT_UPLINK_BLOCKS {
+            let mut codeword: Vec<u8> = coded
+                .iter()
+                .skip(block)
+                .step_by(UAT_UPLINK_BLOCKS)
+                .cloned()
+                .collect();
+            corrected += UAT_RS_UPLINK.decode(&mut codeword)?;
+            payload.extend_from_slice(&codeword[..UAT_UPLINK_BLOCK_BYTES]);
+        }
+        Some((payload, corrected))
+    }
+}
+
+impl codec::Decoder for FrameDecoder {
+    type Item = Frame;
+    type Error = std::io::Error;
+
+    /* Every sample shifts the bit it starts into the sync register of its
+     * parity, so both ways of pairing samples into bits are searched at
+     * once. A frame that decodes consumes everything up to its end, a sync
+     * word that doesn't lead to one is just scanned past. */
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some((kind, end)) = self.candidate {
+                let frame_end = end + (kind.demod_bits() + 1) * UAT_SAMPLES_PER_BIT;
+                if src.len() < (frame_end + 1) * 2 {
+                    // Not enough data
+                    return Ok(None);
+                }
+                self.candidate = None;
+
+                if let Some(frame) = FrameDecoder::demodulate(src, kind, end, self.position) {
+                    debug!(
+                        "read uat frame: {} ({} corrected, {:.1} dBFS)",
+                        frame, frame.corrected, frame.signal_level
+                    );
+                    METRICS.uat_message(frame.kind);
+                    if frame.corrected > 0 {
+                        Metrics::inc(&METRICS.uat_corrected);
+                    }
+                    let consumed = end + (frame.kind.coded_bytes() * 8 + 1) * UAT_SAMPLES_PER_BIT;
+                    self.consume(src, consumed);
+                    self.sync = [0, 0];
+                    return Ok(Some(frame));
+                }
+            }
+
+            let n = self.scanned;
+            if src.len() < (n + 2) * 2 {
+                // keep the sync word of a frame that may start at the end of the buffer
+                if n > UAT_SYNC_SAMPLES {
+                    self.consume(src, n - UAT_SYNC_SAMPLES);
+                }
+                return Ok(None);
+            }
+
+            let bit = (phase_delta(src, n) > 0.0) as u64;
+            let register = &mut self.sync[((self.position + n as u64) & 1) as usize];
+            *register = (*register << 1 | bit) & UAT_SYNC_MASK;
+            let register = *register;
+            self.scanned += 1;
+
+            if n + UAT_SAMPLES_PER_BIT < UAT_SYNC_SAMPLES {
+                // not a full sync word in the register yet
+                continue;
+            }
+            if (register ^ UAT_ADSB_SYNC).count_ones() <= UAT_SYNC_MAX_ERRORS {
+                self.candidate = Some((FrameKind::LongAdsb, n));
+                Metrics::inc(&METRICS.uat_syncs);
+            } else if (register ^ UAT_UPLINK_SYNC).count_ones() <= UAT_SYNC_MAX_ERRORS {
+                self.candidate = Some((FrameKind::Uplink, n));
+                Metrics::inc(&METRICS.uat_syncs);
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
+        }
+
+        // whatever is left is too short to hold a frame
+        let remaining = src.len();
+        src.advance(remaining);
+        self.position += (remaining / 2) as u64;
+        Metrics::add(&METRICS.uat_samples, (remaining / 2) as u64);
+        self.scanned = 0;
+        self.candidate = None;
+        Ok(None)
+    }
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:?},{})", self.kind, hex::encode(&self.bytes))
+    }
+}
+
+impl Frame {
+    pub fn kind(&self) -> FrameKind {
+        self.kind
+    }
+
+    /// Payload after error correction, without the parity
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// True for basic and long ADS-B frames, false for ground uplinks
+    pub fn is_adsb(&self) -> bool {
+        self.kind != FrameKind::Uplink
+    }
+
+    /// Absolute index of the first sync word sample since the decoder started
+    pub fn sample_index(&self) -> u64 {
+        self.sample_index
+    }
+
+    /// System time at which the frame was demodulated
+    pub fn received_at(&self) -> SystemTime {
+        self.received_at
+    }
+
+    /// Signal level (RSSI) in dBFS
+    pub fn signal_level(&self) -> f32 {
+        self.signal_level
+    }
+
+    /// Number of bytes corrected by the Reed-Solomon decoder
+    pub fn corrected(&self) -> usize {
+        self.corrected
+    }
+}
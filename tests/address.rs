@@ -0,0 +1,40 @@
+use fishfinder::adsb::{Address, AddressFamily, AddressType};
+
+const TEST_ADDRESS: u32 = 0xa1b2c3;
+
+#[test]
+fn icao_addresses_share_one_key_however_they_were_received() {
+    let icao = Address::new(TEST_ADDRESS, AddressType::Icao);
+
+    for &address_type in &[AddressType::AdsR, AddressType::TisB] {
+        assert_eq!(Address::new(TEST_ADDRESS, address_type), icao);
+    }
+    assert!(icao.is_icao());
+    assert_eq!(icao.to_string(), "a1b2c3");
+}
+
+#[test]
+fn non_icao_addresses_are_kept_apart_by_family() {
+    let self_assigned = Address::new(TEST_ADDRESS, AddressType::NonIcao);
+    let track_file = Address::new(TEST_ADDRESS, AddressType::TisBOther);
+    let anonymous = Address::new(TEST_ADDRESS, AddressType::Anonymous);
+
+    // an ADS-B device without an ICAO address keeps it when relayed by ADS-R
+    assert_eq!(
+        Address::new(TEST_ADDRESS, AddressType::AdsROther),
+        self_assigned
+    );
+    assert_eq!(self_assigned.family, AddressFamily::AdsBOther);
+    assert_eq!(track_file.family, AddressFamily::TisBTrackFile);
+    assert_eq!(anonymous.family, AddressFamily::Anonymous);
+
+    let icao = Address::new(TEST_ADDRESS, AddressType::Icao);
+    let keys = [icao, self_assigned, track_file, anonymous];
+    for (i, a) in keys.iter().enumerate() {
+        for b in &keys[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+    assert!(!track_file.is_icao());
+    assert_eq!(track_file.to_string(), "~a1b2c3");
+}
@@ -0,0 +1,89 @@
+use fishfinder::ahrs::{Ahrs, Attitude, ImuSample, Replay};
+use fishfinder::gdl90::ahrs as gdl90_ahrs;
+use std::time::Duration;
+
+const SAMPLE_IMU_LOG: &str = "samples/imu.csv";
+// speed of a coordinated standard rate turn at 25° of bank
+const SAMPLE_GROUND_SPEED: f64 = 170.0;
+
+#[test]
+fn level_and_still() {
+    let mut ahrs = Ahrs::new();
+    for i in 0..100 {
+        ahrs.update(
+            &ImuSample {
+                at: Duration::from_millis(i * 20),
+                accel: [0.0, 0.0, 1.0],
+                gyro: [0.0; 3],
+                mag: None,
+            },
+            None,
+        );
+    }
+
+    let attitude = ahrs.attitude();
+    assert!(attitude.roll.abs() < 0.1, "{:?}", attitude);
+    assert!(attitude.pitch.abs() < 0.1, "{:?}", attitude);
+    assert!(attitude.turn_rate.abs() < 0.01, "{:?}", attitude);
+    assert!((attitude.g_load - 1.0).abs() < 0.01, "{:?}", attitude);
+    assert_eq!(attitude.heading, None);
+}
+
+#[test]
+fn nose_up_from_accelerometer() {
+    let pitch = 10f64.to_radians();
+    let mut ahrs = Ahrs::new();
+    let attitude = ahrs.update(
+        &ImuSample {
+            at: Duration::from_secs(0),
+            accel: [pitch.sin(), 0.0, pitch.cos()],
+            gyro: [0.0; 3],
+            mag: None,
+        },
+        None,
+    );
+    assert!((attitude.pitch - 10.0).abs() < 0.1, "{:?}", attitude);
+    assert!(attitude.roll.abs() < 0.1, "{:?}", attitude);
+}
+
+#[test]
+fn replayed_standard_rate_turn() {
+    let mut ahrs = Ahrs::new();
+    for sample in Replay::from_file(SAMPLE_IMU_LOG).unwrap() {
+        ahrs.update(&sample, Some(SAMPLE_GROUND_SPEED));
+    }
+
+    let attitude = ahrs.attitude();
+    assert!((attitude.roll - 25.0).abs() < 1.0, "{:?}", attitude);
+    assert!(attitude.pitch.abs() < 1.0, "{:?}", attitude);
+    assert!((attitude.turn_rate - 3.0).abs() < 0.1, "{:?}", attitude);
+    assert!((attitude.g_load - 1.103).abs() < 0.01, "{:?}", attitude);
+    assert!(attitude.slip_skid.abs() < 0.1, "{:?}", attitude);
+    // 10 s at 3°/s from 090
+    let heading = attitude.heading.unwrap();
+    assert!((heading - 120.0).abs() < 2.0, "{:?}", attitude);
+}
+
+#[test]
+fn foreflight_and_stratux_ahrs_messages() {
+    let attitude = Attitude {
+        roll: -12.3,
+        pitch: 4.5,
+        heading: Some(270.0),
+        turn_rate: -1.5,
+        g_load: 1.2,
+        slip_skid: 0.0,
+        last_update: None,
+    };
+
+    assert_eq!(
+        gdl90_ahrs::foreflight(&attitude),
+        vec![0x65, 0x01, 0xff, 0x85, 0x00, 0x2d, 0x8a, 0x8c, 0xff, 0xff, 0xff, 0xff]
+    );
+
+    let stratux = gdl90_ahrs::stratux(&attitude, Some(4500.0));
+    assert_eq!(stratux.len(), 24);
+    assert_eq!(&stratux[..4], &[0x4c, 0x45, 0x01, 0x01]);
+    assert_eq!(&stratux[12..16], &[0xff, 0xf1, 0x00, 0x0c]);
+    assert_eq!(&stratux[18..20], &9500u16.to_be_bytes());
+}
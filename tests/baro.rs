@@ -0,0 +1,76 @@
+use fishfinder::baro::{self, bmp280, Barometer, Reading, Replay};
+use fishfinder::gps::Ownship;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::sync::watch;
+
+const SAMPLE_BARO_LOG: &str = "samples/baro.csv";
+
+#[test]
+fn bmp280_compensation_matches_datasheet_example() {
+    let calibration = bmp280::Calibration {
+        t1: 27504,
+        t2: 26435,
+        t3: -1000,
+        p1: 36477,
+        p2: -10685,
+        p3: 3024,
+        p4: 2855,
+        p5: 140,
+        p6: -7,
+        p7: 15500,
+        p8: -14600,
+        p9: 6000,
+    };
+
+    let reading = calibration.compensate(519888, 415148).unwrap();
+    assert!((reading.temperature - 25.08).abs() < 0.01, "{:?}", reading);
+    assert!((reading.pressure - 100653.27).abs() < 0.1, "{:?}", reading);
+}
+
+#[test]
+fn pressure_altitude_follows_standard_atmosphere() {
+    assert!(baro::pressure_altitude(baro::STANDARD_PRESSURE).abs() < 0.01);
+    assert!((baro::pressure_altitude(84_307.0) - 5000.0).abs() < 5.0);
+    assert!((baro::pressure_altitude(69_682.0) - 10000.0).abs() < 5.0);
+}
+
+#[test]
+fn replay_skips_comments_and_runs_out() {
+    let mut replay = Replay::from_file(SAMPLE_BARO_LOG).unwrap();
+    let first = replay.read().unwrap();
+    assert_eq!(first.pressure, 84_310.0);
+    assert_eq!(first.temperature, 5.2);
+    assert!(replay.read().is_ok());
+    assert!(replay.read().is_ok());
+    assert!(replay.read().is_err());
+
+    assert!(Replay::parse("1013.25\n").is_err());
+}
+
+#[tokio::test]
+async fn baro_feeds_ownship_pressure_altitude() {
+    let (tx, rx) = watch::channel(Ownship::default());
+    let sensor = Box::new(Replay::from_file(SAMPLE_BARO_LOG).unwrap());
+    baro::run(sensor, Arc::new(tx)).await.unwrap();
+
+    let ownship = rx.borrow();
+    let altitude = ownship.baro_altitude(SystemTime::now()).unwrap();
+    assert!((altitude - 5004.0).abs() < 5.0, "{}", altitude);
+    assert_eq!(
+        ownship.reference_altitude(SystemTime::now()),
+        Some(altitude)
+    );
+}
+
+#[test]
+fn constant_sensor_never_runs_out() {
+    let reading = Reading {
+        pressure: baro::STANDARD_PRESSURE,
+        temperature: 15.0,
+    };
+    let mut sensor = Replay::constant(reading);
+    for _ in 0..3 {
+        assert_eq!(sensor.read().unwrap(), reading);
+    }
+}
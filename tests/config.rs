@@ -0,0 +1,83 @@
+use fishfinder::config::Config;
+use fishfinder::geo::METERS_PER_NM;
+use fishfinder::metrics;
+use fishfinder::sdr::input::Band;
+use fishfinder::sdr::repair::RepairPolicy;
+use log::LevelFilter;
+use std::time::Duration;
+
+const SAMPLE_CONFIG: &str = "samples/fishfinder.toml";
+
+fn error(text: &str) -> String {
+    match Config::from_toml(text).and_then(|config| config.validate()) {
+        Ok(_) => panic!("accepted {:?}", text),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn sample_config_covers_the_stack() {
+    let config = Config::load(SAMPLE_CONFIG).unwrap();
+    config.validate().unwrap();
+
+    assert_eq!(config.log_level, Some(LevelFilter::Info));
+    let bands: Vec<Band> = config.radios.iter().map(|r| r.band).collect();
+    assert_eq!(bands, vec![Band::ModeS, Band::Uat]);
+    assert_eq!(config.radios[1].serial.as_deref(), Some("00000978"));
+    assert_eq!(config.radios[1].ppm, -2);
+    assert_eq!(config.repair_policy(), RepairPolicy::TwoBitKnownIcao);
+    assert_eq!(config.sensors.gps_baud, Some(115200));
+    assert_eq!(config.outputs.gdl90.len(), 2);
+    assert_eq!(config.outputs.beast, Some(30005));
+    assert_eq!(config.outputs.metrics, Some(9100));
+    assert_eq!(config.summary_interval(), Some(Duration::from_secs(300)));
+
+    let tracker = config.tracker_config();
+    let receiver = tracker.receiver.unwrap();
+    assert!((receiver.position.latitude - 47.45).abs() < 1e-9);
+    assert_eq!(receiver.altitude, Some(430));
+    assert!((tracker.max_range - 250.0 * METERS_PER_NM).abs() < 1e-6);
+    assert_eq!(tracker.ownship_icao, Some(0xa1b2c3));
+    assert_eq!(tracker.aircraft_timeout, Duration::from_secs(90));
+    assert_eq!(tracker.address_type_timeout, Duration::from_secs(20));
+}
+
+#[test]
+fn empty_config_is_the_default() {
+    let config = Config::from_toml("").unwrap();
+    config.validate().unwrap();
+    assert_eq!(config, Config::default());
+    assert_eq!(config.repair_policy(), RepairPolicy::SingleBit);
+    assert!(config.tracker_config().receiver.is_none());
+    assert_eq!(
+        config.summary_interval(),
+        Some(metrics::METRICS_SUMMARY_INTERVAL)
+    );
+
+    let quiet = Config::from_toml("summary_interval = 0\n").unwrap();
+    assert_eq!(quiet.summary_interval(), None);
+}
+
+#[test]
+fn invalid_configs_are_explained() {
+    assert!(error("[receiver]\nlatitude = 47.45\n").contains("latitude and longitude"));
+    assert!(error("[receiver]\nlatitude = 147\nlongitude = 1\n").contains("latitude 147"));
+    assert!(error("[tracker]\naircraft_timeout = 0\n").contains("aircraft_timeout"));
+    assert!(error("[tracker]\nrepair = \"3bit\"\n").contains("unknown repair policy"));
+    assert!(error("[tracker]\nownship = \"xyz\"\n").contains("ICAO address"));
+    assert!(error("[[radio]]\nband = \"1030\"\n").contains("unknown band"));
+    assert!(error(
+        "[[radio]]\nband = \"1090\"\nserial = \"1\"\n[[radio]]\nband = \"978\"\nserial = \"1\"\n"
+    )
+    .contains("serial 1 used twice"));
+    assert!(error("[outputs]\nbeast = 30005\nsbs = 30005\n").contains("beast and sbs"));
+    assert!(error("[outputs]\njson = 8080\nmetrics = 8080\n").contains("json and metrics"));
+    assert!(
+        error("[outputs]\ngdl90 = [\"192.168.1.255:4000\"]\nsbs = 4000\n")
+            .contains("gdl90 and sbs")
+    );
+    assert!(error("[outputs]\nbeast = 0\n").contains("beast port can't be 0"));
+    assert!(error("[outputs]\nmetrics = 0\n").contains("metrics port can't be 0"));
+    assert!(error("[reciever]\nlatitude = 1\n").contains("unknown field"));
+    assert!(error("log_level = \"loud\"\n").contains("log_level"));
+}
@@ -0,0 +1,120 @@
+use fishfinder::adsb::cpr::{self, CprFrame, CPR_MAX};
+
+// the latitude where the number of longitude zones drops from 59 to 58
+const NL_58_BOUNDARY: f64 = 10.470_471_30;
+
+/* Airborne CPR encoding, the inverse of what the decoder does */
+fn encode(lat: f64, lon: f64, odd: bool) -> CprFrame {
+    let i = if odd { 1.0 } else { 0.0 };
+    let dlat = 360.0 / (60.0 - i);
+    let yz = (CPR_MAX * lat.rem_euclid(dlat) / dlat + 0.5).floor();
+    let rlat = dlat * (yz / CPR_MAX + (lat / dlat).floor());
+    let dlon = 360.0 / (cpr::nl(rlat) as f64 - i).max(1.0);
+    let xz = (CPR_MAX * lon.rem_euclid(dlon) / dlon + 0.5).floor();
+
+    CprFrame {
+        lat: yz as u32 % 131072,
+        lon: xz as u32 % 131072,
+        odd,
+    }
+}
+
+fn assert_near(actual: (f64, f64), expected: (f64, f64)) {
+    assert!(
+        (actual.0 - expected.0).abs() < 0.0002 && (actual.1 - expected.1).abs() < 0.0002,
+        "{:?} is not {:?}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn number_of_longitude_zones() {
+    assert_eq!(cpr::nl(0.0), 59);
+    assert_eq!(cpr::nl(NL_58_BOUNDARY - 0.001), 59);
+    assert_eq!(cpr::nl(NL_58_BOUNDARY + 0.001), 58);
+    assert_eq!(cpr::nl(-52.0), 36);
+    assert_eq!(cpr::nl(87.0), 2);
+    assert_eq!(cpr::nl(89.0), 1);
+}
+
+#[test]
+fn global_decode_of_known_pair() {
+    let even = CprFrame {
+        lat: 93000,
+        lon: 51372,
+        odd: false,
+    };
+    let odd = CprFrame {
+        lat: 74158,
+        lon: 50194,
+        odd: true,
+    };
+
+    assert_near(
+        cpr::global_airborne(&even, &odd, false).unwrap(),
+        (52.25720, 3.91937),
+    );
+    assert_near(
+        cpr::global_airborne(&even, &odd, true).unwrap(),
+        (52.26578, 3.93891),
+    );
+}
+
+#[test]
+fn global_decode_round_trips() {
+    for &(lat, lon) in &[
+        (47.3612, 8.5509),
+        (-33.9461, 151.1772),
+        (40.6413, -73.7781),
+        (-54.8019, -68.3030),
+        (0.05, 179.99),
+    ] {
+        let even = encode(lat, lon, false);
+        let odd = encode(lat, lon, true);
+        assert_near(cpr::global_airborne(&even, &odd, true).unwrap(), (lat, lon));
+        assert_near(
+            cpr::global_airborne(&even, &odd, false).unwrap(),
+            (lat, lon),
+        );
+    }
+}
+
+#[test]
+fn pair_across_a_zone_boundary_is_rejected() {
+    // the aircraft crossed from 59 into 58 longitude zones between the frames
+    let even = encode(NL_58_BOUNDARY - 0.002, 20.0, false);
+    let odd = encode(NL_58_BOUNDARY + 0.002, 20.0, true);
+
+    assert_eq!(cpr::global_airborne(&even, &odd, true), None);
+    assert_eq!(cpr::global_airborne(&even, &odd, false), None);
+}
+
+#[test]
+fn local_decode_relative_to_reference() {
+    let even = CprFrame {
+        lat: 93000,
+        lon: 51372,
+        odd: false,
+    };
+
+    assert_near(
+        cpr::local(&even, (52.258, 3.918), false),
+        (52.25720, 3.91937),
+    );
+    // anywhere within half a zone of the aircraft works
+    assert_near(cpr::local(&even, (54.5, 1.0), false), (52.25720, 3.91937));
+}
+
+#[test]
+fn local_decode_round_trips_in_both_hemispheres() {
+    for &(lat, lon) in &[(47.3612, 8.5509), (-33.9461, 151.1772), (40.6413, -73.7781)] {
+        for &odd in &[false, true] {
+            let frame = encode(lat, lon, odd);
+            assert_near(
+                cpr::local(&frame, (lat + 1.0, lon - 1.0), false),
+                (lat, lon),
+            );
+        }
+    }
+}
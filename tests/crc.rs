@@ -0,0 +1,164 @@
+use fishfinder::sdr::crc::{self, MODES_GENERATOR_POLY, MODES_PARITY_BITS};
+use std::time::Instant;
+
+/* Valid frames: the DF17 identification example from "The 1090MHz Riddle"
+ * and a DF17 airborne position and DF11 all-call reply from
+ * samples/modes1.bin */
+const VALID_FRAMES: [&str; 3] = [
+    "8d4840d6202cc371c32ce0576098",
+    "8f4d2023587f345e35837e2218b2",
+    "5d4d20237a55a6",
+];
+
+/* CRC-24 one bit at a time, straight from the generator polynomial */
+fn bitwise_crc(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &byte in data {
+        for bit in (0..8).rev() {
+            let input = ((byte >> bit) & 1) as u32;
+            let top = (crc >> 23) & 1;
+            crc = (crc << 1) & 0xffffff;
+            if top ^ input == 1 {
+                crc ^= MODES_GENERATOR_POLY;
+            }
+        }
+    }
+    crc
+}
+
+fn frames() -> Vec<Vec<u8>> {
+    VALID_FRAMES
+        .iter()
+        .map(|f| hex::decode(f).unwrap())
+        .collect()
+}
+
+fn flip(frame: &mut [u8], bit: usize) {
+    frame[bit / 8] ^= 0x80 >> (bit % 8);
+}
+
+fn data_bits(frame: &[u8]) -> usize {
+    frame.len() * 8 - MODES_PARITY_BITS
+}
+
+#[test]
+fn table_crc_matches_bitwise_reference() {
+    for frame in frames() {
+        let data = &frame[..frame.len() - 3];
+        assert_eq!(crc::modes_crc(data), bitwise_crc(data));
+        assert_eq!(crc::modes_crc(data), crc::modes_parity(&frame));
+        assert_eq!(crc::modes_syndrome(&frame), 0);
+    }
+
+    // and on something that isn't a frame
+    let noise: Vec<u8> = (0..=255u8).map(|b| b.wrapping_mul(37) ^ 0x5a).collect();
+    for len in 0..noise.len() {
+        assert_eq!(crc::modes_crc(&noise[..len]), bitwise_crc(&noise[..len]));
+    }
+}
+
+#[test]
+fn single_bit_errors_in_data_are_fixed() {
+    for frame in frames() {
+        for bit in 0..data_bits(&frame) {
+            let mut damaged = frame.clone();
+            flip(&mut damaged, bit);
+
+            assert_eq!(crc::modes_correct(&mut damaged, 1), Some(vec![bit]));
+            assert_eq!(damaged, frame);
+        }
+    }
+}
+
+#[test]
+fn double_bit_errors_in_data_are_fixed() {
+    for frame in frames() {
+        let bits = data_bits(&frame);
+        for i in 0..bits {
+            for j in (i + 1)..bits {
+                let mut damaged = frame.clone();
+                flip(&mut damaged, i);
+                flip(&mut damaged, j);
+
+                assert_eq!(crc::modes_correct(&mut damaged.clone(), 1), None);
+                assert_eq!(crc::modes_correct(&mut damaged, 2), Some(vec![i, j]));
+                assert_eq!(damaged, frame);
+            }
+        }
+    }
+}
+
+#[test]
+fn errors_in_parity_only_are_not_fixed() {
+    for frame in frames() {
+        let bits = frame.len() * 8;
+        for i in data_bits(&frame)..bits {
+            let mut damaged = frame.clone();
+            flip(&mut damaged, i);
+            assert_eq!(
+                crc::modes_correct(&mut damaged.clone(), 2),
+                None,
+                "bit {}",
+                i
+            );
+
+            for j in (i + 1)..bits {
+                let mut damaged = damaged.clone();
+                flip(&mut damaged, j);
+                assert_eq!(
+                    crc::modes_correct(&mut damaged, 2),
+                    None,
+                    "bits {} {}",
+                    i,
+                    j
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn hints_extend_single_bit_correction() {
+    let frame = frames().remove(0);
+    let mut damaged = frame.clone();
+    flip(&mut damaged, 40);
+    flip(&mut damaged, 70);
+
+    // one of the two errors is a weak bit, that one may come on top of the single bit
+    let corrected = crc::modes_correct_with_hints(&mut damaged.clone(), &[12, 70], 1);
+    assert_eq!(corrected, Some(vec![40, 70]));
+    // neither is, a single bit correction doesn't fix it
+    assert_eq!(
+        crc::modes_correct_with_hints(&mut damaged.clone(), &[12], 1),
+        None
+    );
+    assert_eq!(crc::modes_correct(&mut damaged, 1), None);
+}
+
+/* cargo test --release --test crc -- --ignored --nocapture */
+#[test]
+#[ignore]
+fn table_crc_is_faster_than_bitwise() {
+    let frame = frames().remove(0);
+    let data = &frame[..frame.len() - 3];
+    let rounds = 1_000_000;
+
+    let time = |f: &dyn Fn(&[u8]) -> u32| {
+        let started = Instant::now();
+        let mut sum = 0u32;
+        for i in 0..rounds {
+            sum = sum.wrapping_add(f(&data[..data.len() - (i & 1)]));
+        }
+        (started.elapsed(), sum)
+    };
+    let (table, a) = time(&crc::modes_crc);
+    let (bitwise, b) = time(&bitwise_crc);
+    assert_eq!(a, b);
+
+    let speedup = bitwise.as_secs_f64() / table.as_secs_f64();
+    println!(
+        "{} CRCs: table {:?}, bitwise {:?}, {:.1}x",
+        rounds, table, bitwise, speedup
+    );
+    assert!(speedup > 2.0);
+}
@@ -0,0 +1,105 @@
+use fishfinder::adsb::es::{self, EmergencyState, EsMessage};
+
+/* The ME field of a 112 bit extended squitter given in hex */
+fn me(hex: &str) -> Vec<u8> {
+    (8..22)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn identification() {
+    match es::decode(&me("8D4840D6202CC371C32CE0576098")) {
+        EsMessage::Identification { category, callsign } => {
+            assert_eq!(category, 0xa0);
+            assert_eq!(callsign, "KLM1023");
+        }
+        m => panic!("unexpected {:?}", m),
+    }
+}
+
+#[test]
+fn airborne_position() {
+    match es::decode(&me("8D40621D58C382D690C8AC2863A7")) {
+        EsMessage::AirbornePosition {
+            type_code,
+            altitude,
+            gnss,
+            cpr,
+            ..
+        } => {
+            assert_eq!(type_code, 11);
+            assert_eq!(altitude, Some(38000));
+            assert!(!gnss);
+            assert!(!cpr.odd);
+            assert_eq!(cpr.lat, 93000);
+            assert_eq!(cpr.lon, 51372);
+        }
+        m => panic!("unexpected {:?}", m),
+    }
+
+    match es::decode(&me("8D40621D58C386435CC412692AD6")) {
+        EsMessage::AirbornePosition { cpr, .. } => {
+            assert!(cpr.odd);
+            assert_eq!(cpr.lat, 74158);
+            assert_eq!(cpr.lon, 50194);
+        }
+        m => panic!("unexpected {:?}", m),
+    }
+}
+
+#[test]
+fn ground_speed_velocity() {
+    match es::decode(&me("8D485020994409940838175B284F")) {
+        EsMessage::AirborneVelocity(v) => {
+            assert!((v.ground_speed.unwrap() - 159.2).abs() < 0.1);
+            assert!((v.track.unwrap() - 182.88).abs() < 0.01);
+            assert_eq!(v.vertical_rate, Some(-832));
+            assert!(v.vertical_rate_is_gnss);
+            assert_eq!(v.gnss_baro_diff, Some(550));
+            assert_eq!(v.heading, None);
+        }
+        m => panic!("unexpected {:?}", m),
+    }
+}
+
+#[test]
+fn airspeed_velocity() {
+    match es::decode(&me("8DA05F219B06B6AF189400CBC33F")) {
+        EsMessage::AirborneVelocity(v) => {
+            assert!((v.heading.unwrap() - 243.98).abs() < 0.01);
+            assert_eq!(v.airspeed, Some(375));
+            assert!(v.airspeed_is_true);
+            assert_eq!(v.vertical_rate, Some(-2304));
+            assert!(!v.vertical_rate_is_gnss);
+            assert_eq!(v.ground_speed, None);
+        }
+        m => panic!("unexpected {:?}", m),
+    }
+}
+
+#[test]
+fn emergency_status() {
+    // TC 28 subtype 1, emergency 5 and squawk 7500 (A=7, B=5)
+    let mut me = [0u8; 7];
+    me[0] = 28 << 3 | 1;
+    me[1] = 5 << 5 | 0x0a;
+    me[2] = 0xa2;
+
+    match es::decode(&me) {
+        EsMessage::AircraftStatus { emergency, squawk } => {
+            assert_eq!(emergency, EmergencyState::UnlawfulInterference);
+            assert_eq!(squawk, 0x7500);
+        }
+        m => panic!("unexpected {:?}", m),
+    }
+}
+
+#[test]
+fn unknown_type_code() {
+    assert_eq!(
+        es::decode(&[0, 0, 0, 0, 0, 0, 0]),
+        EsMessage::Unknown { type_code: 0 }
+    );
+}
@@ -0,0 +1,103 @@
+use fishfinder::sdr::crc;
+use fishfinder::sdr::filter::{IcaoFilter, ICAO_FILTER_TTL};
+use fishfinder::sdr::mode_s::Frame;
+use std::time::SystemTime;
+
+const TEST_ADDRESS: u32 = 0xa1b2c3;
+
+/* An extended squitter of the given DF and CF/CA with a valid parity */
+fn squitter(df: u8, cf: u8, address: u32) -> Frame {
+    let mut bytes = vec![
+        df << 3 | cf,
+        (address >> 16) as u8,
+        (address >> 8) as u8,
+        address as u8,
+        0x58,
+        0x7f,
+        0x34,
+        0x5e,
+        0x35,
+        0x83,
+        0x7e,
+    ];
+    let parity = crc::modes_crc(&bytes);
+    bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+
+    Frame::from_bytes(&bytes, SystemTime::now())
+}
+
+/* A DF11 all-call reply (CA 5) to an interrogator with code `ic` */
+fn all_call(address: u32, ic: u32) -> Frame {
+    let mut bytes = vec![
+        11 << 3 | 5,
+        (address >> 16) as u8,
+        (address >> 8) as u8,
+        address as u8,
+    ];
+    let parity = crc::modes_crc(&bytes) ^ ic;
+    bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+
+    Frame::from_bytes(&bytes, SystemTime::now())
+}
+
+#[test]
+fn all_call_replies_are_learned_whatever_the_interrogator_code() {
+    let mut filter = IcaoFilter::new(ICAO_FILTER_TTL);
+    assert!(filter.is_empty());
+
+    for &ic in &[0, 5, 0x7f] {
+        let frame = all_call(TEST_ADDRESS + ic, ic);
+        assert_eq!(frame.bytes().len(), 7);
+        assert!(filter.accept(&frame), "II/SI {} rejected", ic);
+        assert!(filter.contains(TEST_ADDRESS + ic, frame.received_at()));
+    }
+    assert_eq!(filter.len(), 3);
+}
+
+#[test]
+fn valid_df18_is_accepted() {
+    let mut filter = IcaoFilter::new(ICAO_FILTER_TTL);
+
+    for cf in 0..8 {
+        let frame = squitter(18, cf, TEST_ADDRESS + cf as u32);
+        assert!(frame.valid());
+        assert!(filter.accept(&frame), "DF18 CF{} rejected", cf);
+    }
+}
+
+#[test]
+fn only_icao_df18_addresses_become_known() {
+    let mut filter = IcaoFilter::new(ICAO_FILTER_TTL);
+    let at = SystemTime::now();
+
+    // TIS-B fine position, ADS-R and anonymous addresses aren't transponders
+    for &cf in &[1, 2, 5, 6] {
+        assert!(filter.accept(&squitter(18, cf, TEST_ADDRESS)));
+        assert!(!filter.contains(TEST_ADDRESS, at), "CF{} made known", cf);
+    }
+
+    assert!(filter.accept(&squitter(18, 0, TEST_ADDRESS)));
+    assert!(filter.contains(TEST_ADDRESS, at));
+}
+
+#[test]
+fn corrupt_squitters_are_rejected() {
+    let mut filter = IcaoFilter::new(ICAO_FILTER_TTL);
+
+    let frames = vec![
+        all_call(TEST_ADDRESS, 0),
+        squitter(17, 5, TEST_ADDRESS),
+        squitter(18, 0, TEST_ADDRESS),
+    ];
+    for frame in frames {
+        let mut bytes = frame.bytes().to_vec();
+        bytes[2] ^= 0x10;
+        let frame = Frame::from_bytes(&bytes, SystemTime::now());
+        assert!(
+            !filter.accept(&frame),
+            "corrupt DF{} accepted",
+            frame.downlink_format()
+        );
+    }
+    assert!(filter.is_empty());
+}
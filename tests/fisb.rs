@@ -0,0 +1,105 @@
+use fishfinder::fisb::{self, dlac, Product, ReportKind, WeatherCache};
+use fishfinder::gdl90;
+use std::time::{Duration, SystemTime};
+
+/* Uplinks in dump978's output format: text products and NEXRAD around Seattle */
+const SAMPLE_UPLINKS: &str = "samples/fisb.txt";
+
+fn sample_uplinks() -> Vec<fisb::Uplink> {
+    std::fs::read_to_string(SAMPLE_UPLINKS)
+        .unwrap()
+        .lines()
+        .map(|line| fisb::decode(&fisb::parse_hex(line).unwrap()).unwrap())
+        .collect()
+}
+
+#[test]
+fn dlac_round_trip() {
+    let text = "METAR KSEA 011753Z 17005KT 10SM A3012=";
+    assert_eq!(dlac::decode(&dlac::encode(text)), text);
+}
+
+#[test]
+fn uplink_header_and_apdus() {
+    let uplinks = sample_uplinks();
+    assert_eq!(uplinks.len(), 2);
+
+    let position = uplinks[0].position.unwrap();
+    assert!((position.latitude - 47.45).abs() < 1e-4);
+    assert!((position.longitude + 122.3).abs() < 1e-4);
+    assert_eq!(uplinks[0].slot_id, 5);
+    assert_eq!(uplinks[0].tisb_site_id, 3);
+
+    let ids: Vec<u16> = uplinks[0].apdus.iter().map(|a| a.product_id).collect();
+    assert_eq!(ids, vec![fisb::FISB_GENERIC_TEXT, fisb::FISB_NOTAM]);
+    assert_eq!(uplinks[0].apdus[0].time.hours, 17);
+    assert_eq!(uplinks[0].apdus[0].time.minutes, 53);
+
+    match &uplinks[1].apdus[1].product {
+        Product::Nexrad(blocks) => {
+            let numbers: Vec<u32> = blocks.iter().map(|b| b.block_number).collect();
+            assert_eq!(numbers, vec![320300, 320301]);
+            assert!(blocks.iter().all(|b| b.is_empty()));
+        }
+        product => panic!("expected NEXRAD, got {:?}", product),
+    }
+}
+
+#[test]
+fn weather_cache_keeps_latest_products() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_790_000_000);
+    let mut cache = WeatherCache::new();
+    for uplink in sample_uplinks() {
+        cache.process(&uplink, start);
+    }
+
+    let metar = cache.metar("KSEA").unwrap();
+    assert!(metar.text.starts_with("METAR KSEA 011753Z 17005KT"));
+    assert!(cache.taf("KSEA").unwrap().text.contains("0118/0218"));
+    assert_eq!(cache.reports(ReportKind::Pirep).count(), 1);
+
+    let notams: Vec<_> = cache.reports(ReportKind::Notam).collect();
+    assert_eq!(notams.len(), 1);
+    assert_eq!(notams[0].location, "SEA");
+    assert_eq!(notams[0].number, Some(1234));
+    assert!(notams[0].text.contains("RWY 16L/34R CLSD"));
+
+    let blocks: Vec<_> = cache.nexrad(false).collect();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].block_number, 320247);
+    assert_eq!(blocks[0].intensity[0], 0);
+    assert_eq!(blocks[0].intensity[40], 3);
+    assert_eq!(blocks[0].intensity[127], 5);
+    let (north, west) = blocks[0].north_west();
+    assert!((north - 47.4667).abs() < 1e-3);
+    assert!((west + 122.4).abs() < 1e-3);
+
+    // NEXRAD goes stale first, TAFs last
+    cache.expire(start + Duration::from_secs(3 * 3600));
+    assert_eq!(cache.nexrad(false).count(), 0);
+    assert!(cache.metar("KSEA").is_none());
+    assert!(cache.reports(ReportKind::Notam).next().is_none());
+    assert!(cache.taf("KSEA").is_some());
+}
+
+#[test]
+fn uplink_is_passed_through_as_gdl90() {
+    let payload = fisb::parse_hex(
+        std::fs::read_to_string(SAMPLE_UPLINKS)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap(),
+    )
+    .unwrap();
+
+    let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_790_000_000_500);
+    let message = gdl90::uplink(&payload, Some(at));
+    assert_eq!(message.len(), 436);
+    assert_eq!(message[0], gdl90::GDL90_UPLINK);
+    // 0.5s in 80ns units
+    assert_eq!(&message[1..4], &6_250_000u32.to_le_bytes()[..3]);
+    assert_eq!(&message[4..], &payload[..]);
+
+    assert_eq!(&gdl90::uplink(&payload, None)[1..4], &[0xff, 0xff, 0xff]);
+}
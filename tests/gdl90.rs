@@ -0,0 +1,122 @@
+use fishfinder::adsb::{SharedTracker, Tracker, TrackerConfig};
+use fishfinder::gdl90::{self, report::ReportAddress, Report};
+use fishfinder::geo::Position;
+use fishfinder::gps::Ownship;
+use fishfinder::sdr::{dsp, mode_s};
+use std::time::SystemTime;
+use tokio_stream::StreamExt;
+use tokio_util::codec::FramedRead;
+
+const SAMPLE_RECORDING: &str = "samples/modes1.bin";
+const SAMPLE_AIRCRAFT: u32 = 0x4d2023;
+
+#[test]
+fn heartbeat_frame_matches_spec_example() {
+    let framed = gdl90::frame(&[0x00, 0x81, 0x41, 0xdb, 0xd0, 0x08, 0x02]);
+    assert_eq!(
+        framed,
+        vec![0x7e, 0x00, 0x81, 0x41, 0xdb, 0xd0, 0x08, 0x02, 0xb3, 0x8b, 0x7e]
+    );
+}
+
+#[test]
+fn flag_and_escape_bytes_are_stuffed() {
+    let framed = gdl90::frame(&[0x14, 0x7e, 0x7d]);
+    assert_eq!(&framed[..6], &[0x7e, 0x14, 0x7d, 0x5e, 0x7d, 0x5d]);
+    assert_eq!(*framed.last().unwrap(), 0x7e);
+}
+
+#[test]
+fn geometric_altitude_clamps_vfom() {
+    // 3000 ft in 5 ft steps, VFOM 50 m
+    assert_eq!(
+        gdl90::geometric_altitude(3000.0, Some(50.0)),
+        vec![0x0b, 0x02, 0x58, 0x00, 0x32]
+    );
+    assert_eq!(
+        &gdl90::geometric_altitude(3000.0, Some(40000.0))[3..],
+        &[0x7f, 0xfe]
+    );
+    assert_eq!(&gdl90::geometric_altitude(3000.0, None)[3..], &[0x7f, 0xff]);
+}
+
+#[test]
+fn traffic_report_matches_spec_example() {
+    let report = Report {
+        alert: false,
+        address_type: ReportAddress::AdsbIcao,
+        address: 0o52642511,
+        position: Some(Position::new(44.90708, -122.99488)),
+        altitude: Some(5000),
+        airborne: true,
+        track: Some(45.0),
+        nic: 10,
+        nac_p: 9,
+        ground_speed: Some(123.0),
+        vertical_rate: Some(64),
+        emitter_category: 1,
+        callsign: Some("N825V".to_string()),
+        emergency: 0,
+    };
+
+    assert_eq!(
+        report.traffic(),
+        vec![
+            0x14, 0x00, 0xab, 0x45, 0x49, 0x1f, 0xef, 0x15, 0xa8, 0x89, 0x78, 0x0f, 0x09, 0xa9,
+            0x07, 0xb0, 0x01, 0x20, 0x01, 0x4e, 0x38, 0x32, 0x35, 0x56, 0x20, 0x20, 0x20, 0x00
+        ]
+    );
+}
+
+#[tokio::test]
+async fn ownship_is_reported_but_not_as_traffic() {
+    let config = TrackerConfig {
+        ownship_icao: Some(SAMPLE_AIRCRAFT),
+        ..TrackerConfig::default()
+    };
+    let tracker = SharedTracker::new(Tracker::with_config(config));
+
+    let file = tokio::fs::File::open(SAMPLE_RECORDING).await.unwrap();
+    let mut frames = FramedRead::new(
+        dsp::IQMagnitudeReader::new(file),
+        mode_s::FrameDecoder::new(),
+    )
+    .map(|f| f.unwrap());
+    while let Some(frame) = frames.next().await {
+        if frame.valid() {
+            tracker.process(&frame);
+        }
+    }
+
+    let snapshot = tracker.snapshot();
+    assert_eq!(tracker.ownship().unwrap().address.address, SAMPLE_AIRCRAFT);
+    assert!(snapshot
+        .traffic()
+        .all(|ac| ac.address.address != SAMPLE_AIRCRAFT));
+
+    let messages = gdl90::messages(&tracker, &Ownship::default(), SystemTime::now());
+    let ownship: Vec<_> = messages.iter().filter(|m| m[1] == 0x0a).collect();
+    assert_eq!(ownship.len(), 1);
+    assert_eq!(&ownship[0][3..6], &[0x4d, 0x20, 0x23]);
+    assert!(messages
+        .iter()
+        .filter(|m| m[1] == 0x14)
+        .all(|m| m[3..6] != [0x4d, 0x20, 0x23]));
+}
+
+#[test]
+fn foreflight_discovery_names_app_and_port() {
+    let discovery =
+        gdl90::discovery::parse(br#"{"App":"ForeFlight","GDL90":{"port":4000}}"#).unwrap();
+    assert_eq!(discovery.app, "ForeFlight");
+    assert_eq!(discovery.port, 4000);
+
+    let spaced = gdl90::discovery::parse(br#"{ "GDL90": { "port": 4002 }, "App": "Garmin" }"#);
+    assert_eq!(
+        spaced.map(|d| (d.app, d.port)),
+        Some(("Garmin".to_string(), 4002))
+    );
+
+    assert_eq!(gdl90::discovery::parse(b"hello"), None);
+    assert_eq!(gdl90::discovery::parse(br#"{"App":"ForeFlight"}"#), None);
+}
@@ -0,0 +1,90 @@
+use fishfinder::adsb::gillham;
+
+/* Gray coded altitude in 0xABCD form for a multiple of 100 ft */
+fn gillham_code(altitude: i32) -> u16 {
+    let n = (altitude / 100 + 12) as u32;
+    let five_hundreds = n / 5;
+    let mut one_hundreds = n % 5 + 1;
+    if five_hundreds & 1 != 0 {
+        one_hundreds = 6 - one_hundreds;
+    }
+
+    // C1 C2 C4, with 5 sent as the gray code of 7
+    let c = [0, 0b001, 0b011, 0b010, 0b110, 0b100][one_hundreds as usize];
+    // D2 D4 A1 A2 A4 B1 B2 B4
+    let g = five_hundreds ^ (five_hundreds >> 1);
+
+    let mut code = 0u16;
+    for &(bit, mask) in &[
+        (c & 0b100, 0x0010),
+        (c & 0b010, 0x0020),
+        (c & 0b001, 0x0040),
+        (g & 0x80, 0x0002),
+        (g & 0x40, 0x0004),
+        (g & 0x20, 0x1000),
+        (g & 0x10, 0x2000),
+        (g & 0x08, 0x4000),
+        (g & 0x04, 0x0100),
+        (g & 0x02, 0x0200),
+        (g & 0x01, 0x0400),
+    ] {
+        if bit != 0 {
+            code |= mask;
+        }
+    }
+    code
+}
+
+#[test]
+fn identity_codes() {
+    assert_eq!(gillham::decode_id13(0), 0x0000);
+    // A1 A2 A4 B1 B2 B4
+    assert_eq!(gillham::decode_id13(0x0aaa), 0x7700);
+    // A1 A2 A4 B1 B4
+    assert_eq!(gillham::decode_id13(0x0aa2), 0x7500);
+    // C1 C2 C4 D1 D2 D4
+    assert_eq!(gillham::decode_id13(0x1555), 0x0077);
+}
+
+#[test]
+fn gray_coded_altitudes_in_100ft_steps() {
+    for altitude in (-1200..=126_700).step_by(100) {
+        assert_eq!(
+            gillham::mode_a_to_mode_c(gillham_code(altitude)).map(|n| n * 100),
+            Some(altitude),
+            "{} ft",
+            altitude
+        );
+    }
+}
+
+#[test]
+fn invalid_gray_codes() {
+    // no C bits
+    assert_eq!(gillham::mode_a_to_mode_c(0x1000), None);
+    // D1 set
+    assert_eq!(gillham::mode_a_to_mode_c(0x0011), None);
+    // C1 C4 is a one hundreds value of 6
+    assert_eq!(gillham::mode_a_to_mode_c(0x0050), None);
+}
+
+#[test]
+fn ac13_altitudes() {
+    // Q bit, 25 ft increments
+    assert_eq!(gillham::decode_ac13(0x1838), Some(38000));
+    assert_eq!(gillham::decode_ac13(0x0010), Some(-1000));
+    // gray coded, C1 only is -800 ft
+    assert_eq!(gillham::decode_ac13(0x1000), Some(-800));
+    // unknown and metric
+    assert_eq!(gillham::decode_ac13(0), None);
+    assert_eq!(gillham::decode_ac13(0x1878), None);
+}
+
+#[test]
+fn ac12_altitudes() {
+    assert_eq!(gillham::decode_ac12(0xc38), Some(38000));
+    assert_eq!(gillham::decode_ac12(0x010), Some(-1000));
+    // the same gray coded -800 ft as above, without the M bit
+    assert_eq!(gillham::decode_ac12(0x800), Some(-800));
+    assert_eq!(gillham::decode_ac12(0), None);
+}
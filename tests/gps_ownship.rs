@@ -0,0 +1,91 @@
+use fishfinder::gps::{self, ownship::Fix, ubx, GpsDecoder, GpsMessage, Ownship};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio_stream::StreamExt;
+use tokio_util::codec::FramedRead;
+
+const SAMPLE_NMEA_LOG: &str = "samples/gps.nmea";
+
+fn ubx_frame(class: u8, id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = ubx::UBX_SYNC.to_vec();
+    frame.extend_from_slice(&[class, id]);
+    frame.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    frame.extend_from_slice(payload);
+    let (ck_a, ck_b) = ubx::checksum(&frame[2..]);
+    frame.extend_from_slice(&[ck_a, ck_b]);
+    frame
+}
+
+fn nav_pvt() -> Vec<u8> {
+    let mut payload = vec![0u8; 92];
+    payload[20] = 3; // 3D fix
+    payload[21] = 0x01; // gnssFixOK
+    payload[23] = 11;
+    payload[24..28].copy_from_slice(&137_815_000i32.to_le_bytes());
+    payload[28..32].copy_from_slice(&371_100_000i32.to_le_bytes());
+    payload[32..36].copy_from_slice(&1_563_800i32.to_le_bytes());
+    payload[36..40].copy_from_slice(&1_524_000i32.to_le_bytes());
+    payload[56..60].copy_from_slice(&(-2_540i32).to_le_bytes()); // climbing 500 fpm
+    payload[60..64].copy_from_slice(&54_119i32.to_le_bytes()); // 105.2 kt
+    payload[64..68].copy_from_slice(&15_840_000i32.to_le_bytes());
+    ubx_frame(0x01, 0x07, &payload)
+}
+
+#[tokio::test]
+async fn ownship_from_nmea_log() {
+    let (tx, rx) = watch::channel(Ownship::default());
+    let src = gps::open(SAMPLE_NMEA_LOG, 9600).await.unwrap();
+    gps::run(src, Arc::new(tx)).await.unwrap();
+
+    let ownship = rx.borrow();
+    let position = ownship.position.unwrap();
+    assert_eq!(ownship.fix, Fix::Fix3D);
+    assert_eq!(ownship.quality, 2);
+    assert!((position.latitude - 37.1093).abs() < 1e-4, "{:?}", position);
+    assert!(
+        (position.longitude - 13.7827).abs() < 1e-4,
+        "{:?}",
+        position
+    );
+    assert!((ownship.altitude.unwrap() - 5000.0).abs() < 1.0);
+    assert!((ownship.height_above_ellipsoid.unwrap() - 5130.6).abs() < 1.0);
+    assert_eq!(ownship.ground_speed, Some(105.2));
+    assert_eq!(ownship.track, Some(158.4));
+    assert_eq!(ownship.satellites_used, Some(9));
+    assert_eq!(ownship.satellites["GP"].len(), 10);
+    assert_eq!(ownship.vdop, Some(1.3));
+}
+
+#[tokio::test]
+async fn ubx_and_nmea_interleaved_with_garbage() {
+    let mut stream = b"\x00\xff garbage $GPVTG,158.4,T,,M,105.2,N,194.8,K,A*07\r\n".to_vec();
+    stream.extend_from_slice(&nav_pvt());
+    stream.extend_from_slice(b"\xb5\x62\x01\x07\x05\x00broken");
+    stream.extend_from_slice(&ubx_frame(0x0a, 0x04, &[1, 2, 3]));
+    stream.extend_from_slice(b"$GPGSA,A,3,02,05,,,,,,,,,,,1.6,0.9,1.3*39\r\n");
+
+    let messages: Vec<GpsMessage> = FramedRead::new(&stream[..], GpsDecoder::new())
+        .map(|m| m.unwrap())
+        .collect()
+        .await;
+    assert_eq!(messages.len(), 4, "{:?}", messages);
+    assert_eq!(
+        messages[2],
+        GpsMessage::Ubx(ubx::UbxMessage::Unknown {
+            class: 0x0a,
+            id: 0x04
+        })
+    );
+
+    let mut ownship = Ownship::default();
+    for message in &messages {
+        ownship.update(message);
+    }
+    let position = ownship.position.unwrap();
+    assert_eq!(ownship.fix, Fix::Fix3D);
+    assert!((position.latitude - 37.11).abs() < 1e-6);
+    assert!((position.longitude - 13.7815).abs() < 1e-6);
+    assert!((ownship.ground_speed.unwrap() - 105.2).abs() < 0.01);
+    assert!((ownship.vertical_speed.unwrap() - 500.0).abs() < 0.1);
+    assert_eq!(ownship.satellites_used, Some(11));
+}
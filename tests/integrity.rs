@@ -0,0 +1,220 @@
+use fishfinder::adsb::es::{self, EsMessage, OperationalStatus, TargetState};
+use fishfinder::adsb::integrity::{self, Integrity};
+
+const NM: f64 = 1852.0;
+
+fn operational_status(version: u8, nic_supplement_a: bool) -> OperationalStatus {
+    OperationalStatus {
+        airborne: true,
+        version,
+        nic_supplement_a,
+        nac_p: 9,
+        gva: if version >= 2 { Some(2) } else { None },
+        sil: 3,
+        sil_per_sample: version >= 2,
+        nic_baro: Some(true),
+        ..OperationalStatus::default()
+    }
+}
+
+/* Set bits `first..=last` (1-based) of an ME field */
+fn set_bits(me: &mut [u8], first: usize, last: usize, value: u32) {
+    for bit in first..=last {
+        if value >> (last - bit) & 1 != 0 {
+            me[(bit - 1) / 8] |= 0x80 >> ((bit - 1) % 8);
+        }
+    }
+}
+
+/* An airborne operational status ME with TCAS bit 11, NIC A, NACp 9, GVA 2, SIL 3 and NIC baro set */
+fn operational_status_me(version: u32) -> [u8; 7] {
+    let mut me = [0; 7];
+    set_bits(&mut me, 1, 5, 31);
+    set_bits(&mut me, 11, 11, 1);
+    set_bits(&mut me, 41, 43, version);
+    set_bits(&mut me, 44, 44, 1);
+    set_bits(&mut me, 45, 48, 9);
+    set_bits(&mut me, 49, 50, 2);
+    set_bits(&mut me, 51, 52, 3);
+    set_bits(&mut me, 53, 53, 1);
+    set_bits(&mut me, 55, 55, 1);
+    me
+}
+
+#[test]
+fn decoded_operational_status_by_version() {
+    // version, NACp, SIL, per sample, GVA, NIC baro, TCAS operational
+    let cases = [
+        (0, None, None, false, None, None, None),
+        (1, Some(9), Some(3), false, None, Some(true), Some(false)),
+        (2, Some(9), Some(3), true, Some(2), Some(true), Some(true)),
+    ];
+
+    for &(version, nac_p, sil, sil_per_sample, gva, nic_baro, tcas) in &cases {
+        let status = match es::decode(&operational_status_me(version)) {
+            EsMessage::OperationalStatus(status) => status,
+            m => panic!("unexpected {:?}", m),
+        };
+        assert_eq!(status.tcas_operational, tcas, "version {}", version);
+
+        let mut integrity = Integrity::default();
+        integrity.update_operational_status(&status);
+        assert_eq!(integrity.version, Some(version as u8));
+        assert_eq!(integrity.nac_p, nac_p, "version {}", version);
+        assert_eq!(integrity.sil, sil, "version {}", version);
+        assert_eq!(
+            integrity.sil_per_sample, sil_per_sample,
+            "version {}",
+            version
+        );
+        assert_eq!(integrity.gva, gva, "version {}", version);
+        assert_eq!(integrity.nic_baro, nic_baro, "version {}", version);
+        assert_eq!(
+            integrity.nic_supplement_a,
+            version > 0,
+            "version {}",
+            version
+        );
+    }
+}
+
+#[test]
+fn decoded_target_state() {
+    // version 2 format, NACp 10, NIC baro, SIL 2
+    let mut me = [0; 7];
+    set_bits(&mut me, 1, 5, 29);
+    set_bits(&mut me, 6, 7, 1);
+    set_bits(&mut me, 40, 43, 10);
+    set_bits(&mut me, 44, 44, 1);
+    set_bits(&mut me, 45, 46, 2);
+
+    let state = match es::decode(&me) {
+        EsMessage::TargetState(state) => state,
+        m => panic!("unexpected {:?}", m),
+    };
+    let mut integrity = Integrity::default();
+    integrity.update_target_state(&state);
+    assert_eq!(integrity.nac_p, Some(10));
+    assert_eq!(integrity.sil, Some(2));
+    assert_eq!(integrity.nic_baro, Some(true));
+
+    // the DO-260A format (subtype 0) isn't decoded
+    me[0] &= !0x06;
+    assert_eq!(es::decode(&me), EsMessage::Unknown { type_code: 29 });
+}
+
+#[test]
+fn version_0_status_carries_no_integrity() {
+    let mut integrity = Integrity::default();
+    integrity.update_operational_status(&operational_status(0, true));
+
+    assert_eq!(integrity.version, Some(0));
+    assert_eq!(integrity.nac_p, None);
+    assert_eq!(integrity.sil, None);
+    assert_eq!(integrity.nic_baro, None);
+    assert!(!integrity.nic_supplement_a);
+}
+
+#[test]
+fn target_state_updates_accuracy() {
+    let mut integrity = Integrity::default();
+    integrity.update_operational_status(&operational_status(2, false));
+    integrity.update_target_state(&TargetState {
+        nac_p: 10,
+        sil: 2,
+        sil_per_sample: false,
+        nic_baro: false,
+        ..TargetState::default()
+    });
+
+    assert_eq!(integrity.nac_p, Some(10));
+    assert_eq!(integrity.sil, Some(2));
+    assert!(!integrity.sil_per_sample);
+    assert_eq!(integrity.nic_baro, Some(false));
+    // target state doesn't say anything about the version
+    assert_eq!(integrity.version, Some(2));
+}
+
+#[test]
+fn nic_from_position_by_version_and_supplements() {
+    // version (None if no status yet), supplement A, supplement B, type code, NIC, Rc
+    let cases = [
+        (None, false, true, 11, Some(8), Some(0.1 * NM)),
+        (Some(0), false, true, 11, Some(8), Some(0.1 * NM)),
+        // before version 2 supplement A is used in place of B
+        (Some(1), true, false, 11, Some(9), Some(75.0)),
+        (Some(1), false, true, 11, Some(8), Some(0.1 * NM)),
+        (Some(2), true, true, 11, Some(9), Some(75.0)),
+        (Some(2), true, false, 11, Some(8), Some(0.1 * NM)),
+        (Some(2), false, true, 13, Some(6), Some(0.3 * NM)),
+        (Some(2), false, false, 13, Some(6), Some(0.5 * NM)),
+        (Some(2), true, true, 13, Some(6), Some(0.6 * NM)),
+        (Some(1), true, false, 16, Some(3), Some(4.0 * NM)),
+        (Some(2), false, true, 16, Some(2), Some(8.0 * NM)),
+        (Some(2), false, false, 9, Some(11), Some(7.5)),
+        (Some(2), false, false, 17, Some(1), Some(20.0 * NM)),
+        (Some(2), false, false, 18, Some(0), None),
+        (Some(2), false, false, 20, Some(11), Some(7.5)),
+        (Some(2), false, false, 22, Some(0), None),
+        (Some(2), false, false, 19, None, None),
+    ];
+
+    for &(version, a, b, type_code, nic, rc) in &cases {
+        let mut integrity = Integrity::default();
+        if let Some(version) = version {
+            integrity.update_operational_status(&operational_status(version, a));
+        }
+        integrity.update_position(type_code, b);
+
+        assert_eq!(
+            (integrity.nic, integrity.rc),
+            (nic, rc),
+            "version {:?} A {} B {} TC {}",
+            version,
+            a,
+            b,
+            type_code
+        );
+    }
+}
+
+#[test]
+fn surface_nic_uses_supplements_a_and_c() {
+    // type code, supplement A, supplement C, NIC, Rc
+    let cases = [
+        (5, false, false, 11, Some(7.5)),
+        (6, false, false, 10, Some(25.0)),
+        (7, true, false, 9, Some(75.0)),
+        (7, false, false, 8, Some(0.1 * NM)),
+        (8, true, true, 7, Some(0.2 * NM)),
+        (8, true, false, 6, Some(0.3 * NM)),
+        (8, false, true, 6, Some(0.6 * NM)),
+        (8, false, false, 0, None),
+    ];
+
+    for &(type_code, a, c, nic, rc) in &cases {
+        assert_eq!(
+            integrity::nic(type_code, a, false, c),
+            Some((nic, rc)),
+            "TC {} A {} C {}",
+            type_code,
+            a,
+            c
+        );
+    }
+}
+
+#[test]
+fn uat_nic_sets_containment_radius() {
+    let mut integrity = Integrity::default();
+    for &(nic, rc) in &[
+        (0, None),
+        (1, Some(20.0 * NM)),
+        (8, Some(0.1 * NM)),
+        (11, Some(7.5)),
+    ] {
+        integrity.update_nic(nic);
+        assert_eq!(integrity.nic, Some(nic));
+        assert_eq!(integrity.rc, rc);
+    }
+}
@@ -0,0 +1,106 @@
+use fishfinder::adsb::{SharedTracker, Tracker};
+use fishfinder::fisb::{self, WeatherCache};
+use fishfinder::metrics::{self, METRICS};
+use fishfinder::sdr::input;
+use fishfinder::sdr::repair::RepairPolicy;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const SAMPLE_RECORDING: &str = "samples/modes1.bin";
+const SAMPLE_UPLINKS: &str = "samples/fisb.txt";
+
+/* Counters are shared by every test in this binary, only compare growth */
+fn get(counter: &AtomicU64) -> u64 {
+    counter.load(Ordering::Relaxed)
+}
+
+async fn tracker_fed_with_recording() -> SharedTracker {
+    let tracker = SharedTracker::new(Tracker::new());
+    let file = tokio::fs::File::open(SAMPLE_RECORDING).await.unwrap();
+    input::run_mode_s(file, tracker.clone(), RepairPolicy::SingleBit).await;
+    tracker
+}
+
+async fn get_path(addr: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn pipeline_counts_samples_frames_and_messages() {
+    let samples = get(&METRICS.mode_s_samples);
+    let preambles = get(&METRICS.mode_s_preambles);
+    let demodulated = get(&METRICS.mode_s_demodulated);
+    let crc_ok = get(&METRICS.mode_s_crc_ok);
+    let messages = METRICS.messages();
+
+    let tracker = tracker_fed_with_recording().await;
+    let iq_samples = std::fs::metadata(SAMPLE_RECORDING).unwrap().len() / 2;
+
+    assert!(get(&METRICS.mode_s_samples) - samples >= iq_samples);
+    assert!(get(&METRICS.mode_s_preambles) - preambles >= 300);
+    assert!(get(&METRICS.mode_s_demodulated) - demodulated >= 300);
+    assert!(get(&METRICS.mode_s_crc_ok) - crc_ok >= 254);
+    assert!(METRICS.messages() - messages >= 254);
+
+    let text = METRICS.prometheus(&tracker);
+    assert!(text.contains("# TYPE fishfinder_mode_s_messages_total counter\n"));
+    assert!(text.contains("fishfinder_mode_s_messages_total{df=\"17\"} "));
+    assert!(text.contains("# TYPE fishfinder_mode_s_repairs_total counter\n"));
+    assert!(text.contains("fishfinder_mode_s_repairs_total{df=\"11\",result=\"skipped\"} "));
+    assert!(text.contains("fishfinder_samples_total{band=\"1090\"} "));
+    assert!(text.contains("fishfinder_output_clients{output=\"gdl90\"} "));
+    assert!(text.contains("\nfishfinder_aircraft 1\n"), "{}", text);
+    // no receiver location, no range
+    assert!(text.contains("\nfishfinder_max_range_meters 0\n"));
+}
+
+#[test]
+fn weather_cache_counts_products() {
+    let products = get(&METRICS.fisb_products);
+
+    let mut cache = WeatherCache::new();
+    let mut processed = 0;
+    for line in std::fs::read_to_string(SAMPLE_UPLINKS).unwrap().lines() {
+        let uplink = fisb::decode(&fisb::parse_hex(line).unwrap()).unwrap();
+        processed += cache.process(&uplink, std::time::SystemTime::now());
+    }
+
+    assert!(processed > 0);
+    assert!(get(&METRICS.fisb_products) - products >= processed as u64);
+
+    let text = METRICS.prometheus(&SharedTracker::new(Tracker::new()));
+    assert!(text.contains("# TYPE fishfinder_fisb_products_total counter\n"));
+    assert!(text.contains("\nfishfinder_fisb_cached_products "));
+}
+
+#[tokio::test]
+async fn metrics_are_served_over_http() {
+    let tracker = tracker_fed_with_recording().await;
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    tokio::spawn(metrics::run(tracker, addr));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let response = get_path(addr, "/metrics").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("Content-Type: text/plain; version=0.0.4\r\n"));
+    assert!(response.contains("\nfishfinder_aircraft 1\n"));
+
+    let response = get_path(addr, "/").await;
+    assert!(
+        response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{}",
+        response
+    );
+}
@@ -0,0 +1,274 @@
+use fishfinder::adsb::es::EsMessage;
+use fishfinder::sdr::input::Band;
+use fishfinder::sdr::lines::{self, LineFormat};
+use fishfinder::sdr::{crc, dsp, mode_s, stats};
+use tokio_stream::StreamExt;
+use tokio_util::codec::FramedRead;
+
+const SAMPLE_RECORDING: &str = "samples/modes1.bin";
+const SAMPLE_AIRCRAFT: u32 = 0x4d2023;
+
+// quiet samples before a synthetic frame starts
+const LEAD_SAMPLES: usize = 100;
+
+async fn decode_recording(path: &str) -> Vec<mode_s::Frame> {
+    let file = tokio::fs::File::open(path).await.unwrap();
+    let magnitude_src = dsp::IQMagnitudeReader::new(file);
+    let frame_stream = FramedRead::new(magnitude_src, mode_s::FrameDecoder::new());
+
+    frame_stream.map(|f| f.unwrap()).collect().await
+}
+
+async fn decode_iq(iq: Vec<u8>) -> Vec<mode_s::Frame> {
+    let magnitude_src = dsp::IQMagnitudeReader::new(std::io::Cursor::new(iq));
+    let frame_stream = FramedRead::new(magnitude_src, mode_s::FrameDecoder::new());
+
+    frame_stream.map(|f| f.unwrap()).collect().await
+}
+
+/* The KLM1023 identification squitter */
+fn squitter() -> Vec<u8> {
+    let mut bytes = vec![
+        0x8d, 0x48, 0x40, 0xd6, 0x20, 0x2c, 0xc3, 0x71, 0xc3, 0x2c, 0xe0,
+    ];
+    let parity = crc::modes_crc(&bytes);
+    bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+    bytes
+}
+
+/* Pulse level (0 or 1) of every half microsecond of preamble and frame */
+fn chips(bytes: &[u8]) -> Vec<f64> {
+    let mut chips = vec![
+        1., 0., 1., 0., 0., 0., 0., 1., 0., 1., 0., 0., 0., 0., 0., 0.,
+    ];
+    for byte in bytes {
+        for bit in (0..8).rev() {
+            match byte >> bit & 1 {
+                1 => chips.extend_from_slice(&[1., 0.]),
+                _ => chips.extend_from_slice(&[0., 1.]),
+            }
+        }
+    }
+    chips
+}
+
+/* IQ samples of the chips starting `offset` samples after LEAD_SAMPLES,
+ * every sample gets the energy of the chips it overlaps */
+fn iq(chips: &[f64], offset: f64, amplitude: f64, noise: u8) -> Vec<u8> {
+    let mut iq = Vec::new();
+    for j in 0..LEAD_SAMPLES + chips.len() + 300 {
+        let mut level = 0.0;
+        for (k, chip) in chips.iter().enumerate() {
+            let start = (LEAD_SAMPLES + k) as f64 + offset;
+            let overlap = ((j + 1) as f64).min(start + 1.0) - (j as f64).max(start);
+            if overlap > 0.0 {
+                level += overlap * chip;
+            }
+        }
+        iq.push(127 + (level * amplitude).round() as u8 + noise);
+        iq.push(127);
+    }
+    iq
+}
+
+#[tokio::test]
+async fn decodes_valid_frames_from_recording() {
+    let frames = decode_recording(SAMPLE_RECORDING).await;
+    let valid: Vec<&mode_s::Frame> = frames.iter().filter(|f| f.valid()).collect();
+
+    let short = valid
+        .iter()
+        .filter(|f| f.bytes().len() == mode_s::MODES_SHORT_MSG_BYTES)
+        .count();
+    let long = valid
+        .iter()
+        .filter(|f| f.bytes().len() == mode_s::MODES_LONG_MSG_BYTES)
+        .count();
+
+    assert_eq!(valid.len(), short + long);
+    assert!(short >= 73, "only {} valid short frames", short);
+    assert!(long >= 181, "only {} valid long frames", long);
+}
+
+#[tokio::test]
+async fn invalid_frames_do_not_hide_valid_ones() {
+    let frames = decode_recording(SAMPLE_RECORDING).await;
+    let invalid = frames.iter().filter(|f| !f.valid()).count();
+
+    // every broken candidate is reported once per burst, not once per sample offset
+    assert!(invalid <= 110, "{} invalid frames", invalid);
+}
+
+#[tokio::test]
+async fn clean_frame_decodes_at_every_sub_sample_phase() {
+    // the demodulator's phases and half way between them
+    for step in 0..mode_s::MODES_DEMOD_PHASES * 2 {
+        let offset = step as f64 / (mode_s::MODES_DEMOD_PHASES * 2) as f64;
+        let frames = decode_iq(iq(&chips(&squitter()), offset, 80.0, 0)).await;
+
+        assert_eq!(frames.len(), 1, "offset {}", offset);
+        assert!(frames[0].valid(), "offset {}: {}", offset, frames[0]);
+        assert_eq!(frames[0].bytes(), &squitter()[..]);
+    }
+}
+
+#[tokio::test]
+async fn frame_timing_and_levels() {
+    for &offset in &[0.0, 0.25, 0.5, 0.75] {
+        let frames = decode_iq(iq(&chips(&squitter()), offset, 80.0, 4)).await;
+        let frame = &frames[0];
+
+        // the preamble start to within a sample, 6 ticks of the 12 MHz clock
+        let start = (LEAD_SAMPLES as f64 + offset) * 6.0;
+        let mlat = frame.mlat_timestamp() as f64;
+        assert!(
+            (mlat - start).abs() <= 6.0,
+            "offset {}: {} vs {}",
+            offset,
+            mlat,
+            start
+        );
+        assert_eq!(
+            frame.mlat_timestamp(),
+            (frame.sample_index() * 4 + frame.phase() as u64) * 3 / 2
+        );
+        assert!(frame.sample_index() + 1 >= LEAD_SAMPLES as u64);
+        assert!(frame.sample_index() <= LEAD_SAMPLES as u64 + 1);
+
+        // pulses of 84 and empty samples of 4 out of 128 are about -3.7 and -30 dBFS,
+        // less and more when out of phase
+        assert!(
+            frame.signal_level() > -9.0 && frame.signal_level() < -3.0,
+            "offset {}: signal {}",
+            offset,
+            frame.signal_level()
+        );
+        assert!(
+            frame.noise_level() > -31.0 && frame.noise_level() < -10.0,
+            "offset {}: noise {}",
+            offset,
+            frame.noise_level()
+        );
+        assert!(frame.signal_level() - frame.noise_level() > 3.0);
+    }
+
+    // the same frame later in the stream is found at a later sample
+    let mut later = vec![127; 2 * 1000];
+    later.extend(iq(&chips(&squitter()), 0.0, 80.0, 0));
+    let frames = decode_iq(later).await;
+    assert!(frames[0].sample_index() + 1 >= 1000 + LEAD_SAMPLES as u64);
+}
+
+#[tokio::test]
+async fn ambiguous_bit_repeats_its_predecessor() {
+    let bytes = squitter();
+    // bit 9 is a 1 like bit 8, make both of its halves equal
+    assert_eq!(bytes[1] >> 6, 0b01);
+    let mut chips = chips(&bytes);
+    let bit = 9;
+    chips[16 + bit * 2] = 0.5;
+    chips[16 + bit * 2 + 1] = 0.5;
+
+    let frames = decode_iq(iq(&chips, 0.0, 80.0, 0)).await;
+    let frame = &frames[0];
+    assert!(frame.valid(), "{}", frame);
+    assert_eq!(frame.bytes(), &bytes[..]);
+    let weakest = (0..frame.confidence().len())
+        .min_by_key(|&i| frame.confidence()[i])
+        .unwrap();
+    assert_eq!(weakest, bit);
+}
+
+#[tokio::test]
+async fn two_bit_error_on_a_weak_bit_needs_the_hint() {
+    let bytes = squitter();
+    // bit 46 is a 0 following a 1, send it as a weak 1
+    let weak = 46;
+    assert_eq!(bytes[5] >> 1 & 0b11, 0b10);
+    let mut chips = chips(&bytes);
+    chips[16 + weak * 2] = 0.6;
+    chips[16 + weak * 2 + 1] = 0.4;
+    // and a clean error elsewhere
+    let strong = 70;
+    chips.swap(16 + strong * 2, 16 + strong * 2 + 1);
+
+    let frames = decode_iq(iq(&chips, 0.0, 80.0, 0)).await;
+    assert_eq!(frames.len(), 1);
+    let frame = &frames[0];
+    assert!(!frame.valid(), "{}", frame);
+    let weakest = (0..frame.confidence().len())
+        .min_by_key(|&i| frame.confidence()[i])
+        .unwrap();
+    assert_eq!(weakest, weak);
+
+    // a single bit from the syndrome alone doesn't do it
+    assert_eq!(crc::modes_correct(&mut frame.bytes().to_vec(), 1), None);
+
+    let repaired = frame.repair(1).unwrap();
+    assert!(repaired.valid());
+    assert_eq!(repaired.bytes(), &bytes[..]);
+    assert_eq!(repaired.corrected(), &[weak, strong]);
+    assert_eq!(repaired.corrected_bits(), 2);
+}
+
+#[tokio::test]
+async fn extended_squitters_parse_with_the_in_tree_decoder() {
+    let frames = decode_recording(SAMPLE_RECORDING).await;
+    let valid: Vec<&mode_s::Frame> = frames.iter().filter(|f| f.valid()).collect();
+
+    for frame in &valid {
+        match frame.downlink_format() {
+            17 => assert!(frame.parse().is_some(), "{}", frame),
+            _ => assert!(frame.parse().is_none(), "{}", frame),
+        }
+    }
+    assert!(valid
+        .iter()
+        .filter_map(|f| f.parse())
+        .any(|m| matches!(m, EsMessage::AirbornePosition { .. })));
+}
+
+#[tokio::test]
+async fn frames_print_as_avr_and_json_lines() {
+    let frames = decode_recording(SAMPLE_RECORDING).await;
+    let frame = frames
+        .iter()
+        .find(|f| f.valid() && f.downlink_format() == 17)
+        .unwrap();
+    let hex = hex::encode(frame.bytes());
+
+    assert_eq!(lines::mode_s(frame, LineFormat::Avr), format!("*{};", hex));
+
+    let json = lines::mode_s(frame, LineFormat::Json);
+    assert!(
+        json.starts_with("{\"type\":\"mode_s\",\"df\":17,"),
+        "{}",
+        json
+    );
+    assert!(json.contains("\"address\":\"4d2023\""), "{}", json);
+    assert!(json.contains(&format!("\"hex\":\"{}\"", hex)), "{}", json);
+    assert!(json.ends_with('}'));
+}
+
+#[tokio::test]
+async fn stats_count_frames_and_aircraft_of_recording() {
+    let file = tokio::fs::File::open(SAMPLE_RECORDING).await.unwrap();
+    let samples = file.metadata().await.unwrap().len() / 2;
+    let mut stats = stats::analyse(file, Band::ModeS).await;
+    stats.samples = Some(samples);
+
+    assert!(stats.valid() >= 254, "only {} valid frames", stats.valid());
+    assert_eq!(
+        stats.aircraft(),
+        1,
+        "only {:06x} is in the recording",
+        SAMPLE_AIRCRAFT
+    );
+    assert!(stats.frames["DF17"] > 0);
+    assert!(stats.mean_signal().unwrap() < 0.0);
+    assert!(stats.duration().unwrap().as_secs_f64() > 0.0);
+
+    let report = stats.to_string();
+    assert!(report.contains("band:       1090 MHz"), "{}", report);
+    assert!(report.contains("DF17"), "{}", report);
+}
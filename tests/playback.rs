@@ -0,0 +1,154 @@
+use fishfinder::sdr::playback::Playback;
+use std::future;
+use std::io::{self, Cursor, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, ReadBuf};
+
+/* 200 samples a second, slow enough to measure the pacing of a tiny recording */
+const TEST_SAMPLE_RATE: u32 = 200;
+
+fn recording(samples: usize) -> Vec<u8> {
+    (0..samples * 2).map(|i| i as u8).collect()
+}
+
+/* A recording that hands out at most `chunk` bytes per read, like a pipe */
+struct Dribble {
+    inner: Cursor<Vec<u8>>,
+    chunk: usize,
+}
+
+impl AsyncRead for Dribble {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let n = this.chunk.min(buf.remaining());
+        let mut chunk = vec![0; n];
+        let mut chunk_buf = ReadBuf::new(&mut chunk);
+        match Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf) {
+            Poll::Ready(Ok(())) => {
+                buf.put_slice(chunk_buf.filled());
+                Poll::Ready(Ok(()))
+            }
+            other => other,
+        }
+    }
+}
+
+impl AsyncSeek for Dribble {
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        Pin::new(&mut self.get_mut().inner).start_seek(position)
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.get_mut().inner).poll_complete(cx)
+    }
+}
+
+/* Read everything with a buffer of `size`, returns the bytes and the size of every read */
+async fn read_all<T: AsyncRead + Unpin>(
+    src: &mut T,
+    size: usize,
+    max: usize,
+) -> (Vec<u8>, Vec<usize>) {
+    let mut data = Vec::new();
+    let mut reads = Vec::new();
+    let mut buf = vec![0; size];
+    while data.len() < max {
+        let n = src.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+        reads.push(n);
+    }
+    (data, reads)
+}
+
+#[tokio::test]
+async fn playback_is_paced_at_the_sample_rate() {
+    let data = recording(40);
+    let mut playback = Playback::new(Cursor::new(data.clone()), TEST_SAMPLE_RATE);
+
+    let started = Instant::now();
+    let (played, _) = read_all(&mut playback, 4096, usize::MAX).await;
+    let elapsed = started.elapsed();
+
+    assert_eq!(played, data);
+    // 40 samples at 200/s, less the tick delivered ahead of time
+    assert!(elapsed >= Duration::from_millis(180), "took {:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(600), "took {:?}", elapsed);
+}
+
+#[tokio::test]
+async fn tiny_buffers_never_read_as_eof() {
+    let data = recording(20);
+    for &size in &[2, 3] {
+        let src = Cursor::new(data.clone());
+        let mut playback = Playback::new(src, TEST_SAMPLE_RATE).speed(100.0);
+        let (played, reads) = read_all(&mut playback, size, usize::MAX).await;
+
+        assert_eq!(played, data, "buffer of {}", size);
+        assert!(reads.iter().all(|&n| n <= size));
+    }
+}
+
+#[tokio::test]
+async fn no_room_for_a_whole_sample_waits_instead_of_splitting_it() {
+    let data = recording(20);
+    let src = Dribble {
+        inner: Cursor::new(data.clone()),
+        chunk: 1,
+    };
+    let mut playback = Playback::new(src, TEST_SAMPLE_RATE).speed(100.0);
+
+    // one byte left in the caller's buffer
+    let mut storage = [0u8; 3];
+    let mut buf = ReadBuf::new(&mut storage);
+    buf.advance(2);
+    let poll =
+        future::poll_fn(|cx| Poll::Ready(Pin::new(&mut playback).poll_read(cx, &mut buf))).await;
+    assert!(poll.is_pending());
+    assert_eq!(buf.filled().len(), 2);
+
+    let (played, reads) = read_all(&mut playback, 16, usize::MAX).await;
+    assert_eq!(played, data);
+    assert!(reads.iter().all(|&n| n % 2 == 0), "reads {:?}", reads);
+}
+
+#[tokio::test]
+async fn odd_reads_keep_samples_aligned() {
+    let data = recording(100);
+    let src = Dribble {
+        inner: Cursor::new(data.clone()),
+        chunk: 3,
+    };
+    let mut playback = Playback::new(src, TEST_SAMPLE_RATE).speed(100.0);
+    let (played, reads) = read_all(&mut playback, 64, usize::MAX).await;
+
+    assert_eq!(played, data);
+    assert!(reads.iter().all(|&n| n % 2 == 0), "reads {:?}", reads);
+}
+
+#[tokio::test]
+async fn looping_repeats_the_recording() {
+    let data = recording(15);
+    let src = Dribble {
+        inner: Cursor::new(data.clone()),
+        chunk: 7,
+    };
+    let mut playback = Playback::new(src, TEST_SAMPLE_RATE)
+        .speed(100.0)
+        .looping(true);
+    let (played, reads) = read_all(&mut playback, 16, data.len() * 3).await;
+
+    assert!(played.len() >= data.len() * 3);
+    for (i, &byte) in played.iter().enumerate() {
+        assert_eq!(byte, data[i % data.len()], "byte {}", i);
+    }
+    assert!(reads.iter().all(|&n| n % 2 == 0), "reads {:?}", reads);
+}
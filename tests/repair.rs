@@ -0,0 +1,182 @@
+use fishfinder::metrics::METRICS;
+use fishfinder::sdr::crc;
+use fishfinder::sdr::filter::{IcaoFilter, ICAO_FILTER_TTL};
+use fishfinder::sdr::mode_s::Frame;
+use fishfinder::sdr::repair::{RepairPolicy, RepairResult, Repairer};
+use std::time::SystemTime;
+
+const TEST_ADDRESS: u32 = 0xa1b2c3;
+
+/* An extended squitter (DF17 CA 5, DF18 CF 0) with a valid parity */
+fn squitter(df: u8) -> Vec<u8> {
+    let mut bytes = vec![
+        df << 3 | if df == 17 { 5 } else { 0 },
+        (TEST_ADDRESS >> 16) as u8,
+        (TEST_ADDRESS >> 8) as u8,
+        TEST_ADDRESS as u8,
+        0x58,
+        0x7f,
+        0x34,
+        0x5e,
+        0x35,
+        0x83,
+        0x7e,
+    ];
+    let parity = crc::modes_crc(&bytes);
+    bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+    bytes
+}
+
+fn df18() -> Vec<u8> {
+    squitter(18)
+}
+
+/* A DF11 all-call reply with a valid parity */
+fn df11() -> Vec<u8> {
+    let mut bytes = vec![11 << 3 | 5, 0xa1, 0xb2, 0xc3];
+    let parity = crc::modes_crc(&bytes);
+    bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+    bytes
+}
+
+fn damaged(mut bytes: Vec<u8>, bits: &[usize]) -> Frame {
+    for &bit in bits {
+        bytes[bit / 8] ^= 0x80 >> (bit % 8);
+    }
+    Frame::from_bytes(&bytes, SystemTime::now())
+}
+
+fn corrected_metrics() -> (u64, u64) {
+    (
+        METRICS.mode_s_repairs(18, RepairResult::Corrected1Bit),
+        METRICS.mode_s_repairs(18, RepairResult::Corrected2Bit),
+    )
+}
+
+/* The only test in this binary repairing DF18 */
+#[test]
+fn only_repaired_frames_count_as_corrections() {
+    let known = IcaoFilter::new(ICAO_FILTER_TTL);
+    let mut repairer = Repairer::new(RepairPolicy::SingleBit);
+    let before = corrected_metrics();
+
+    let clean = Frame::from_bytes(&df18(), SystemTime::now());
+    let repaired = repairer.repair(&clean, &known).unwrap();
+    assert_eq!(repaired.bytes(), clean.bytes());
+    assert_eq!(repaired.corrected_bits(), 0);
+
+    let total = repairer.stats().total();
+    assert_eq!(total.attempted, 0);
+    assert_eq!(total.corrected_1bit, 0);
+    assert_eq!(total.corrected_2bit, 0);
+    assert_eq!(corrected_metrics(), before);
+
+    let mut bytes = df18();
+    bytes[7] ^= 0x04;
+    let damaged = Frame::from_bytes(&bytes, SystemTime::now());
+    let repaired = repairer.repair(&damaged, &known).unwrap();
+    assert_eq!(repaired.bytes(), &df18()[..]);
+    assert_eq!(repaired.corrected(), &[61]);
+
+    let total = repairer.stats().total();
+    assert_eq!(total.attempted, 1);
+    assert_eq!(total.corrected_1bit, 1);
+    assert_eq!(corrected_metrics(), (before.0 + 1, before.1));
+}
+
+#[test]
+fn three_bit_errors_are_never_repaired() {
+    let mut bytes = df18();
+    bytes[5] ^= 0x80;
+    bytes[7] ^= 0x04;
+    bytes[9] ^= 0x01;
+    let damaged = Frame::from_bytes(&bytes, SystemTime::now());
+
+    for max_bits in 1..=4 {
+        assert!(
+            damaged.repair(max_bits).is_none(),
+            "repaired with {}",
+            max_bits
+        );
+    }
+    assert!(crc::modes_correct_with_hints(&mut bytes.clone(), &[40, 61, 79], 3).is_none());
+}
+
+#[test]
+fn frames_without_confidence_have_no_weak_bits() {
+    let mut bytes = df18();
+    bytes[0] ^= 0x02;
+    bytes[8] ^= 0x02;
+    let damaged = Frame::from_bytes(&bytes, SystemTime::now());
+
+    assert!(damaged.repair(1).is_none());
+    assert_eq!(damaged.repair(2).unwrap().corrected(), &[6, 70]);
+}
+
+#[test]
+fn repairs_never_touch_the_df_field() {
+    let mut known = IcaoFilter::new(ICAO_FILTER_TTL);
+    let mut repairer = Repairer::new(RepairPolicy::TwoBitKnownIcao);
+
+    // a DF18 with two bits of its DF flipped reads as DF17
+    let frame = damaged(df18(), &[3, 4]);
+    assert_eq!(frame.downlink_format(), 17);
+    assert_eq!(frame.repair(2).unwrap().corrected(), &[3, 4]);
+    known.insert(TEST_ADDRESS, frame.received_at());
+
+    assert!(repairer.repair(&frame, &known).is_none());
+    assert_eq!(repairer.stats().df(17).refused, 1);
+}
+
+#[test]
+fn two_bit_repairs_need_a_known_address() {
+    let mut known = IcaoFilter::new(ICAO_FILTER_TTL);
+    let mut repairer = Repairer::new(RepairPolicy::TwoBitKnownIcao);
+    let frame = damaged(squitter(17), &[40, 70]);
+
+    assert!(repairer.repair(&frame, &known).is_none());
+    assert_eq!(repairer.stats().df(17).refused, 1);
+    assert!(METRICS.mode_s_repairs(17, RepairResult::Refused) >= 1);
+
+    known.insert(TEST_ADDRESS, frame.received_at());
+    let repaired = repairer.repair(&frame, &known).unwrap();
+    assert_eq!(repaired.bytes(), &squitter(17)[..]);
+    assert_eq!(repaired.corrected(), &[40, 70]);
+    assert_eq!(repairer.stats().df(17).corrected_2bit, 1);
+}
+
+#[test]
+fn df18_is_held_to_a_single_bit() {
+    let mut known = IcaoFilter::new(ICAO_FILTER_TTL);
+    let mut repairer = Repairer::new(RepairPolicy::TwoBitKnownIcao);
+    let frame = damaged(df18(), &[40, 70]);
+    known.insert(TEST_ADDRESS, frame.received_at());
+
+    assert!(repairer.repair(&frame, &known).is_none());
+    assert_eq!(repairer.stats().df(18).failed, 1);
+    assert_eq!(repairer.stats().df(18).corrected_2bit, 0);
+}
+
+#[test]
+fn policies_skip_formats_they_dont_cover() {
+    let known = IcaoFilter::new(ICAO_FILTER_TTL);
+
+    let mut off = Repairer::new(RepairPolicy::Off);
+    assert!(off.repair(&damaged(squitter(17), &[70]), &known).is_none());
+    assert_eq!(off.stats().df(17).skipped, 1);
+
+    for &policy in &[RepairPolicy::SingleBit, RepairPolicy::TwoBitKnownIcao] {
+        let mut repairer = Repairer::new(policy);
+        assert!(repairer.repair(&damaged(df11(), &[30]), &known).is_none());
+        assert_eq!(repairer.stats().df(11).skipped, 1, "{}", policy);
+        assert_eq!(repairer.stats().df(11).failed, 0, "{}", policy);
+    }
+
+    // single bit only, a two bit error is tried but not fixed
+    let mut single = Repairer::new(RepairPolicy::SingleBit);
+    assert!(single
+        .repair(&damaged(squitter(17), &[40, 70]), &known)
+        .is_none());
+    assert_eq!(single.stats().df(17).skipped, 0);
+    assert_eq!(single.stats().df(17).failed, 1);
+}
@@ -0,0 +1,691 @@
+use fishfinder::adsb::cpr::{self, CprFrame, CPR_MAX};
+use fishfinder::adsb::es::EmergencyState;
+use fishfinder::adsb::event::{Alert, AlertKind, Field, TrackerEvent};
+use fishfinder::adsb::receiver::ReceiverLocation;
+use fishfinder::adsb::shared::SharedTracker;
+use fishfinder::adsb::{
+    AddressFamily, Tracker, TrackerConfig, AIRCRAFT_TIMEOUT, CPR_AIRBORNE_LOCAL_RANGE,
+    DEFAULT_MAX_RANGE, SQUAWK_EMERGENCY, SQUAWK_HIJACK, SQUAWK_RADIO_FAILURE,
+};
+use fishfinder::geo::{Position, METERS_PER_NM};
+use fishfinder::sdr::crc;
+use fishfinder::sdr::mode_s::Frame;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+
+const TEST_ADDRESS: u32 = 0xa1b2c3;
+const OTHER_ADDRESS: u32 = 0x3c6444;
+
+// 13 bit identity codes as transmitted
+const ID13_1200: u32 = 0x0808;
+const ID13_7500: u32 = 0x0aa2;
+const ID13_7600: u32 = 0x0a8a;
+const ID13_7700: u32 = 0x0aaa;
+
+fn at(seconds: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000 + seconds)
+}
+
+/* A DF5/DF21 identity reply with the address in its parity, flight status 0
+ * is airborne and 5 airborne with SPI (IDENT) */
+fn identity_reply(df: u8, fs: u8, id13: u32, address: u32, at: SystemTime) -> Frame {
+    let mut bytes = vec![df << 3 | fs, 0, (id13 >> 8) as u8, id13 as u8];
+    if df == 21 {
+        // a Comm-B reply, the MB field doesn't matter here
+        bytes.extend_from_slice(&[0; 7]);
+    }
+    let parity = crc::modes_crc(&bytes) ^ address;
+    bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+
+    Frame::from_bytes(&bytes, at)
+}
+
+/* A DF17 extended squitter with a valid parity */
+fn squitter(me: &[u8; 7], address: u32, at: SystemTime) -> Frame {
+    let mut bytes = vec![
+        17 << 3 | 5,
+        (address >> 16) as u8,
+        (address >> 8) as u8,
+        address as u8,
+    ];
+    bytes.extend_from_slice(me);
+    let parity = crc::modes_crc(&bytes);
+    bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+
+    Frame::from_bytes(&bytes, at)
+}
+
+/* Aircraft status (TC 28 subtype 1) with an emergency state and identity code */
+fn aircraft_status(emergency: u8, id13: u32) -> [u8; 7] {
+    [
+        28 << 3 | 1,
+        emergency << 5 | (id13 >> 8) as u8,
+        id13 as u8,
+        0,
+        0,
+        0,
+        0,
+    ]
+}
+
+/* Airborne CPR encoding, the inverse of what the decoder does */
+fn encode(lat: f64, lon: f64, odd: bool) -> CprFrame {
+    let i = if odd { 1.0 } else { 0.0 };
+    let dlat = 360.0 / (60.0 - i);
+    let yz = (CPR_MAX * lat.rem_euclid(dlat) / dlat + 0.5).floor();
+    let rlat = dlat * (yz / CPR_MAX + (lat / dlat).floor());
+    let dlon = 360.0 / (cpr::nl(rlat) as f64 - i).max(1.0);
+    let xz = (CPR_MAX * lon.rem_euclid(dlon) / dlon + 0.5).floor();
+
+    CprFrame {
+        lat: yz as u32 % 131072,
+        lon: xz as u32 % 131072,
+        odd,
+    }
+}
+
+/* Airborne position (TC 11) at 38000 ft */
+fn airborne_position(lat: f64, lon: f64, odd: bool) -> [u8; 7] {
+    let cpr = encode(lat, lon, odd);
+    let mut me = [11 << 3, 0xc3, 0x80, 0, 0, 0, 0];
+    if odd {
+        me[2] |= 0x04;
+    }
+    me[2] |= (cpr.lat >> 15) as u8;
+    me[3] = (cpr.lat >> 7) as u8;
+    me[4] = (cpr.lat << 1) as u8 | (cpr.lon >> 16) as u8;
+    me[5] = (cpr.lon >> 8) as u8;
+    me[6] = cpr.lon as u8;
+    me
+}
+
+fn assert_near(actual: Option<Position>, expected: (f64, f64)) {
+    let actual = actual.expect("no position");
+    assert!(
+        (actual.latitude - expected.0).abs() < 0.001
+            && (actual.longitude - expected.1).abs() < 0.001,
+        "{:?} is not {:?}",
+        actual,
+        expected
+    );
+}
+
+fn alerts(events: &mut broadcast::Receiver<TrackerEvent>) -> Vec<AlertKind> {
+    let mut kinds = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let TrackerEvent::Emergency(Alert { address, kind, .. }) = event {
+            assert_eq!(address.address, TEST_ADDRESS);
+            kinds.push(kind);
+        }
+    }
+    kinds
+}
+
+#[test]
+fn identity_replies_decode_the_squawk() {
+    let mut tracker = Tracker::new();
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+    assert_eq!(tracker.get(TEST_ADDRESS).unwrap().squawk, Some(0x1200));
+
+    tracker.process(&identity_reply(21, 0, ID13_7600, TEST_ADDRESS, at(1)));
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.squawk, Some(SQUAWK_RADIO_FAILURE));
+    assert_eq!(ac.on_ground, Some(false));
+    assert!(!ac.ident);
+}
+
+#[test]
+fn emergency_squawks_alert_once_per_transition() {
+    for &(id13, squawk) in &[
+        (ID13_7500, SQUAWK_HIJACK),
+        (ID13_7600, SQUAWK_RADIO_FAILURE),
+        (ID13_7700, SQUAWK_EMERGENCY),
+    ] {
+        let mut tracker = Tracker::new();
+        let mut events = tracker.subscribe();
+
+        tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+        assert_eq!(alerts(&mut events), vec![]);
+
+        for t in 1..5 {
+            tracker.process(&identity_reply(5, 0, id13, TEST_ADDRESS, at(t)));
+        }
+        assert_eq!(alerts(&mut events), vec![AlertKind::Squawk(squawk)]);
+
+        // back to normal and squawking it again is a new alert
+        tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(5)));
+        tracker.process(&identity_reply(21, 0, id13, TEST_ADDRESS, at(6)));
+        tracker.process(&identity_reply(21, 0, id13, TEST_ADDRESS, at(7)));
+        assert_eq!(alerts(&mut events), vec![AlertKind::Squawk(squawk)]);
+    }
+}
+
+#[test]
+fn first_message_squawking_emergency_alerts() {
+    let mut tracker = Tracker::new();
+    let mut events = tracker.subscribe();
+
+    tracker.process(&identity_reply(5, 0, ID13_7700, TEST_ADDRESS, at(0)));
+    tracker.process(&identity_reply(5, 0, ID13_7700, TEST_ADDRESS, at(1)));
+    assert_eq!(
+        alerts(&mut events),
+        vec![AlertKind::Squawk(SQUAWK_EMERGENCY)]
+    );
+}
+
+#[test]
+fn emergency_state_alerts_once() {
+    let mut tracker = Tracker::new();
+    let mut events = tracker.subscribe();
+
+    tracker.process(&squitter(
+        &aircraft_status(0, ID13_1200),
+        TEST_ADDRESS,
+        at(0),
+    ));
+    assert_eq!(alerts(&mut events), vec![]);
+
+    for t in 1..5 {
+        tracker.process(&squitter(
+            &aircraft_status(2, ID13_1200),
+            TEST_ADDRESS,
+            at(t),
+        ));
+    }
+    assert_eq!(
+        alerts(&mut events),
+        vec![AlertKind::Emergency(EmergencyState::Medical)]
+    );
+
+    // a different emergency is a new alert, ending it isn't one
+    tracker.process(&squitter(
+        &aircraft_status(3, ID13_1200),
+        TEST_ADDRESS,
+        at(5),
+    ));
+    tracker.process(&squitter(
+        &aircraft_status(0, ID13_1200),
+        TEST_ADDRESS,
+        at(6),
+    ));
+    assert_eq!(
+        alerts(&mut events),
+        vec![AlertKind::Emergency(EmergencyState::MinimumFuel)]
+    );
+}
+
+#[test]
+fn emergency_state_and_squawk_in_one_message() {
+    let mut tracker = Tracker::new();
+    let mut events = tracker.subscribe();
+
+    tracker.process(&squitter(
+        &aircraft_status(0, ID13_1200),
+        TEST_ADDRESS,
+        at(0),
+    ));
+    tracker.process(&squitter(
+        &aircraft_status(5, ID13_7500),
+        TEST_ADDRESS,
+        at(1),
+    ));
+    tracker.process(&squitter(
+        &aircraft_status(5, ID13_7500),
+        TEST_ADDRESS,
+        at(2),
+    ));
+    assert_eq!(
+        alerts(&mut events),
+        vec![
+            AlertKind::Squawk(SQUAWK_HIJACK),
+            AlertKind::Emergency(EmergencyState::UnlawfulInterference)
+        ]
+    );
+}
+
+#[test]
+fn ident_alerts_once_while_spi_is_set() {
+    let mut tracker = Tracker::new();
+    let mut events = tracker.subscribe();
+
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+    for t in 1..5 {
+        tracker.process(&identity_reply(5, 5, ID13_1200, TEST_ADDRESS, at(t)));
+    }
+    assert!(tracker.get(TEST_ADDRESS).unwrap().ident);
+    assert_eq!(alerts(&mut events), vec![AlertKind::Ident]);
+
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(5)));
+    assert!(!tracker.get(TEST_ADDRESS).unwrap().ident);
+    tracker.process(&identity_reply(21, 5, ID13_1200, TEST_ADDRESS, at(6)));
+    assert_eq!(alerts(&mut events), vec![AlertKind::Ident]);
+}
+
+#[test]
+fn new_aircraft_then_changed_fields() {
+    let mut tracker = Tracker::new();
+    let mut events = tracker.subscribe();
+
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+    match events.try_recv().unwrap() {
+        TrackerEvent::NewAircraft(ac) => {
+            assert_eq!(ac.address.address, TEST_ADDRESS);
+            assert_eq!(ac.squawk, Some(0x1200));
+        }
+        e => panic!("unexpected {:?}", e),
+    }
+
+    // the same again changes nothing worth an event
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(1)));
+    assert!(events.try_recv().is_err());
+
+    tracker.process(&identity_reply(5, 1, ID13_7500, TEST_ADDRESS, at(2)));
+    match events.try_recv().unwrap() {
+        TrackerEvent::Updated { aircraft, fields } => {
+            assert_eq!(aircraft.squawk, Some(SQUAWK_HIJACK));
+            assert_eq!(fields, vec![Field::OnGround, Field::Squawk]);
+        }
+        e => panic!("unexpected {:?}", e),
+    }
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        TrackerEvent::Emergency(_)
+    ));
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn every_subscriber_sees_every_event() {
+    let mut tracker = Tracker::new();
+    let first = tracker.subscribe();
+    let second = tracker.subscribe();
+
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+    tracker.process(&identity_reply(5, 0, ID13_1200, OTHER_ADDRESS, at(0)));
+
+    for events in &mut [first, second] {
+        let mut new = Vec::new();
+        while let Ok(TrackerEvent::NewAircraft(ac)) = events.try_recv() {
+            new.push(ac.address.address);
+        }
+        assert_eq!(new, vec![TEST_ADDRESS, OTHER_ADDRESS]);
+    }
+}
+
+#[test]
+fn silent_aircraft_expire_with_a_lost_event() {
+    let mut tracker = Tracker::with_config(TrackerConfig {
+        aircraft_timeout: Duration::from_secs(30),
+        ..TrackerConfig::default()
+    });
+
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+    tracker.process(&identity_reply(5, 0, ID13_1200, OTHER_ADDRESS, at(20)));
+    let mut events = tracker.subscribe();
+
+    tracker.expire(at(30));
+    assert!(events.try_recv().is_err());
+    assert!(tracker.get(TEST_ADDRESS).is_some());
+
+    tracker.expire(at(31));
+    match events.try_recv().unwrap() {
+        TrackerEvent::Lost(ac) => assert_eq!(ac.address.address, TEST_ADDRESS),
+        e => panic!("unexpected {:?}", e),
+    }
+    assert!(events.try_recv().is_err());
+    assert!(tracker.get(TEST_ADDRESS).is_none());
+    assert!(tracker.get(OTHER_ADDRESS).is_some());
+}
+
+#[tokio::test]
+async fn silent_aircraft_are_lost_without_further_input() {
+    let tracker = SharedTracker::new(Tracker::with_config(TrackerConfig {
+        aircraft_timeout: Duration::from_millis(100),
+        ..TrackerConfig::default()
+    }));
+    let mut events = tracker.subscribe();
+    let at = SystemTime::now();
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at));
+    tokio::spawn(tracker.clone().expire_every(Duration::from_millis(20)));
+
+    let lost = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let TrackerEvent::Lost(ac) = events.recv().await.unwrap() {
+                return ac;
+            }
+        }
+    })
+    .await
+    .expect("no Lost event");
+    assert_eq!(lost.address.address, TEST_ADDRESS);
+    assert!(at.elapsed().unwrap() >= Duration::from_millis(100));
+    assert!(tracker.snapshot().is_empty());
+}
+
+#[test]
+fn processing_expires_aircraft_as_time_passes() {
+    let mut tracker = Tracker::new();
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+    let mut events = tracker.subscribe();
+
+    let later = at(0) + AIRCRAFT_TIMEOUT + Duration::from_secs(2);
+    tracker.process(&identity_reply(5, 0, ID13_1200, OTHER_ADDRESS, later));
+
+    assert!(matches!(events.try_recv().unwrap(), TrackerEvent::Lost(_)));
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        TrackerEvent::NewAircraft(_)
+    ));
+    assert!(tracker.get(TEST_ADDRESS).is_none());
+}
+
+#[test]
+fn snapshots_keep_the_state_they_were_taken_with() {
+    let mut tracker = Tracker::new();
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+    let before = tracker.snapshot();
+
+    tracker.process(&identity_reply(5, 0, ID13_7700, TEST_ADDRESS, at(1)));
+    tracker.process(&identity_reply(5, 0, ID13_1200, OTHER_ADDRESS, at(1)));
+    let after = tracker.snapshot();
+
+    assert_eq!(before.len(), 1);
+    assert_eq!(before.get(TEST_ADDRESS).unwrap().squawk, Some(0x1200));
+    assert_eq!(before.get(TEST_ADDRESS).unwrap().msg_count, 1);
+    assert!(before.get(OTHER_ADDRESS).is_none());
+
+    assert_eq!(after.len(), 2);
+    assert_eq!(
+        after.get(TEST_ADDRESS).unwrap().squawk,
+        Some(SQUAWK_EMERGENCY)
+    );
+    assert_eq!(after.get(TEST_ADDRESS).unwrap().msg_count, 2);
+
+    // expiring doesn't take aircraft out of existing snapshots either
+    tracker.expire(at(1) + AIRCRAFT_TIMEOUT + Duration::from_secs(1));
+    assert!(tracker.snapshot().is_empty());
+    assert_eq!(after.len(), 2);
+}
+
+#[test]
+fn snapshot_lookup_is_by_icao_address() {
+    let mut tracker = Tracker::new();
+    tracker.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(0)));
+
+    // a TIS-B target with a non-ICAO address (CF 2, IMF set) with the same bits
+    let mut me = [0; 7];
+    me[0] = 11 << 3 | 1;
+    let mut bytes = vec![
+        18 << 3 | 2,
+        (OTHER_ADDRESS >> 16) as u8,
+        (OTHER_ADDRESS >> 8) as u8,
+        OTHER_ADDRESS as u8,
+    ];
+    bytes.extend_from_slice(&me);
+    let parity = crc::modes_crc(&bytes);
+    bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+    tracker.process(&Frame::from_bytes(&bytes, at(0)));
+
+    let snapshot = tracker.snapshot();
+    assert_eq!(snapshot.len(), 2);
+    assert_eq!(
+        snapshot.get(TEST_ADDRESS).unwrap().address.address,
+        TEST_ADDRESS
+    );
+    assert!(snapshot.get(OTHER_ADDRESS).is_none());
+    assert_eq!(
+        snapshot.iter().filter(|ac| !ac.address.is_icao()).count(),
+        1
+    );
+}
+
+#[test]
+fn non_icao_addresses_are_kept_apart_by_family() {
+    let mut tracker = Tracker::new();
+    let mut me = [0; 7];
+    me[0] = 11 << 3;
+
+    // a self-assigned ADS-B address (CF 1) and a TIS-B track file (CF 5) with the same bits
+    for &cf in &[1, 5, 1] {
+        let mut bytes = vec![
+            18 << 3 | cf,
+            (OTHER_ADDRESS >> 16) as u8,
+            (OTHER_ADDRESS >> 8) as u8,
+            OTHER_ADDRESS as u8,
+        ];
+        bytes.extend_from_slice(&me);
+        let parity = crc::modes_crc(&bytes);
+        bytes.extend_from_slice(&[(parity >> 16) as u8, (parity >> 8) as u8, parity as u8]);
+        tracker.process(&Frame::from_bytes(&bytes, at(0)));
+    }
+
+    let snapshot = tracker.snapshot();
+    assert_eq!(snapshot.len(), 2);
+    let mut families: Vec<(AddressFamily, u64)> = snapshot
+        .iter()
+        .map(|ac| (ac.address.family, ac.msg_count))
+        .collect();
+    families.sort_by_key(|&(_, count)| count);
+    assert_eq!(
+        families,
+        vec![
+            (AddressFamily::TisBTrackFile, 1),
+            (AddressFamily::AdsBOther, 2)
+        ]
+    );
+    assert!(snapshot
+        .iter()
+        .all(|ac| ac.address.address == OTHER_ADDRESS));
+    assert!(snapshot.get(OTHER_ADDRESS).is_none());
+}
+
+#[test]
+fn shared_tracker_snapshots_across_threads() {
+    let shared = SharedTracker::new(Tracker::new());
+    let decoder = shared.clone();
+
+    std::thread::spawn(move || {
+        for t in 0..10 {
+            decoder.process(&identity_reply(5, 0, ID13_1200, TEST_ADDRESS, at(t)));
+        }
+    })
+    .join()
+    .unwrap();
+
+    let snapshot = shared.snapshot();
+    assert_eq!(snapshot.get(TEST_ADDRESS).unwrap().msg_count, 10);
+    assert_eq!(shared.get(TEST_ADDRESS).unwrap().msg_count, 10);
+}
+
+#[test]
+fn positions_are_published_once_reliable() {
+    let mut tracker = Tracker::new();
+    let (lat, lon) = (52.2572, 3.9194);
+
+    tracker.process(&squitter(
+        &airborne_position(lat, lon, false),
+        TEST_ADDRESS,
+        at(0),
+    ));
+    tracker.process(&squitter(
+        &airborne_position(lat, lon, true),
+        TEST_ADDRESS,
+        at(1),
+    ));
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.altitude, Some(38000));
+    assert_eq!(ac.position_stats.accepted, 1);
+    assert_eq!(ac.position(), None);
+
+    tracker.process(&squitter(
+        &airborne_position(lat, lon, false),
+        TEST_ADDRESS,
+        at(2),
+    ));
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.position_stats.accepted, 2);
+    assert_near(ac.position(), (lat, lon));
+}
+
+#[test]
+fn nearest_sorts_by_distance_and_skips_what_it_cant_measure() {
+    let mut tracker = Tracker::new();
+    let places = [(TEST_ADDRESS, 52.2572, 3.9194), (OTHER_ADDRESS, 52.0, 4.5)];
+    for &(address, lat, lon) in &places {
+        for t in 0..3 {
+            let me = airborne_position(lat, lon, t % 2 == 1);
+            tracker.process(&squitter(&me, address, at(t)));
+        }
+    }
+
+    let snapshot = tracker.snapshot();
+    let center = Position {
+        latitude: 52.0,
+        longitude: 4.4,
+    };
+    let nearest = snapshot.nearest(&center, 5);
+    assert_eq!(nearest.len(), 2);
+    assert_eq!(nearest[0].1.address.address, OTHER_ADDRESS);
+    assert!(nearest[0].0 < nearest[1].0);
+
+    let nowhere = Position {
+        latitude: f64::NAN,
+        longitude: 4.4,
+    };
+    assert!(snapshot.nearest(&nowhere, 5).is_empty());
+}
+
+#[test]
+fn positions_out_of_range_are_rejected() {
+    let mut tracker = Tracker::with_config(TrackerConfig {
+        receiver: Some(ReceiverLocation::new(47.45, -122.3, None)),
+        max_range: 100.0 * METERS_PER_NM,
+        ..TrackerConfig::default()
+    });
+
+    // about 150 NM north of the receiver
+    for t in 0..4 {
+        let me = airborne_position(49.95, -122.3, t % 2 == 1);
+        tracker.process(&squitter(&me, TEST_ADDRESS, at(t)));
+    }
+
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.position(), None);
+    assert_eq!(ac.position_stats.accepted, 0);
+    // the first frame was decoded relative to the receiver, the rest as pairs
+    assert_eq!(ac.position_stats.rejected_range, 4);
+}
+
+#[test]
+fn positions_implying_impossible_speed_are_rejected() {
+    let mut tracker = Tracker::new();
+    let (lat, lon) = (52.2572, 3.9194);
+
+    for t in 0..4 {
+        let me = airborne_position(lat, lon, t % 2 == 1);
+        tracker.process(&squitter(&me, TEST_ADDRESS, at(t)));
+    }
+    assert_near(tracker.get(TEST_ADDRESS).unwrap().position(), (lat, lon));
+
+    // a degree north 3 s later would be 20000 kt
+    for t in 4..6 {
+        let me = airborne_position(lat + 1.0, lon, t % 2 == 1);
+        tracker.process(&squitter(&me, TEST_ADDRESS, at(t)));
+    }
+
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_near(ac.position(), (lat, lon));
+    assert_eq!(ac.position_stats.accepted, 3);
+    assert!(ac.position_stats.rejected_speed >= 1);
+}
+
+#[test]
+fn pairs_from_different_zones_are_rejected() {
+    let mut tracker = Tracker::new();
+    let boundary = 10.470_471_30;
+
+    tracker.process(&squitter(
+        &airborne_position(boundary - 0.002, 20.0, false),
+        TEST_ADDRESS,
+        at(0),
+    ));
+    tracker.process(&squitter(
+        &airborne_position(boundary + 0.002, 20.0, true),
+        TEST_ADDRESS,
+        at(1),
+    ));
+
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.position_stats.rejected_zone, 1);
+    assert_eq!(ac.position_stats.accepted, 0);
+}
+
+#[test]
+fn single_frames_are_decoded_relative_to_the_receiver() {
+    let receiver = ReceiverLocation::new(47.45, -122.3, None);
+    let mut tracker = Tracker::with_config(TrackerConfig {
+        receiver: Some(receiver),
+        ..TrackerConfig::default()
+    });
+    assert_eq!(DEFAULT_MAX_RANGE, CPR_AIRBORNE_LOCAL_RANGE);
+
+    // about 170 NM south east, only even frames so there's never a pair
+    let (lat, lon) = (45.5, -119.8);
+    for t in 0..2 {
+        tracker.process(&squitter(
+            &airborne_position(lat, lon, false),
+            TEST_ADDRESS,
+            at(t),
+        ));
+    }
+
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.position_stats.accepted, 2);
+    assert_near(ac.position(), (lat, lon));
+    let distance = receiver.relative(&ac).unwrap().distance;
+    assert!(distance > 150.0 * METERS_PER_NM && distance < CPR_AIRBORNE_LOCAL_RANGE);
+}
+
+#[test]
+fn receiver_is_no_reference_beyond_half_a_zone() {
+    let mut tracker = Tracker::with_config(TrackerConfig {
+        receiver: Some(ReceiverLocation::new(47.45, -122.3, None)),
+        max_range: 250.0 * METERS_PER_NM,
+        ..TrackerConfig::default()
+    });
+
+    for t in 0..2 {
+        tracker.process(&squitter(
+            &airborne_position(45.5, -119.8, false),
+            TEST_ADDRESS,
+            at(t),
+        ));
+    }
+
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.position_stats.accepted, 0);
+    assert_eq!(ac.position(), None);
+}
+
+#[test]
+fn single_frames_are_decoded_relative_to_the_last_position() {
+    let mut tracker = Tracker::new();
+    let (lat, lon) = (52.2572, 3.9194);
+
+    for t in 0..3 {
+        let me = airborne_position(lat, lon, t % 2 == 1);
+        tracker.process(&squitter(&me, TEST_ADDRESS, at(t)));
+    }
+
+    // too late to pair with the earlier frames, 40 s at 600 kt later
+    let (lat, lon) = (lat + 0.1, lon + 0.05);
+    tracker.process(&squitter(
+        &airborne_position(lat, lon, true),
+        TEST_ADDRESS,
+        at(62),
+    ));
+
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.position_stats.accepted, 3);
+    assert_near(ac.position(), (lat, lon));
+}
@@ -0,0 +1,272 @@
+use fishfinder::adsb::{SharedTracker, Source, Tracker};
+use fishfinder::sdr::fec::ReedSolomon;
+use fishfinder::sdr::input::{self, Band, RadioSpec};
+use fishfinder::sdr::repair::RepairPolicy;
+use fishfinder::sdr::uat::{self, FrameKind};
+use std::f64::consts::PI;
+use tokio::sync::broadcast;
+use tokio_stream::StreamExt;
+use tokio_util::codec::FramedRead;
+
+const TEST_ADDRESS: u32 = 0xa1b2c3;
+
+/* The aircraft in the 1090 MHz sample recording */
+const MODE_S_RECORDING: &str = "samples/modes1.bin";
+const MODE_S_ADDRESS: u32 = 0x4d2023;
+
+/* Set bits `first..=last` (1-based, MSB first) of a payload */
+fn put(payload: &mut [u8], first: usize, last: usize, value: u32) {
+    for bit in first..=last {
+        let mask = 0x80 >> ((bit - 1) % 8);
+        match (value >> (last - bit)) & 1 {
+            1 => payload[(bit - 1) / 8] |= mask,
+            _ => payload[(bit - 1) / 8] &= !mask,
+        }
+    }
+}
+
+fn base40(c: u8) -> u32 {
+    match c {
+        b'0'..=b'9' => (c - b'0') as u32,
+        b'A'..=b'Z' => (c - b'A') as u32 + 10,
+        _ => 36,
+    }
+}
+
+/* Long ADS-B payload type 1: N825V, light aircraft, climbing north east */
+fn test_payload() -> Vec<u8> {
+    let mut p = vec![0u8; uat::UAT_LONG_ADSB_BYTES];
+    put(&mut p, 1, 5, 1);
+    put(&mut p, 6, 8, 0);
+    put(&mut p, 9, 32, TEST_ADDRESS);
+
+    let lat = (44.90708 * (1 << 24) as f64 / 360.0).round() as u32;
+    let lon = ((360.0 - 122.99488) * (1 << 24) as f64 / 360.0).round() as u32;
+    put(&mut p, 33, 55, lat);
+    put(&mut p, 56, 79, lon);
+    put(&mut p, 81, 92, (5000 + 1000) / 25 + 1);
+    put(&mut p, 93, 96, 8);
+    put(&mut p, 100, 110, 101);
+    put(&mut p, 111, 121, 101);
+    put(&mut p, 124, 132, 11);
+
+    let category = 1;
+    let cs: Vec<u32> = b"N825V   ".iter().map(|&c| base40(c)).collect();
+    put(&mut p, 137, 152, category * 1600 + cs[0] * 40 + cs[1]);
+    put(&mut p, 153, 168, cs[2] * 1600 + cs[3] * 40 + cs[4]);
+    put(&mut p, 169, 184, cs[5] * 1600 + cs[6] * 40 + cs[7]);
+    put(&mut p, 191, 192, 3);
+    put(&mut p, 201, 204, 9);
+    put(&mut p, 205, 207, 2);
+    put(&mut p, 215, 215, 1);
+
+    put(&mut p, 233, 244, (5200 + 1000) / 25 + 1);
+    p
+}
+
+fn to_bits(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|b| (0..8).map(move |i| b >> (7 - i) & 1 == 1))
+        .collect()
+}
+
+/* CPFSK at 2 samples per bit with a modulation index of 0.6, after some
+ * noise and with the receiver `offset` Hz off frequency */
+fn modulate(sync: u64, coded: &[u8], offset: f64) -> Vec<u8> {
+    let mut bits: Vec<bool> = (0..uat::UAT_SYNC_BITS)
+        .map(|i| sync >> (uat::UAT_SYNC_BITS - 1 - i) & 1 == 1)
+        .collect();
+    bits.extend(to_bits(coded));
+
+    let mut iq = Vec::new();
+    let mut seed = 12345u32;
+    for _ in 0..301 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        iq.push((seed >> 16) as u8);
+        iq.push((seed >> 8) as u8);
+    }
+
+    let deviation = 0.6 * PI / 2.0;
+    let drift = 2.0 * PI * offset / uat::UAT_SAMPLE_RATE as f64;
+    let mut phase = 0.0f64;
+    for bit in bits.iter().chain([false; 64].iter()) {
+        for _ in 0..uat::UAT_SAMPLES_PER_BIT {
+            iq.push((127.5 + 100.0 * phase.cos()) as u8);
+            iq.push((127.5 + 100.0 * phase.sin()) as u8);
+            phase += if *bit { deviation } else { -deviation } + drift;
+        }
+    }
+    iq
+}
+
+fn encode_adsb(payload: &[u8]) -> Vec<u8> {
+    let mut coded = payload.to_vec();
+    coded.extend(ReedSolomon::new(uat::UAT_LONG_ADSB_PARITY).encode(payload));
+    coded
+}
+
+async fn decode(iq: Vec<u8>) -> Vec<uat::Frame> {
+    FramedRead::new(&iq[..], uat::FrameDecoder::new())
+        .map(|f| f.unwrap())
+        .collect()
+        .await
+}
+
+#[test]
+fn reed_solomon_corrects_up_to_half_the_parity() {
+    let rs = ReedSolomon::new(uat::UAT_UPLINK_BLOCK_PARITY);
+    let data: Vec<u8> = (0..uat::UAT_UPLINK_BLOCK_BYTES as u8)
+        .map(|i| i.wrapping_mul(37))
+        .collect();
+    let mut codeword = data.clone();
+    codeword.extend(rs.encode(&data));
+
+    let mut clean = codeword.clone();
+    assert_eq!(rs.decode(&mut clean), Some(0));
+
+    for (n, &i) in [0, 5, 17, 30, 44, 60, 71, 72, 80, 91].iter().enumerate() {
+        codeword[i] ^= 0x5a + n as u8;
+    }
+    assert_eq!(rs.decode(&mut codeword), Some(10));
+    assert_eq!(&codeword[..data.len()], &data[..]);
+}
+
+#[tokio::test]
+async fn long_adsb_frame_with_errors_and_frequency_offset() {
+    let payload = test_payload();
+    let mut coded = encode_adsb(&payload);
+    coded[3] ^= 0xff;
+    coded[40] ^= 0x01;
+
+    let frames = decode(modulate(uat::UAT_ADSB_SYNC, &coded, 20_000.0)).await;
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].kind(), FrameKind::LongAdsb);
+    assert_eq!(frames[0].bytes(), &payload[..]);
+    assert_eq!(frames[0].corrected(), 2);
+}
+
+#[tokio::test]
+async fn uplink_frame_is_deinterleaved() {
+    let rs = ReedSolomon::new(uat::UAT_UPLINK_BLOCK_PARITY);
+    let payload: Vec<u8> = (0..uat::UAT_UPLINK_BYTES)
+        .map(|i| (i * 7 + 3) as u8)
+        .collect();
+
+    let blocks: Vec<Vec<u8>> = payload
+        .chunks(uat::UAT_UPLINK_BLOCK_BYTES)
+        .map(|block| {
+            let mut codeword = block.to_vec();
+            codeword.extend(rs.encode(block));
+            codeword
+        })
+        .collect();
+    let mut coded = Vec::new();
+    for i in 0..blocks[0].len() {
+        for block in &blocks {
+            coded.push(block[i]);
+        }
+    }
+    // a burst, spread over all blocks by the interleaving
+    for b in &mut coded[100..130] {
+        *b = !*b;
+    }
+
+    let frames = decode(modulate(uat::UAT_UPLINK_SYNC, &coded, -10_000.0)).await;
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].kind(), FrameKind::Uplink);
+    assert!(!frames[0].is_adsb());
+    assert_eq!(frames[0].bytes(), &payload[..]);
+    assert_eq!(frames[0].corrected(), 30);
+}
+
+#[tokio::test]
+async fn adsb_payload_updates_the_tracker() {
+    let mut iq = modulate(uat::UAT_ADSB_SYNC, &encode_adsb(&test_payload()), 0.0);
+    iq.extend(iq.clone());
+
+    let tracker = SharedTracker::new(Tracker::new());
+    for frame in decode(iq).await {
+        tracker.process_uat(&frame);
+    }
+
+    let ac = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(ac.msg_count, 2);
+    assert_eq!(ac.callsign.as_deref(), Some("N825V"));
+    assert_eq!(ac.emitter_category, Some(0xa1));
+    assert_eq!(ac.altitude, Some(5000));
+    assert_eq!(ac.alt_is_gnss, Some(false));
+    assert_eq!(ac.alt_gnss_baro_diff, Some(200));
+    assert_eq!(ac.on_ground, Some(false));
+    assert_eq!(ac.vertical_rate, Some(640));
+    assert!((ac.ground_speed.unwrap() - 141.42).abs() < 0.01);
+    assert!((ac.track.unwrap() - 45.0).abs() < 0.01);
+    assert_eq!(ac.integrity.nic, Some(8));
+    assert_eq!(ac.integrity.nac_p, Some(9));
+
+    let position = ac.position().unwrap();
+    assert!((position.latitude - 44.90708).abs() < 1e-4);
+    assert!((position.longitude + 122.99488).abs() < 1e-4);
+}
+
+#[test]
+fn radio_spec_has_band_serial_and_ppm() {
+    let spec: RadioSpec = "978:00000978:-2".parse().unwrap();
+    assert_eq!(spec.band, Band::Uat);
+    assert_eq!(spec.serial.as_deref(), Some("00000978"));
+    assert_eq!(spec.ppm, -2);
+
+    let spec: RadioSpec = "1090".parse().unwrap();
+    assert_eq!(spec.band, Band::ModeS);
+    assert_eq!(spec.serial, None);
+    assert!("1030:1".parse::<RadioSpec>().is_err());
+}
+
+#[tokio::test]
+async fn recordings_of_both_bands_merge_into_one_tracker() {
+    // the 1090 MHz aircraft heard on UAT as well, and a TIS-B target
+    let mut payload = test_payload();
+    put(&mut payload, 9, 32, MODE_S_ADDRESS);
+    let mut iq = modulate(uat::UAT_ADSB_SYNC, &encode_adsb(&payload), 0.0);
+    let mut payload = test_payload();
+    put(&mut payload, 6, 8, 2);
+    iq.extend(modulate(uat::UAT_ADSB_SYNC, &encode_adsb(&payload), 0.0));
+
+    let uat_recording = std::env::temp_dir().join("fishfinder-merge-uat.bin");
+    std::fs::write(&uat_recording, &iq).unwrap();
+
+    let tracker = SharedTracker::new(Tracker::new());
+    let (uplinks, _) = broadcast::channel(1);
+    let mode_s_src = tokio::fs::File::open(MODE_S_RECORDING).await.unwrap();
+    let uat_src = tokio::fs::File::open(&uat_recording).await.unwrap();
+    let (mode_s_frames, uat_frames) = tokio::join!(
+        input::run(
+            mode_s_src,
+            Band::ModeS,
+            tracker.clone(),
+            RepairPolicy::SingleBit,
+            uplinks.clone(),
+        ),
+        input::run(
+            uat_src,
+            Band::Uat,
+            tracker.clone(),
+            RepairPolicy::SingleBit,
+            uplinks,
+        ),
+    );
+    std::fs::remove_file(&uat_recording).unwrap();
+
+    assert!(mode_s_frames > 0);
+    assert_eq!(uat_frames, 2);
+    assert_eq!(tracker.snapshot().len(), 2);
+
+    let ac = tracker.get(MODE_S_ADDRESS).unwrap();
+    assert_eq!(ac.sources.len(), 2);
+    assert!(ac.sources.contains(&Source::Es1090));
+    assert!(ac.sources.contains(&Source::Uat));
+
+    let target = tracker.get(TEST_ADDRESS).unwrap();
+    assert_eq!(target.source, Source::TisB);
+    assert_eq!(target.sources, vec![Source::TisB]);
+}